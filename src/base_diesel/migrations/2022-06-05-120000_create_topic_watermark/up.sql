@@ -0,0 +1,10 @@
+CREATE TABLE topic_watermark (
+    id SERIAL PRIMARY KEY,
+    topic_id INTEGER REFERENCES topic (id) NOT NULL,
+    flow_step_id INTEGER REFERENCES flow_step (id) NOT NULL,
+    newest_id VARCHAR(64) NOT NULL,
+    newest_dt TIMESTAMP WITHOUT TIME ZONE,
+    created_dt TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_dt TIMESTAMP WITHOUT TIME ZONE,
+    UNIQUE (topic_id, flow_step_id)
+);
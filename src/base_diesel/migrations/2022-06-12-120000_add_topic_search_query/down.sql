@@ -0,0 +1 @@
+ALTER TABLE topic DROP COLUMN search_query;
@@ -0,0 +1,2 @@
+-- structured query (ct_nlp SearchQuery json), rendered in place of search_text when set
+ALTER TABLE topic ADD COLUMN search_query TEXT;
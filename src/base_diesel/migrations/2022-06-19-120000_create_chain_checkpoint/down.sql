@@ -0,0 +1,2 @@
+DROP TABLE landing_catalog;
+DROP TABLE chain_checkpoint;
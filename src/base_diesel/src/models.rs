@@ -15,6 +15,8 @@ pub struct Topic {
     pub work_dir: Option<String>,
     pub created_dt: SystemTime,
     pub updated_dt: Option<SystemTime>,
+    /// structured query (ct_nlp SearchQuery json), rendered in place of search_text when set
+    pub search_query: Option<String>,
 }
 
 #[derive(Queryable, Identifiable, AsChangeset, Debug, PartialEq)]
@@ -72,6 +74,51 @@ pub struct JobStep {
     pub updated_dt: Option<SystemTime>,
 }
 
+/// Newest tweet landed for a topic by a flow step
+#[derive(Queryable, Identifiable, AsChangeset, Debug, PartialEq)]
+#[table_name = "topic_watermark"]
+pub struct TopicWatermark {
+    pub id: i32,
+    pub topic_id: i32,
+    pub flow_step_id: i32,
+    pub newest_id: String,
+    pub newest_dt: Option<SystemTime>,
+    pub created_dt: SystemTime,
+    pub updated_dt: Option<SystemTime>,
+}
+
+/// Last finalized block an on-chain landing step landed for a contract
+#[derive(Queryable, Identifiable, Debug, PartialEq)]
+#[table_name = "chain_checkpoint"]
+pub struct ChainCheckpoint {
+    pub id: i32,
+    pub topic_id: i32,
+    pub flow_step_id: i32,
+    pub contract_address: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub created_dt: SystemTime,
+}
+
+/// A file landed by a job step, block range set for on-chain landings
+#[derive(Queryable, Identifiable, AsChangeset, Debug, PartialEq)]
+#[table_name = "landing_catalog"]
+pub struct LandingCatalog {
+    pub id: i32,
+    pub topic_id: i32,
+    pub flow_step_id: i32,
+    pub job_step_id: i32,
+    pub file_path: String,
+    pub row_count: i64,
+    pub contract_address: Option<String>,
+    pub from_block: Option<i64>,
+    pub to_block: Option<i64>,
+    pub is_valid: bool,
+    pub invalid_reason: Option<String>,
+    pub created_dt: SystemTime,
+    pub updated_dt: Option<SystemTime>,
+}
+
 #[derive(Deserialize, Insertable)]
 #[table_name = "topic"]
 pub struct TopicForm<'a> {
@@ -84,6 +131,8 @@ pub struct TopicForm<'a> {
     work_dir: Option<&'a str>,
     created_dt: SystemTime,
     updated_dt: Option<SystemTime>,
+    #[serde(default)]
+    search_query: Option<&'a str>,
 }
 
 #[derive(Deserialize, Insertable)]
@@ -135,3 +184,41 @@ pub struct JobStepForm<'a> {
     created_dt: SystemTime,
     updated_dt: Option<SystemTime>,
 }
+
+#[derive(Deserialize, Insertable)]
+#[table_name = "topic_watermark"]
+pub struct TopicWatermarkForm<'a> {
+    pub topic_id: i32,
+    pub flow_step_id: i32,
+    pub newest_id: &'a str,
+    pub newest_dt: Option<SystemTime>,
+    pub created_dt: SystemTime,
+    pub updated_dt: Option<SystemTime>,
+}
+
+#[derive(Deserialize, Insertable)]
+#[table_name = "chain_checkpoint"]
+pub struct ChainCheckpointForm<'a> {
+    pub topic_id: i32,
+    pub flow_step_id: i32,
+    pub contract_address: &'a str,
+    pub block_number: i64,
+    pub block_hash: &'a str,
+    pub created_dt: SystemTime,
+}
+
+#[derive(Deserialize, Insertable)]
+#[table_name = "landing_catalog"]
+pub struct LandingCatalogForm<'a> {
+    pub topic_id: i32,
+    pub flow_step_id: i32,
+    pub job_step_id: i32,
+    pub file_path: &'a str,
+    pub row_count: i64,
+    pub contract_address: Option<&'a str>,
+    pub from_block: Option<i64>,
+    pub to_block: Option<i64>,
+    pub is_valid: bool,
+    pub created_dt: SystemTime,
+    pub updated_dt: Option<SystemTime>,
+}
@@ -1,3 +1,15 @@
+table! {
+    chain_checkpoint (id) {
+        id -> Int4,
+        topic_id -> Int4,
+        flow_step_id -> Int4,
+        contract_address -> Varchar,
+        block_number -> Int8,
+        block_hash -> Varchar,
+        created_dt -> Timestamp,
+    }
+}
+
 table! {
     flow (id) {
         id -> Int4,
@@ -53,6 +65,24 @@ table! {
     }
 }
 
+table! {
+    landing_catalog (id) {
+        id -> Int4,
+        topic_id -> Int4,
+        flow_step_id -> Int4,
+        job_step_id -> Int4,
+        file_path -> Varchar,
+        row_count -> Int8,
+        contract_address -> Nullable<Varchar>,
+        from_block -> Nullable<Int8>,
+        to_block -> Nullable<Int8>,
+        is_valid -> Bool,
+        invalid_reason -> Nullable<Varchar>,
+        created_dt -> Timestamp,
+        updated_dt -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     topic (id) {
         id -> Int4,
@@ -65,18 +95,41 @@ table! {
         work_dir -> Nullable<Varchar>,
         created_dt -> Timestamp,
         updated_dt -> Nullable<Timestamp>,
+        search_query -> Nullable<Text>,
+    }
+}
+
+table! {
+    topic_watermark (id) {
+        id -> Int4,
+        topic_id -> Int4,
+        flow_step_id -> Int4,
+        newest_id -> Varchar,
+        newest_dt -> Nullable<Timestamp>,
+        created_dt -> Timestamp,
+        updated_dt -> Nullable<Timestamp>,
     }
 }
 
+joinable!(chain_checkpoint -> topic (topic_id));
+joinable!(chain_checkpoint -> flow_step (flow_step_id));
 joinable!(flow -> topic (id));
 joinable!(flow_step -> flow (id));
 joinable!(job -> flow (id));
 joinable!(job_step -> job (id));
+joinable!(landing_catalog -> topic (topic_id));
+joinable!(landing_catalog -> flow_step (flow_step_id));
+joinable!(landing_catalog -> job_step (job_step_id));
+joinable!(topic_watermark -> topic (topic_id));
+joinable!(topic_watermark -> flow_step (flow_step_id));
 
 allow_tables_to_appear_in_same_query!(
+    chain_checkpoint,
     flow,
     flow_step,
     job,
     job_step,
+    landing_catalog,
     topic,
+    topic_watermark,
 );
@@ -0,0 +1,421 @@
+use conf::{parse_args1, init_logger, get_config, apply_script_options};
+use ct_nlp::{RawSink, raw_path};
+use evm::{check_address, Checkpoint, CheckpointPolicy, REORG_LOOKBACK};
+use source::{check_schema, EvmSource, Source, SourceContext, SourceError};
+
+use diesel::{
+    query_dsl::{QueryDsl, RunQueryDsl},
+    expression::dsl::now,
+    Connection,
+    ExpressionMethods,
+    PgConnection,
+};
+
+use base_diesel::{
+    models::{ChainCheckpoint, ChainCheckpointForm, JobStep, LandingCatalogForm},
+    schema::{chain_checkpoint, flow_step, landing_catalog},
+    schema::{
+        topic::dsl::topic,
+        topic::id as topic_id,
+        topic::search_text,
+        topic::landing_dir,
+    },
+    schema::{
+        job_step::dsl::*,
+        job_step::id,
+        job_step::status,
+        job_step::updated_dt,
+    },
+    get_conn,
+};
+
+use std::{
+    collections::BTreeMap,
+    result::Result,
+    path::Path,
+    fs::File,
+    sync::Arc,
+    time::SystemTime,
+};
+
+use log::info;
+use clap::ArgMatches;
+use chrono::Utc;
+use polars::prelude::*;
+
+#[allow(dead_code)]
+fn usage() {
+    println!("Usage: cargo run
+    --bin chain_land
+    --
+    --job_step_id <job>
+    --config <config>
+    --topic_id <topic>
+    --output_dir <output_dir>");
+}
+
+/// Record the job_step status matching a source error kind
+fn update_step_status(conn: &PgConnection, js_id: i32, err: &SourceError) {
+    let step_status = err.step_status();
+    match diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq(step_status),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn)
+    {
+        Ok(_) => info!("update_step_status|job_step_id={} set to status={}|{}", js_id, step_status, err),
+        Err(db_err) => info!("update_step_status|ERR: failed to update db for job_step_id={}|e={}", js_id, db_err),
+    }
+}
+
+/// Newest checkpoints for the contract, newest first
+fn load_checkpoints(
+    conn: &PgConnection,
+    t_id: i32,
+    fs_id: i32,
+    address: &str,
+) -> Result<Vec<Checkpoint>, diesel::result::Error> {
+    let rows = chain_checkpoint::table
+        .filter(chain_checkpoint::topic_id.eq(t_id))
+        .filter(chain_checkpoint::flow_step_id.eq(fs_id))
+        .filter(chain_checkpoint::contract_address.eq(address))
+        .order(chain_checkpoint::block_number.desc())
+        .limit(REORG_LOOKBACK as i64)
+        .load::<ChainCheckpoint>(conn)?;
+
+    Ok(rows.into_iter()
+        .map(|x| Checkpoint { number: x.block_number as u64, hash: x.block_hash })
+        .collect())
+}
+
+/// Drop the checkpoints a reorg replaced and mark the files landed after the canonical one invalid
+/// Their blocks are re-landed by this run
+fn supersede(
+    conn: &PgConnection,
+    t_id: i32,
+    fs_id: i32,
+    address: &str,
+    superseded: &Checkpoint,
+    canonical: &Checkpoint,
+) -> Result<(usize, usize), diesel::result::Error> {
+    let reason = format!("reorg|superseded block {} {}|canonical block {}",
+        superseded.number, superseded.hash, canonical.number);
+    let canonical = canonical.number as i64;
+
+    conn.transaction(|| {
+        let files = diesel::update(landing_catalog::table)
+            .filter(landing_catalog::topic_id.eq(t_id))
+            .filter(landing_catalog::flow_step_id.eq(fs_id))
+            .filter(landing_catalog::contract_address.eq(address))
+            .filter(landing_catalog::is_valid.eq(true))
+            .filter(landing_catalog::to_block.gt(canonical))
+            .set((
+                landing_catalog::is_valid.eq(false),
+                landing_catalog::invalid_reason.eq(&reason),
+                landing_catalog::updated_dt.eq(now),
+            ))
+            .execute(conn)?;
+
+        let checkpoints = diesel::delete(chain_checkpoint::table)
+            .filter(chain_checkpoint::topic_id.eq(t_id))
+            .filter(chain_checkpoint::flow_step_id.eq(fs_id))
+            .filter(chain_checkpoint::contract_address.eq(address))
+            .filter(chain_checkpoint::block_number.gt(canonical))
+            .execute(conn)?;
+
+        Ok((files, checkpoints))
+    })
+}
+
+/// Catalog the landed file, if there is one, and checkpoint the last block of the run
+#[allow(clippy::too_many_arguments)]
+fn record_run(
+    conn: &PgConnection,
+    t_id: i32,
+    fs_id: i32,
+    js_id: i32,
+    address: &str,
+    landed: Option<(&str, usize)>,
+    from: u64,
+    end: &Checkpoint,
+) -> Result<(), diesel::result::Error> {
+    conn.transaction(|| {
+        if let Some((file_path, rows)) = landed {
+            let form = LandingCatalogForm {
+                topic_id: t_id,
+                flow_step_id: fs_id,
+                job_step_id: js_id,
+                file_path,
+                row_count: rows as i64,
+                contract_address: Some(address),
+                from_block: Some(from as i64),
+                to_block: Some(end.number as i64),
+                is_valid: true,
+                created_dt: SystemTime::now(),
+                updated_dt: None,
+            };
+            diesel::insert_into(landing_catalog::table)
+                .values(&form)
+                .execute(conn)?;
+        }
+
+        let form = ChainCheckpointForm {
+            topic_id: t_id,
+            flow_step_id: fs_id,
+            contract_address: address,
+            block_number: end.number as i64,
+            block_hash: &end.hash,
+            created_dt: SystemTime::now(),
+        };
+        diesel::insert_into(chain_checkpoint::table)
+            .values(&form)
+            .on_conflict((
+                chain_checkpoint::topic_id,
+                chain_checkpoint::flow_step_id,
+                chain_checkpoint::contract_address,
+                chain_checkpoint::block_number,
+            ))
+            .do_update()
+            .set(chain_checkpoint::block_hash.eq(&end.hash))
+            .execute(conn)?;
+
+        Ok(())
+    })
+}
+
+/// The raw copy is part of the landing, without it the step has not landed and the checkpoint stays put
+fn finish_raw(conn: &PgConnection, js_id: i32, raw: &RawSink) -> Result<(), SourceError> {
+    match raw.finish() {
+        Ok(pages) => {
+            info!("main|raw pages landed|pages={}", pages);
+            Ok(())
+        },
+        Err(err) => {
+            info!("main|ERR: unable to finish raw landing|e={}", err);
+            let err = SourceError::from(err);
+            update_step_status(conn, js_id, &err);
+            Err(err)
+        },
+    }
+}
+
+fn complete(conn: &PgConnection, js_id: i32) {
+    let result = diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq("C"),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn);
+
+    match result {
+        Ok(_) => info!("main|chain_land completed for job_step_id={}", js_id),
+        Err(err) => info!("main|ERR: failed to update db for job_step_id={}|e={}", js_id, err),
+    }
+}
+
+/// Lands NFT transfers for the topic's contract (search_text) from the last checkpoint to the finalized block,
+/// or the events of an ABI when the flow step's script_parameters carry --abi (and optionally --events)
+/// conf [evm_confirmations] / [evm_start_block] / [evm_max_blocks_per_run] bound the run, see evm::CheckpointPolicy
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_args1();
+    let config_name = cli_args.value_of("conf").expect("ERR: cli [configuration] is invalid");
+    let output_dir = cli_args.value_of("output").expect("ERR: cli [output_dir] is invalid");
+    let t_id = cli_args.value_of("topic").expect("ERR: cli [topic_id] is invalid")
+        .parse::<i32>().expect("ERR: topic_id <i32> parse failed");
+    let js_id = cli_args.value_of("job_step").expect("ERR: cli [job_step_id] is invalid")
+        .parse::<i32>().expect("ERR: job_step_id <i32> parse failed");
+
+    let mut config: BTreeMap<String, String> = get_config(config_name);
+
+    let dt = Utc::now().to_rfc3339();
+    let log_dir = String::from(config.get("log_dir").expect("ERR: log_dir is invalid"));
+    let log_path = format!("{}/{}_chain_land.log", &log_dir, &dt[0..19]);
+
+    init_logger(&log_path);
+    info!("main|starting");
+    info!("main|topic_id={}", t_id);
+    info!("main|job_step_id={}", js_id);
+
+    let conn = match get_conn(
+        config.get("pg_db").expect("ERR: conf [pg_db] is invalid"),
+        config.get("pg_user").expect("ERR: conf [pg_user] is invalid"),
+        config.get("pg_secret").expect("ERR: conf [pg_secret] is invalid"),
+        config.get("pg_host").expect("ERR: conf [pg_host] is invalid"),
+        config.get("pg_port").expect("ERR: conf [pg_port] is invalid"),
+    ) {
+        Ok(connection) => {
+            info!("main|conn established");
+            connection
+        },
+        Err(err) => {
+            panic!("main|ERR: failed to connect to db|err={}", err);
+        }
+    };
+
+    let topics = topic
+        .filter(topic_id.eq(t_id))
+        .select((search_text, landing_dir))
+        .limit(1)
+        .load::<(String, Option<String>)>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: topic not found for topic_id={}", t_id));
+
+    if topics.is_empty() {
+        panic!("main|ERR: topic not found for topic_id={}", t_id);
+    }
+
+    let fs_id = job_step
+        .filter(id.eq(js_id))
+        .select(flow_step_id)
+        .first::<i32>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: job_step not found for job_step_id={}", js_id));
+
+    // --abi <path> / --events <names> on the flow step land the ABI's events instead of NFT transfers
+    let script_params = flow_step::table
+        .find(fs_id)
+        .select(flow_step::script_parameters)
+        .first::<Option<String>>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: flow_step not found for flow_step_id={}", fs_id));
+    apply_script_options(&mut config, script_params.as_deref().unwrap_or_default());
+
+    let address = match check_address(&topics[0].0) {
+        Ok(x) => x,
+        Err(err) => {
+            let err = SourceError::from(err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    info!("main|contract_address={}", address);
+
+    // untouched responses land next to the parquet, keyed by job_step_id
+    let raw_dir = match topics[0].1.as_deref() {
+        Some(x) if !x.is_empty() => x,
+        _ => output_dir,
+    };
+    let raw = Arc::new(RawSink::create(&raw_path(raw_dir, js_id, "chain_land", &dt[0..19]))?);
+
+    let context = SourceContext {
+        config: &config,
+        raw: Some(Arc::clone(&raw)),
+    };
+    let prepared = match config.contains_key("evm_abi") {
+        true => EvmSource::events_from_context(&context),
+        false => EvmSource::from_context(&context),
+    };
+    let prepared = prepared
+        .and_then(|x| Ok((x, CheckpointPolicy::from_config(&config)?)));
+    let (source, policy) = match prepared {
+        Ok(x) => x,
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    info!("main|policy={:?}", policy);
+
+    let checkpoints = load_checkpoints(&conn, t_id, fs_id, &address)?;
+    info!("main|checkpoint={:?}", checkpoints.first());
+
+    let plan = match source.client().plan_range(&checkpoints, &policy).await {
+        Ok(x) => x,
+        Err(err) => {
+            let err = SourceError::from(err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+
+    // plan_range only reports a reorg below a canonical checkpoint, never one that empties the history
+    if let (Some(superseded), Some(canonical)) = (plan.superseded.first(), &plan.canonical) {
+        let (files, dropped) = supersede(&conn, t_id, fs_id, &address, superseded, canonical)?;
+        info!("main|reorg|canonical={}|checkpoints dropped={}|files marked invalid={}|re-landing from block {}",
+            canonical.number, dropped, files, plan.from);
+    }
+
+    let (from, end) = match (plan.range(), &plan.end) {
+        (Some((from, _)), Some(block)) => (from, Checkpoint::from_block(block)?),
+        _ => {
+            info!("main|nothing to land|no finalized blocks after {}", plan.from);
+            finish_raw(&conn, js_id, &raw)?;
+            complete(&conn, js_id);
+            return Ok(());
+        },
+    };
+    info!("main|blocks={}..={}", from, end.number);
+
+    let batches = match source.blocks(&address, from, end.number).await {
+        Ok(x) => x,
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+
+    finish_raw(&conn, js_id, &raw)?;
+
+    let schema = source.schema();
+    let mut stacked: Option<DataFrame> = None;
+    for batch in batches {
+        if let Err(err) = check_schema(&schema, &batch) {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        }
+        stacked = match stacked {
+            Some(df) => Some(df.vstack(&batch)?),
+            None => Some(batch),
+        };
+    }
+
+    // one file per run covering from..=end, a run without decoded logs only moves the checkpoint
+    let out_path = format!("{}/{}_chain_land_{}_{}.parquet", output_dir, &dt[0..19], from, end.number);
+    let landed = match stacked {
+        None => None,
+        Some(mut out_df) => {
+            let rows = out_df.height();
+            match Path::new(&output_dir).exists() {
+                true => info!("main|output_dir={}", output_dir),
+                false => {
+                    std::fs::create_dir_all(output_dir)?;
+                    info!("main|{} created successfully", output_dir);
+                },
+            }
+
+            let written = File::create(&out_path)
+                .map_err(|e| e.to_string())
+                .and_then(|x| ParquetWriter::new(x).finish(&mut out_df).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                info!("main|ERR: unable to write to file|out_path={}|e={}", out_path, e);
+                let _ = std::fs::remove_file(&out_path);
+                let err = SourceError::Parse(format!("unable to write {}|{}", out_path, e));
+                update_step_status(&conn, js_id, &err);
+                return Err(err.into());
+            }
+            info!("main|{} created successfully|rows={}", out_path, rows);
+            Some((out_path.as_str(), rows))
+        },
+    };
+
+    // checkpoint only moves once the file is on disk, a file the catalog does not know about
+    // would be landed again by the next run, so it goes and the step fails
+    if let Err(e) = record_run(&conn, t_id, fs_id, js_id, &address, landed, from, &end) {
+        info!("main|ERR: failed to record run|e={}", e);
+        if landed.is_some() {
+            match std::fs::remove_file(&out_path) {
+                Ok(_) => info!("main|{} removed|not in landing_catalog", out_path),
+                Err(e) => info!("main|ERR: unable to remove {}|e={}", out_path, e),
+            }
+        }
+        let err = SourceError::Parse(format!("unable to record blocks {}..={} in landing_catalog / chain_checkpoint|{}", from, end.number, e));
+        update_step_status(&conn, js_id, &err);
+        return Err(err.into());
+    }
+    info!("main|checkpoint advanced|block={}|hash={}", end.number, end.hash);
+
+    complete(&conn, js_id);
+    info!("main|completed");
+    Ok(())
+}
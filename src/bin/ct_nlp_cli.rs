@@ -1,10 +1,4 @@
-use ct_nlp::{
-    users_lookup,
-    mentions_timeline,
-    user_timeline, 
-    get_recent_tweets, 
-    get_tweet_counts, 
-    tweet_lookup};
+use ct_nlp::{TwitterClient, PageLimit, QueryOptions, Granularity, SearchScope, SearchQuery, Relation};
 use conf::{parse_args, get_config, init_logger};
 
 use chrono::Utc;
@@ -14,7 +8,25 @@ use std::collections::BTreeMap;
 use std::result::Result;
 
 fn usage() {
-    println!("Usage: cargo run --bin ct_nlp_cli  -- --topic <topic> --config <config> --action <action>");
+    println!("Usage: cargo run --bin ct_nlp_cli  -- --topic <topic> --config <config> --action <action>
+    [--start_time <RFC3339>] [--end_time <RFC3339>] [--since_id <id>] [--until_id <id>]
+    [--granularity <minute|hour|day>] [--scope <recent|all>]
+    [--file <ids, usernames or topics, one per line>] (tweet_lookup_batch, users_lookup_batch, recent_batch)
+    [--user_id <id>] (followers, following) [--tweet_id <id>] (liking_users, retweeted_by, quote_tweets)
+    [--tweet_id <id> | --file <ids, one per line>] (threads)
+    [--file <search_query json>] [--scope <recent|all>] (query)");
+}
+
+/// Utility fn to read one id / username per line, blanks and # comments are skipped
+fn read_lines(path: &str) -> Result<Vec<String>, std::io::Error> {
+    let lines = std::fs::read_to_string(path)?
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(String::from)
+        .collect();
+
+    Ok(lines)
 }
 
 #[tokio::main]
@@ -30,13 +42,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     init_logger(&log_path);
     info!("main|starting");
 
+    let client = TwitterClient::from_config(&config)?;
+    let options = QueryOptions {
+        start_time: cli_args.value_of("start_time").map(String::from),
+        end_time: cli_args.value_of("end_time").map(String::from),
+        since_id: cli_args.value_of("since_id").map(String::from),
+        until_id: cli_args.value_of("until_id").map(String::from),
+    };
+
     let cmd = String::from(cli_args.value_of("action").expect("ERR: cli [action] is invalid"));
     match cmd.as_str() {
         "recent" => { 
-            let df = get_recent_tweets(
-                config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+            let df = client.get_recent_tweets(
                 cli_args.value_of("topic").expect("ERR: cli [topic] is invalid"),
-                "100"
+                &options,
+                PageLimit::pages(1),
             ).await?;
 
             let id_col: Vec<&str> = df.column("tweet_id")?
@@ -59,25 +79,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             info!("main|recent|completed");
         },
         "counts" => { 
-            let result = get_tweet_counts(
-                config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+            let granularity: Granularity = cli_args.value_of("granularity").unwrap_or("day").parse()?;
+            let scope: SearchScope = cli_args.value_of("scope").unwrap_or("recent").parse()?;
+            let result = client.get_tweet_counts(
                 cli_args.value_of("topic").expect("ERR: cli [topic] is invalid"),
+                granularity,
+                scope,
+                &options,
+                PageLimit::pages(1),
             ).await;    
 
             match result {
-                Ok(count) => {
-                    let data = match count["data"].as_array() {
-                        Some(x) => x,
-                        _ => return Err("get_tweet_counts|ERR: unable to parse data object".into()),
-                    };
+                Ok(df) => {
+                    let start_col: Vec<&str> = df.column("start")?
+                        .utf8()?
+                        .into_no_null_iter()
+                        .collect();
+                    let count_col: Vec<u64> = df.column("tweet_count")?
+                        .u64()?
+                        .into_no_null_iter()
+                        .collect();
 
                     println!();
-                    for row in data {
-                        let start_dt = row["start"].to_string();
-                        match row["tweet_count"].as_u64() {
-                            Some(x) => { println!("{}|count={}", &start_dt[1..start_dt.len()-1], x); },
-                            None => { continue; },
-                        };
+                    for i in 0..start_col.len() {
+                        println!("{}|count={}", start_col[i], count_col[i]);
                     }
                     println!();
                 },
@@ -85,55 +110,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "tweet_lookup" => {
-            let result = tweet_lookup(
-                config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+            let result = client.tweet_lookup(
                 cli_args.value_of("tweet_id").expect("ERR: cli [tweet_id] is invalid"),
             ).await;
 
             match result {
-                Ok(tweet) => { 
-                    let data = match tweet["data"].as_object() {
+                Ok(tweet) => {
+                    let data = match &tweet.data {
                         Some(x) => x,
                         None => panic!("main|ERR: unable to parse data object"),
                     };
 
-                    let users = match tweet["includes"].as_object() {
-                        Some(includes) => match includes["users"].as_array() {
-                            Some(users) => users,
-                            None => panic!("main|ERR: unable to parse users object"),
-                        },
-                        None => panic!("main|ERR: unable to parse includes object"),
+                    let author_id = data.author_id.as_deref().unwrap_or_default();
+                    let author = match tweet.find_user(author_id) {
+                        Some(user) => user,
+                        None => panic!("main|ERR: unable to parse users object"),
                     };
 
-                    let mut author_id = data["author_id"].to_string();
-                    let mut created_at = data["created_at"].to_string();
-                    let mut text = data["text"].to_string();
-                    let mut tweet_id = data["id"].to_string();
-                    let mut author_name = users[0]["name"].to_string();
-                    let mut author_username = users[0]["username"].to_string();
-
-                    author_id = author_id[1..author_id.len()-1].to_string();
-                    created_at = created_at[1..created_at.len()-1].to_string();
-                    text = text[1..text.len()-1].to_string();
-                    tweet_id = tweet_id[1..tweet_id.len()-1].to_string();
-                    author_name = author_name[1..author_name.len()-1].to_string();
-                    author_username = author_username[1..author_username.len()-1].to_string();
-
-                    println!("\nAuthor: {} // {} ({})\nCreated Dt: {}\nTweet Id: {}\nText: {}\n", 
-                            author_name, 
-                            author_username, 
-                            author_id, 
-                            created_at,
-                            tweet_id,
-                            text);
+                    println!("\nAuthor: {} // {} ({})\nCreated Dt: {}\nTweet Id: {}\nText: {}\n",
+                            author.name,
+                            author.username,
+                            author_id,
+                            data.created_at.as_deref().unwrap_or_default(),
+                            data.id,
+                            data.text);
                 },
                 Err(e) => { info!("main|ERR: unable to parse tweet object|e={:?}", e); },
             }
         },
         "user_timeline" => { 
-            let result = user_timeline(
-                config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+            let result = client.user_timeline(
                 cli_args.value_of("user_id").expect("ERR: cli [user_id] is invalid"),
+                &options,
+                PageLimit::pages(1),
             ).await;
 
             match result {
@@ -161,9 +170,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         },
         "mentions_timeline" => {
-            let result = mentions_timeline(
-                config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+            let result = client.mentions_timeline(
                 cli_args.value_of("user_id").expect("ERR: cli [user_id] is invalid"),
+                &options,
+                PageLimit::pages(1),
             ).await;
 
             match result {
@@ -193,31 +203,153 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "users_lookup" => {
             println!("testing");
   
-            let result = users_lookup(
-                config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+            let result = client.users_lookup(
                 cli_args.value_of("username").expect("ERR: cli [username] is invalid"),
             ).await;
 
             match result {
                 Ok(data) => {
-                    let user = match data["data"].as_array() {
-                        Some(user) => user,
-                        None => panic!("main|users_lookup|ERR: unable to parse data object"),
+                    let user = match data.data.as_deref() {
+                        Some([user, ..]) => user,
+                        _ => panic!("main|users_lookup|ERR: unable to parse data object"),
                     };
 
-                    let mut username = user[0]["username"].to_string();
-                    let mut user_id = user[0]["id"].to_string();
-                    let mut name = user[0]["name"].to_string();
-
-                    username = username[1..username.len()-1].to_string();
-                    user_id = user_id[1..user_id.len()-1].to_string();
-                    name = name[1..name.len()-1].to_string();
-
-                    println!("\nUser: {} // {} ({})\n", name, username, user_id);
+                    println!("\nUser: {} // {} ({})\n", user.name, user.username, user.id);
                 },
                 Err(e) => info!("main|users_lookup|ERR: unable to parse result object|e={}", e),
             }
         },
+        "recent_batch" => {
+            let topics = read_lines(cli_args.value_of("file").expect("ERR: cli [file] is invalid"))?;
+            let topics: Vec<&str> = topics.iter().map(|x| x.as_str()).collect();
+            let fanout = client.get_recent_tweets_many(&topics, &options, PageLimit::pages(1)).await;
+
+            for (topic, result) in &fanout.results {
+                match result {
+                    Ok(df) => println!("    {}|tweets={}", topic, df.height()),
+                    Err(err) => println!("    {}|{}", topic, err),
+                }
+            }
+
+            info!("main|recent_batch|completed|topics={}|failed={}", topics.len(), fanout.failed().len());
+        },
+        "tweet_lookup_batch" => {
+            let ids = read_lines(cli_args.value_of("file").expect("ERR: cli [file] is invalid"))?;
+            let ids: Vec<&str> = ids.iter().map(|x| x.as_str()).collect();
+            let lookup = client.tweet_lookup_batch(&ids).await?;
+
+            let id_col: Vec<&str> = lookup.df.column("tweet_id")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let created_col: Vec<&str> = lookup.df.column("created_at")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let text_col: Vec<&str> = lookup.df.column("text")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+
+            for i in 0..id_col.len() {
+                println!("    {}|{}|{}", id_col[i], created_col[i], text_col[i]);
+            }
+
+            for err in &lookup.missing {
+                println!("    missing|{}|{}", err.resource_id.as_deref().unwrap_or_default(), err.detail.as_deref().unwrap_or_default());
+            }
+
+            info!("main|tweet_lookup_batch|completed|found={}|missing={}", id_col.len(), lookup.missing.len());
+        },
+        "users_lookup_batch" => {
+            let usernames = read_lines(cli_args.value_of("file").expect("ERR: cli [file] is invalid"))?;
+            let usernames: Vec<&str> = usernames.iter().map(|x| x.as_str()).collect();
+            let lookup = client.users_lookup_batch(&usernames).await?;
+
+            let id_col: Vec<&str> = lookup.df.column("user_id")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let username_col: Vec<&str> = lookup.df.column("username")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let name_col: Vec<&str> = lookup.df.column("name")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+
+            for i in 0..id_col.len() {
+                println!("    {}|{}|{}", id_col[i], username_col[i], name_col[i]);
+            }
+
+            for err in &lookup.missing {
+                println!("    missing|{}|{}", err.resource_id.as_deref().unwrap_or_default(), err.detail.as_deref().unwrap_or_default());
+            }
+
+            info!("main|users_lookup_batch|completed|found={}|missing={}", id_col.len(), lookup.missing.len());
+        },
+        "threads" => {
+            let tweet_ids = match cli_args.value_of("file") {
+                Some(path) => read_lines(path)?,
+                None => vec![String::from(cli_args.value_of("tweet_id").expect("ERR: cli [tweet_id] is invalid"))],
+            };
+            let tweet_ids: Vec<&str> = tweet_ids.iter().map(|x| x.as_str()).collect();
+            let scope: SearchScope = cli_args.value_of("scope").unwrap_or("recent").parse()?;
+            let df = client.conversation_threads(&tweet_ids, scope, &options, PageLimit::default()).await?;
+
+            let id_col: Vec<&str> = df.column("tweet_id")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let text_col: Vec<&str> = df.column("text")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let depth_col: Vec<Option<u32>> = df.column("depth")?
+                .u32()?
+                .into_iter()
+                .collect();
+
+            for i in 0..id_col.len() {
+                let indent = "  ".repeat(depth_col[i].unwrap_or_default() as usize);
+                let depth = depth_col[i].map_or(String::from("?"), |x| x.to_string());
+                println!("    {}{}|{}|{}", indent, depth, id_col[i], text_col[i]);
+            }
+
+            info!("main|threads|completed|tweets={}", df.height());
+        },
+        "query" => {
+            let json = std::fs::read_to_string(cli_args.value_of("file").expect("ERR: cli [file] is invalid"))?;
+            let scope: SearchScope = cli_args.value_of("scope").unwrap_or("recent").parse()?;
+            let query = SearchQuery::from_json(&json)?.render(scope)?;
+            println!("    {}", query);
+
+            info!("main|query|completed|len={}", query.chars().count());
+        },
+        x if x.parse::<Relation>().is_ok() => {
+            let relation: Relation = x.parse()?;
+            let target = match relation.is_tweet_relation() {
+                true => cli_args.value_of("tweet_id").expect("ERR: cli [tweet_id] is invalid"),
+                false => cli_args.value_of("user_id").expect("ERR: cli [user_id] is invalid"),
+            };
+            let df = client.relation_edges(relation, target, PageLimit::default()).await?;
+
+            let src_col: Vec<&str> = df.column("src_id")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+            let dst_col: Vec<&str> = df.column("dst_id")?
+                .utf8()?
+                .into_no_null_iter()
+                .collect();
+
+            for i in 0..src_col.len() {
+                println!("    {} {} {}", src_col[i], relation.edge_type(), dst_col[i]);
+            }
+
+            info!("main|{}|completed|edges={}", relation.as_str(), df.height());
+        },
         _ => {
             usage();
             std::process::exit(1);
@@ -0,0 +1,261 @@
+use conf::{parse_land_args, init_logger, get_config, apply_script_options};
+use ct_nlp::{RawSink, raw_path};
+use source::{check_schema, Registry, SourceContext, SourceError, SourceTopic, Window};
+
+use diesel::{
+    query_dsl::{QueryDsl, RunQueryDsl},
+    expression::dsl::now,
+    ExpressionMethods,
+    PgConnection,
+};
+
+use base_diesel::{
+    models::JobStep,
+    schema::flow_step,
+    schema::{
+        topic::dsl::topic,
+        topic::id as topic_id,
+        topic::search_text,
+        topic::search_query,
+        topic::landing_dir,
+    },
+    schema::{
+        job_step::dsl::*,
+        job_step::id,
+        job_step::status,
+        job_step::updated_dt,
+    },
+    get_conn,
+};
+
+use std::{
+    collections::BTreeMap,
+    result::Result,
+    path::Path,
+    fs::File,
+    sync::Arc,
+};
+
+use log::info;
+use clap::ArgMatches;
+use chrono::{NaiveDate, Utc};
+use polars::prelude::*;
+
+#[allow(dead_code)]
+fn usage() {
+    println!("Usage: cargo run
+    --bin land
+    --
+    --job_step_id <job>
+    --config <config>
+    --topic_id <topic>
+    --output_dir <output_dir>
+    [--source <{}>] (default: --source in the flow step's script_parameters)
+    [--start_date <YYYY-MM-DD>]
+    [--end_date <YYYY-MM-DD>]", Registry::builtin().names().join("|"));
+}
+
+/// Record the job_step status matching a source error kind
+fn update_step_status(conn: &PgConnection, js_id: i32, err: &SourceError) {
+    let step_status = err.step_status();
+    match diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq(step_status),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn)
+    {
+        Ok(_) => info!("update_step_status|job_step_id={} set to status={}|{}", js_id, step_status, err),
+        Err(db_err) => info!("update_step_status|ERR: failed to update db for job_step_id={}|e={}", js_id, db_err),
+    }
+}
+
+fn parse_date(value: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").expect("ERR: date <YYYY-MM-DD> parse failed")
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_land_args();
+    let config_name = cli_args.value_of("conf").expect("ERR: cli [configuration] is invalid");
+    let output_dir = cli_args.value_of("output").expect("ERR: cli [output_dir] is invalid");
+    let t_id = cli_args.value_of("topic").expect("ERR: cli [topic_id] is invalid")
+        .parse::<i32>().expect("ERR: topic_id <i32> parse failed");
+    let js_id = cli_args.value_of("job_step").expect("ERR: cli [job_step_id] is invalid")
+        .parse::<i32>().expect("ERR: job_step_id <i32> parse failed");
+    let cli_source = cli_args.value_of("source");
+
+    // default window is yesterday (UTC), end_date is exclusive and defaults to the day after start_date
+    let window = match cli_args.value_of("start_date").map(parse_date) {
+        Some(start_date) => {
+            let end_date = cli_args.value_of("end_date").map_or(start_date + chrono::Duration::days(1), parse_date);
+            Window::days(start_date, end_date).unwrap_or_else(|e| panic!("main|{}", e))
+        },
+        None => Window::yesterday(),
+    };
+
+    let mut config: BTreeMap<String, String> = get_config(config_name);
+
+    let dt = Utc::now().to_rfc3339();
+    let log_dir = String::from(config.get("log_dir").expect("ERR: log_dir is invalid"));
+    let log_path = format!("{}/{}_land.log", &log_dir, &dt[0..19]);
+
+    init_logger(&log_path);
+    info!("main|starting");
+    info!("main|topic_id={}", t_id);
+    info!("main|job_step_id={}", js_id);
+    info!("main|window={}", window);
+
+    let conn = match get_conn(
+        config.get("pg_db").expect("ERR: conf [pg_db] is invalid"),
+        config.get("pg_user").expect("ERR: conf [pg_user] is invalid"),
+        config.get("pg_secret").expect("ERR: conf [pg_secret] is invalid"),
+        config.get("pg_host").expect("ERR: conf [pg_host] is invalid"),
+        config.get("pg_port").expect("ERR: conf [pg_port] is invalid"),
+    ) {
+        Ok(connection) => {
+            info!("main|conn established");
+            connection
+        },
+        Err(err) => {
+            panic!("main|ERR: failed to connect to db|err={}", err);
+        }
+    };
+
+    let topics = topic
+        .filter(topic_id.eq(t_id))
+        .select((search_text, landing_dir, search_query))
+        .limit(1)
+        .load::<(String, Option<String>, Option<String>)>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: topic not found for topic_id={}", t_id));
+
+    let target = match topics.is_empty() {
+        true => panic!("main|ERR: topic not found for topic_id={}", t_id),
+        false => SourceTopic {
+            topic_id: t_id,
+            search_text: topics[0].0.clone(),
+            search_query: topics[0].2.clone(),
+        },
+    };
+
+    // flags on the flow step beyond the standard ones pick and configure the source,
+    // job_controller only forwards the standard ones, e.g. --source evm_events --abi <path> --events Sale
+    let fs_id = job_step
+        .filter(id.eq(js_id))
+        .select(flow_step_id)
+        .first::<i32>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: job_step not found for job_step_id={}", js_id));
+    let script_params = flow_step::table
+        .find(fs_id)
+        .select(flow_step::script_parameters)
+        .first::<Option<String>>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: flow_step not found for flow_step_id={}", fs_id));
+    apply_script_options(&mut config, script_params.as_deref().unwrap_or_default());
+
+    // --source on the cli wins over the flow step's
+    if let Some(x) = cli_source {
+        config.insert(String::from("source"), String::from(x));
+    }
+    let source_name = match config.get("source") {
+        Some(x) => x.clone(),
+        None => {
+            let err = SourceError::Invalid(format!("source is not set, pass --source or add it to the flow step's script_parameters|expected one of {}",
+                Registry::builtin().names().join(", ")));
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    info!("main|source={}", source_name);
+
+    // untouched responses land next to the parquet, keyed by job_step_id
+    let raw_dir = match topics[0].1.as_deref() {
+        Some(x) if !x.is_empty() => x,
+        _ => output_dir,
+    };
+    let step = format!("{}_land", source_name);
+    let raw = Arc::new(RawSink::create(&raw_path(raw_dir, js_id, &step, &dt[0..19]))?);
+
+    let context = SourceContext {
+        config: &config,
+        raw: Some(Arc::clone(&raw)),
+    };
+    let source = match Registry::builtin().build(&source_name, &context) {
+        Ok(x) => x,
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+
+    let schema = source.schema();
+    let batches = match source.fetch(&target, &window).await {
+        Ok(x) => x,
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return match err.is_skippable() {
+                true => Ok(()),
+                false => Err(err.into()),
+            };
+        },
+    };
+    info!("main|batches={}", batches.len());
+
+    match Path::new(&output_dir).exists() {
+        true => info!("main|output_dir={}", output_dir),
+        false => {
+            std::fs::create_dir_all(output_dir)?;
+            info!("main|{} created successfully", output_dir);
+        },
+    }
+
+    let mut n = 0;
+    for (i, mut batch) in batches.into_iter().enumerate() {
+        if let Err(err) = check_schema(&schema, &batch) {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        }
+
+        let out_path = format!("{}/{}_{}_{:04}.parquet", output_dir, &dt[0..19], step, i);
+        let written = File::create(&out_path)
+            .map_err(|e| e.to_string())
+            .and_then(|x| ParquetWriter::new(x).finish(&mut batch).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            info!("main|ERR: unable to write to file|out_path={}|e={}", out_path, e);
+            let _ = std::fs::remove_file(&out_path);
+            let err = SourceError::Parse(format!("unable to write {}|{}", out_path, e));
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        }
+        info!("main|{} created successfully|rows={}", out_path, batch.height());
+        n += 1;
+    }
+
+    // the raw copy is part of the landing, without it the step has not landed
+    match raw.finish() {
+        Ok(pages) => info!("main|raw pages landed|pages={}", pages),
+        Err(err) => {
+            info!("main|ERR: unable to finish raw landing|e={}", err);
+            let err = SourceError::from(err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    }
+
+    // update flow
+    let result = diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq("C"),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(&conn);
+
+    match result {
+        Ok(_) => info!("main|{} completed for job_step_id={}|{} file(s) landed", step, js_id, n),
+        Err(err) => info!("main|ERR: failed to update db for job_step_id={}|e={}", js_id, err),
+    }
+
+    info!("main|completed");
+    Ok(())
+}
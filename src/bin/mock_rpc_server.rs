@@ -0,0 +1,43 @@
+use evm::mock::{MockRpcServer, MOCK_ERC1155, MOCK_ERC721, MOCK_MARKETPLACE};
+
+use log::info;
+use clap::{ArgMatches, Arg, Command};
+
+#[allow(dead_code)]
+fn usage() {
+    println!("Usage: cargo run --features mock --bin mock_rpc_server -- --port <port>");
+}
+
+fn parse_args() -> clap::ArgMatches {
+    let cli_args = Command::new("mock_rpc_server")
+        .args(&[
+            Arg::new("port")
+                .long("port")
+                .short('p')
+                .takes_value(true)
+                .default_value("8545"),
+            Arg::new("help")
+                .long("help")
+                .short('h'),
+        ])
+        .get_matches();
+
+    cli_args
+}
+
+/// Serves the evm mock chain on localhost so the evm_nft_transfers and evm_events sources can run offline
+/// set evm_rpc_url to the printed url and a topic's search_text to one of the printed contracts
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_args();
+    let port = cli_args.value_of("port").expect("ERR: cli [port] is invalid");
+
+    let server = MockRpcServer::bind(&format!("127.0.0.1:{}", port))?;
+    println!("evm_rpc_url: {}", server.url());
+    println!("erc721 contract: {}", MOCK_ERC721);
+    println!("erc1155 contract: {}", MOCK_ERC1155);
+    println!("marketplace contract: {} (abi src/evm/fixtures/marketplace_abi.json)", MOCK_MARKETPLACE);
+    info!("main|mock rpc server listening on {}", server.url());
+
+    server.wait();
+    Ok(())
+}
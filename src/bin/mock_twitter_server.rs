@@ -0,0 +1,43 @@
+use ct_nlp::mock::{MockServer, MOCK_API_KEY, MOCK_API_SECRET, MOCK_BEARER_TOKEN};
+
+use log::info;
+use clap::{ArgMatches, Arg, Command};
+
+#[allow(dead_code)]
+fn usage() {
+    println!("Usage: cargo run --features mock --bin mock_twitter_server -- --port <port>");
+}
+
+fn parse_args() -> clap::ArgMatches {
+    let cli_args = Command::new("mock_twitter_server")
+        .args(&[
+            Arg::new("port")
+                .long("port")
+                .short('p')
+                .takes_value(true)
+                .default_value("8089"),
+            Arg::new("help")
+                .long("help")
+                .short('h'),
+        ])
+        .get_matches();
+
+    cli_args
+}
+
+/// Serves the ct_nlp fixtures on localhost so the landing bins can run offline
+/// set twitter_base_url to the printed url and bearer_token (or twitter_api_key / twitter_api_secret) to the mock credentials
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_args();
+    let port = cli_args.value_of("port").expect("ERR: cli [port] is invalid");
+
+    let server = MockServer::bind(&format!("127.0.0.1:{}", port))?;
+    println!("twitter_base_url: {}", server.url());
+    println!("bearer_token: {}", MOCK_BEARER_TOKEN);
+    println!("twitter_api_key: {}", MOCK_API_KEY);
+    println!("twitter_api_secret: {}", MOCK_API_SECRET);
+    info!("main|mock server listening on {}", server.url());
+
+    server.wait();
+    Ok(())
+}
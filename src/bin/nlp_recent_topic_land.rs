@@ -1,18 +1,22 @@
 use conf::{parse_args1, init_logger, get_config};
-use ct_nlp::get_recent_tweets;
+use ct_nlp::{TwitterClient, RawSink, raw_path, PageLimit, QueryOptions, CtNlpError, newest_tweet, SearchScope, topic_query};
 
 use diesel::{
     query_dsl::{QueryDsl, RunQueryDsl},
     expression::dsl::now,
     ExpressionMethods,
+    PgConnection,
 };
 
 use base_diesel::{
-    models::JobStep,
+    models::{JobStep, TopicWatermark, TopicWatermarkForm},
+    schema::topic_watermark,
     schema::{
         topic::dsl::topic,
         topic::id as topic_id,
         topic::search_text,
+        topic::search_query,
+        topic::landing_dir,
     },
     schema::{
         job_step::dsl::*,
@@ -28,11 +32,13 @@ use std::{
     result::Result,
     path::Path,
     fs::File,
+    sync::Arc,
+    time::SystemTime,
 };
 
 use log::info;
 use clap::ArgMatches;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use polars::prelude::*;
 //use polars::frame::DataFrame;
 
@@ -47,6 +53,51 @@ fn usage() {
     --output_dir <output_dir>");
 }
 
+/// Record the job_step status matching a ct_nlp error kind
+fn update_step_status(conn: &PgConnection, js_id: i32, err: &CtNlpError) {
+    let step_status = err.step_status();
+    match diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq(step_status),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn)
+    {
+        Ok(_) => info!("update_step_status|job_step_id={} set to status={}|{}", js_id, step_status, err),
+        Err(db_err) => info!("update_step_status|ERR: failed to update db for job_step_id={}|e={}", js_id, db_err),
+    }
+}
+
+/// Advance the topic watermark for this flow step to newest_id
+fn update_watermark(
+    conn: &PgConnection,
+    t_id: i32,
+    fs_id: i32,
+    newest_id: &str,
+    newest_dt: Option<SystemTime>,
+) -> Result<usize, diesel::result::Error> {
+    let form = TopicWatermarkForm {
+        topic_id: t_id,
+        flow_step_id: fs_id,
+        newest_id,
+        newest_dt,
+        created_dt: SystemTime::now(),
+        updated_dt: None,
+    };
+
+    diesel::insert_into(topic_watermark::table)
+        .values(&form)
+        .on_conflict((topic_watermark::topic_id, topic_watermark::flow_step_id))
+        .do_update()
+        .set((
+            topic_watermark::newest_id.eq(newest_id),
+            topic_watermark::newest_dt.eq(newest_dt),
+            topic_watermark::updated_dt.eq(now),
+        ))
+        .execute(conn)
+}
+
 // TODO: not sure if flow_step failure update 
 // should happen here or just propagate back to controller 
 // and handle there...tbd
@@ -91,9 +142,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let topics = topic
         .filter(topic_id.eq(t_id))
-        .select(search_text)  
+        .select((search_text, landing_dir, search_query))  
         .limit(1)
-        .load::<String>(&conn)
+        .load::<(String, Option<String>, Option<String>)>(&conn)
         .expect(&format!("main|ERR: topic not found for topic_id={}", t_id));
 
     let target = match topics.is_empty() {
@@ -101,13 +152,67 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             panic!("main|ERR: topic not found for topic_id={}", t_id);
             // update failure 
         },
-        false => &topics[0],
+        false => &topics[0].0,
     }; 
 
-    let df = get_recent_tweets(
-        config.get("bearer_token").expect("ERR: bearer_token is invalid"),
+    // a structured search_query takes precedence over search_text
+    let query = match topic_query(target, topics[0].2.as_deref(), SearchScope::Recent) {
+        Ok(x) => x,
+        Err(err) => {
+            info!("main|search_query cannot be rendered for topic_id={}", t_id);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    let target = &query;
+    info!("main|query={}", target);
+
+    let fs_id = job_step
+        .filter(id.eq(js_id))
+        .select(flow_step_id)
+        .first::<i32>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: job_step not found for job_step_id={}", js_id));
+
+    // only land tweets newer than the last successful write
+    let watermark = topic_watermark::table
+        .filter(topic_watermark::topic_id.eq(t_id))
+        .filter(topic_watermark::flow_step_id.eq(fs_id))
+        .first::<TopicWatermark>(&conn)
+        .ok();
+
+    let options = match &watermark {
+        Some(x) => {
+            info!("main|watermark|newest_id={}|newest_dt={:?}", x.newest_id, x.newest_dt.map(DateTime::<Utc>::from));
+            QueryOptions::since(&x.newest_id)
+        },
+        None => {
+            info!("main|watermark|none for topic_id={}|flow_step_id={}", t_id, fs_id);
+            QueryOptions::default()
+        },
+    };
+
+    // untouched response pages land next to the parquet, keyed by job_step_id
+    let raw_dir = match topics[0].1.as_deref() {
+        Some(x) if !x.is_empty() => x,
+        _ => output_dir,
+    };
+    let raw = Arc::new(RawSink::create(&raw_path(raw_dir, js_id, "nlp_recent_topic_land", &dt[0..19]))?);
+    let client = TwitterClient::from_config(&config)?.with_raw_sink(Arc::clone(&raw));
+    // pages come newest first, a capped run past a watermark would skip the tweets between
+    // the watermark and its last page once the watermark moves, so those runs page to the end
+    let limit = match &watermark {
+        Some(_) => PageLimit::default(),
+        None => PageLimit {
+            max_pages: config.get("max_pages").and_then(|x| x.parse().ok()),
+            max_rows: config.get("max_rows").and_then(|x| x.parse().ok()),
+        },
+    };
+    info!("main|limit={:?}", limit);
+
+    let df = client.get_recent_tweets(
         target,
-        "100",
+        &options,
+        limit,
     ).await;
 
     match Path::new(&output_dir).exists() {
@@ -118,7 +223,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
     }
 
-    let out_path = format!("{}/{}_nlp_recent_topic_land.parquet", output_dir, &dt[0..10]);
+    // one file per run, each run only holds tweets past the previous watermark
+    let out_path = format!("{}/{}_nlp_recent_topic_land.parquet", output_dir, &dt[0..19]);
     match Path::new(&out_path).exists() {
         true => {
             info!("main|{} exists|attempting remove", out_path);
@@ -128,20 +234,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         false => info!("main|out_path={}", out_path),
     }
 
+    // the raw copy is part of the landing, without it the step has not landed and the watermark stays put
+    match raw.finish() {
+        Ok(pages) => info!("main|raw pages landed|pages={}", pages),
+        Err(err) => {
+            info!("main|ERR: unable to finish raw landing|e={}", err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    }
+
     match df {
         Ok(frame) => {
             let mut out_df: polars::frame::DataFrame = frame;
-            let output_file = File::create(out_path).expect("main|ERR: cannot create output file");
-            match ParquetWriter::new(output_file)
-                .finish(&mut out_df) 
-            {
-                Ok(_) => info!("main|file created successfully"),
-                Err(err) => info!("main|ERR: unable to write to file|e={}", err),
+            let newest = newest_tweet(&out_df)?;
+            let written = File::create(&out_path)
+                .map_err(|e| e.to_string())
+                .and_then(|x| ParquetWriter::new(x).finish(&mut out_df).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                // a failed landing fails the step, the watermark stays put so the next run retries
+                info!("main|ERR: unable to write to file|out_path={}|e={}", out_path, e);
+                let _ = std::fs::remove_file(&out_path);
+                let err = CtNlpError::Parse(format!("unable to write {}|{}", out_path, e));
+                update_step_status(&conn, js_id, &err);
+                return Err(err.into());
+            }
+            info!("main|file created successfully|rows={}", out_df.height());
+
+            // watermark only moves once the file is on disk
+            if let Some((newest_id, created_at)) = newest {
+                let newest_dt = DateTime::parse_from_rfc3339(&created_at).ok().map(SystemTime::from);
+                if let Err(e) = update_watermark(&conn, t_id, fs_id, &newest_id, newest_dt) {
+                    // the next run would land the same tweets again, the file goes and the step fails
+                    info!("main|ERR: failed to update watermark|e={}", e);
+                    let _ = std::fs::remove_file(&out_path);
+                    let err = CtNlpError::Parse(format!("unable to advance watermark to {}|{}", newest_id, e));
+                    update_step_status(&conn, js_id, &err);
+                    return Err(err.into());
+                }
+                info!("main|watermark advanced|newest_id={}|newest_dt={}", newest_id, created_at);
             }
         },
-        Err(err) => { 
-            // update failure
-            panic!("main|ERR: unable to write to file|e={}", err);
+        Err(err) if err.is_skippable() => info!("main|nothing to land|{}", err),
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
         },
     }
 
@@ -0,0 +1,234 @@
+use conf::{parse_args1, init_logger, get_config};
+use ct_nlp::{TwitterClient, RawSink, raw_path, PageLimit, QueryOptions, CtNlpError, SearchScope, topic_query};
+
+use diesel::{
+    query_dsl::{QueryDsl, RunQueryDsl},
+    expression::dsl::now,
+    ExpressionMethods,
+    PgConnection,
+};
+
+use base_diesel::{
+    models::JobStep,
+    schema::{
+        topic::dsl::topic,
+        topic::id as topic_id,
+        topic::search_text,
+        topic::search_query,
+        topic::landing_dir,
+    },
+    schema::{
+        job_step::dsl::*,
+        job_step::id,
+        job_step::status,
+        job_step::updated_dt,
+    },
+    get_conn,
+};
+
+use std::{
+    collections::BTreeMap,
+    result::Result,
+    path::Path,
+    fs::File,
+    sync::Arc,
+};
+
+use log::info;
+use clap::ArgMatches;
+use chrono::Utc;
+use polars::prelude::*;
+
+#[allow(dead_code)]
+fn usage() {
+    println!("Usage: cargo run
+    --bin nlp_thread_land
+    --
+    --job_step_id <job>
+    --config <config>
+    --topic_id <topic>
+    --output_dir <output_dir>");
+}
+
+/// Record the job_step status matching a ct_nlp error kind
+fn update_step_status(conn: &PgConnection, js_id: i32, err: &CtNlpError) {
+    let step_status = err.step_status();
+    match diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq(step_status),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn)
+    {
+        Ok(_) => info!("update_step_status|job_step_id={} set to status={}|{}", js_id, step_status, err),
+        Err(db_err) => info!("update_step_status|ERR: failed to update db for job_step_id={}|e={}", js_id, db_err),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_args1();
+    let config_name = cli_args.value_of("conf").expect("ERR: cli [configuration] is invalid");
+    let output_dir = cli_args.value_of("output").expect("ERR: cli [output_dir] is invalid");
+    let t_id = cli_args.value_of("topic").expect("ERR: cli [topic_id] is invalid")
+        .parse::<i32>().expect("ERR: topic_id <i32> parse failed");
+    let js_id = cli_args.value_of("job_step").expect("ERR: cli [job_step_id] is invalid")
+        .parse::<i32>().expect("ERR: job_step_id <i32> parse failed");
+
+    let config: BTreeMap<String, String> = get_config(config_name);
+
+    let dt = Utc::now().to_rfc3339();
+    let log_dir = String::from(config.get("log_dir").expect("ERR: log_dir is invalid"));
+    let log_path = format!("{}/{}_nlp_thread_land.log", &log_dir, &dt[0..19]);
+
+    init_logger(&log_path);
+    info!("main|starting");
+    info!("main|topic_id={}", t_id);
+    info!("main|job_step_id={}", js_id);
+
+    let max_tweets: usize = config.get("thread_max_tweets").map_or(Ok(10), |x| x.parse())
+        .expect("ERR: conf [thread_max_tweets] <usize> parse failed");
+
+    let conn = match get_conn(
+        config.get("pg_db").expect("ERR: conf [pg_db] is invalid"),
+        config.get("pg_user").expect("ERR: conf [pg_user] is invalid"),
+        config.get("pg_secret").expect("ERR: conf [pg_secret] is invalid"),
+        config.get("pg_host").expect("ERR: conf [pg_host] is invalid"),
+        config.get("pg_port").expect("ERR: conf [pg_port] is invalid"),
+    ) {
+        Ok(connection) => {
+            info!("main|conn established");
+            connection
+        },
+        Err(err) => {
+            panic!("main|ERR: failed to connect to db|err={}", err);
+        }
+    };
+
+    let topics = topic
+        .filter(topic_id.eq(t_id))
+        .select((search_text, landing_dir, search_query))
+        .limit(1)
+        .load::<(String, Option<String>, Option<String>)>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: topic not found for topic_id={}", t_id));
+
+    let target = match topics.is_empty() {
+        true => panic!("main|ERR: topic not found for topic_id={}", t_id),
+        false => &topics[0].0,
+    };
+
+    // a structured search_query takes precedence over search_text
+    let query = match topic_query(target, topics[0].2.as_deref(), SearchScope::Recent) {
+        Ok(x) => x,
+        Err(err) => {
+            info!("main|search_query cannot be rendered for topic_id={}", t_id);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    let target = &query;
+    info!("main|query={}", target);
+
+    // untouched response pages land next to the parquet, keyed by job_step_id
+    let raw_dir = match topics[0].1.as_deref() {
+        Some(x) if !x.is_empty() => x,
+        _ => output_dir,
+    };
+    let raw = Arc::new(RawSink::create(&raw_path(raw_dir, js_id, "nlp_thread_land", &dt[0..19]))?);
+    let client = TwitterClient::from_config(&config)?.with_raw_sink(Arc::clone(&raw));
+
+    let limit = PageLimit {
+        max_pages: config.get("max_pages").and_then(|x| x.parse().ok()),
+        max_rows: config.get("max_rows").and_then(|x| x.parse().ok()),
+    };
+
+    // threads are rebuilt for the topic's latest tweets
+    let recent = client.get_recent_tweets(target, &QueryOptions::default(), PageLimit::rows(max_tweets)).await;
+    let ids: Vec<String> = match recent {
+        Ok(df) => df.column("tweet_id")?
+            .utf8()?
+            .into_no_null_iter()
+            .take(max_tweets)
+            .map(String::from)
+            .collect(),
+        Err(err) if err.is_skippable() => {
+            info!("main|no tweets for topic_id={}|{}", t_id, err);
+            vec![]
+        },
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    info!("main|ids={}", ids.len());
+
+    let ids: Vec<&str> = ids.iter().map(|x| x.as_str()).collect();
+    let df = match ids.is_empty() {
+        true => None,
+        false => match client.conversation_threads(&ids, SearchScope::Recent, &QueryOptions::default(), limit).await {
+            Ok(frame) => Some(frame),
+            Err(err) if err.is_skippable() => {
+                info!("main|nothing to land|{}", err);
+                None
+            },
+            Err(err) => {
+                update_step_status(&conn, js_id, &err);
+                return Err(err.into());
+            },
+        },
+    };
+
+    match df {
+        None => info!("main|nothing to land"),
+        Some(mut out_df) => {
+            match Path::new(&output_dir).exists() {
+                true => info!("main|output_dir={}", output_dir),
+                false => {
+                    std::fs::create_dir_all(output_dir)?;
+                    info!("main|{} created successfully", output_dir);
+                },
+            }
+
+            let out_path = format!("{}/{}_nlp_thread_land.parquet", output_dir, &dt[0..19]);
+            let written = File::create(&out_path)
+                .map_err(|e| e.to_string())
+                .and_then(|x| ParquetWriter::new(x).finish(&mut out_df).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                info!("main|ERR: unable to write to file|out_path={}|e={}", out_path, e);
+                let _ = std::fs::remove_file(&out_path);
+                let err = CtNlpError::Parse(format!("unable to write {}|{}", out_path, e));
+                update_step_status(&conn, js_id, &err);
+                return Err(err.into());
+            }
+            info!("main|{} created successfully|rows={}", out_path, out_df.height());
+        },
+    }
+
+    // the raw copy is part of the landing, without it the step has not landed
+    match raw.finish() {
+        Ok(pages) => info!("main|raw pages landed|pages={}", pages),
+        Err(err) => {
+            info!("main|ERR: unable to finish raw landing|e={}", err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    }
+
+    // update flow
+    let result = diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq("C"),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(&conn);
+
+    match result {
+        Ok(_) => info!("main|nlp_thread_land completed for job_step_id={}", js_id),
+        Err(err) => info!("main|ERR: failed to update db for job_step_id={}|e={}", js_id, err),
+    }
+
+    info!("main|completed");
+    Ok(())
+}
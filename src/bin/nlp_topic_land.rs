@@ -1,15 +1,245 @@
-use conf::{get_config, init_logger};
+use conf::{parse_nlp_topic_land_args, init_logger, get_config};
+use ct_nlp::{TwitterClient, RawSink, raw_path, PageLimit, QueryOptions, CtNlpError, SearchScope, topic_query};
+
+use diesel::{
+    query_dsl::{QueryDsl, RunQueryDsl},
+    expression::dsl::now,
+    ExpressionMethods,
+    PgConnection,
+};
+
+use base_diesel::{
+    models::JobStep,
+    schema::{
+        topic::dsl::topic,
+        topic::id as topic_id,
+        topic::search_text,
+        topic::search_query,
+        topic::landing_dir,
+    },
+    schema::{
+        job_step::dsl::*,
+        job_step::id,
+        job_step::status,
+        job_step::updated_dt,
+    },
+    get_conn,
+};
+
+use std::{
+    collections::BTreeMap,
+    result::Result,
+    path::Path,
+    fs::File,
+    sync::Arc,
+};
 
 use log::info;
-use std::result::Result;
+use clap::ArgMatches;
+use chrono::{Duration, NaiveDate, Utc};
+use polars::prelude::*;
 
+#[allow(dead_code)]
 fn usage() {
-    println!("Usage: cargo run --bin nlp_topic_land -- --config <config> --topic <topic>");
+    println!("Usage: cargo run
+    --bin nlp_topic_land
+    --
+    --job_step_id <job>
+    --config <config>
+    --topic_id <topic>
+    --output_dir <output_dir>
+    [--start_date <YYYY-MM-DD>]
+    [--end_date <YYYY-MM-DD>]");
+}
+
+/// Record the job_step status matching a ct_nlp error kind
+fn update_step_status(conn: &PgConnection, js_id: i32, err: &CtNlpError) {
+    let step_status = err.step_status();
+    match diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq(step_status),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn)
+    {
+        Ok(_) => info!("update_step_status|job_step_id={} set to status={}|{}", js_id, step_status, err),
+        Err(db_err) => info!("update_step_status|ERR: failed to update db for job_step_id={}|e={}", js_id, db_err),
+    }
+}
+
+fn parse_date(value: Option<&str>, default: NaiveDate) -> NaiveDate {
+    match value {
+        Some(x) => NaiveDate::parse_from_str(x, "%Y-%m-%d").expect("ERR: date <YYYY-MM-DD> parse failed"),
+        None => default,
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_nlp_topic_land_args();
+    let config_name = cli_args.value_of("conf").expect("ERR: cli [configuration] is invalid");
+    let output_dir = cli_args.value_of("output").expect("ERR: cli [output_dir] is invalid");
+    let t_id = cli_args.value_of("topic").expect("ERR: cli [topic_id] is invalid")
+        .parse::<i32>().expect("ERR: topic_id <i32> parse failed");
+    let js_id = cli_args.value_of("job_step").expect("ERR: cli [job_step_id] is invalid")
+        .parse::<i32>().expect("ERR: job_step_id <i32> parse failed");
+
+    // default window is yesterday (UTC), end_date is exclusive
+    let today = Utc::now().date_naive();
+    let start_date = parse_date(cli_args.value_of("start_date"), today - Duration::days(1));
+    let end_date = parse_date(cli_args.value_of("end_date"), start_date + Duration::days(1));
+
+    let config: BTreeMap<String, String> = get_config(config_name);
+
+    let dt = Utc::now().to_rfc3339();
+    let log_dir = String::from(config.get("log_dir").expect("ERR: log_dir is invalid"));
+    let log_path = format!("{}/{}_nlp_topic_land.log", &log_dir, &dt[0..19]);
+
+    init_logger(&log_path);
     info!("main|starting");
+    info!("main|topic_id={}", t_id);
+    info!("main|job_step_id={}", js_id);
+    info!("main|window={}..{}", start_date, end_date);
+
+    if start_date >= end_date {
+        panic!("main|ERR: start_date must be before end_date|start_date={}|end_date={}", start_date, end_date);
+    }
+
+    let conn = match get_conn(
+        config.get("pg_db").expect("ERR: conf [pg_db] is invalid"),
+        config.get("pg_user").expect("ERR: conf [pg_user] is invalid"),
+        config.get("pg_secret").expect("ERR: conf [pg_secret] is invalid"),
+        config.get("pg_host").expect("ERR: conf [pg_host] is invalid"),
+        config.get("pg_port").expect("ERR: conf [pg_port] is invalid"),
+    ) {
+        Ok(connection) => {
+            info!("main|conn established");
+            connection
+        },
+        Err(err) => {
+            panic!("main|ERR: failed to connect to db|err={}", err);
+        }
+    };
+
+    let topics = topic
+        .filter(topic_id.eq(t_id))
+        .select((search_text, landing_dir, search_query))
+        .limit(1)
+        .load::<(String, Option<String>, Option<String>)>(&conn)
+        .unwrap_or_else(|_| panic!("main|ERR: topic not found for topic_id={}", t_id));
+
+    let target = match topics.is_empty() {
+        true => panic!("main|ERR: topic not found for topic_id={}", t_id),
+        false => &topics[0].0,
+    };
+
+    // a structured search_query takes precedence over search_text
+    let query = match topic_query(target, topics[0].2.as_deref(), SearchScope::All) {
+        Ok(x) => x,
+        Err(err) => {
+            info!("main|search_query cannot be rendered for topic_id={}", t_id);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    };
+    let target = &query;
+    info!("main|query={}", target);
+
+    // untouched response pages land next to the parquet, keyed by job_step_id
+    let raw_dir = match topics[0].1.as_deref() {
+        Some(x) if !x.is_empty() => x,
+        _ => output_dir,
+    };
+    let raw = Arc::new(RawSink::create(&raw_path(raw_dir, js_id, "nlp_topic_land", &dt[0..19]))?);
+    let client = TwitterClient::from_config(&config)?.with_raw_sink(Arc::clone(&raw));
+    let limit = PageLimit {
+        max_pages: config.get("max_pages").and_then(|x| x.parse().ok()),
+        max_rows: config.get("max_rows").and_then(|x| x.parse().ok()),
+    };
+
+    match Path::new(&output_dir).exists() {
+        true => info!("main|output_dir={}", output_dir),
+        false => {
+            std::fs::create_dir_all(output_dir)?;
+            info!("main|{} created successfully", output_dir);
+        },
+    }
+
+    // one parquet per day in the window
+    let mut day = start_date;
+    let mut n = 0;
+    while day < end_date {
+        let next_day = day + Duration::days(1);
+        let options = QueryOptions::window(
+            &format!("{}T00:00:00Z", day),
+            &format!("{}T00:00:00Z", next_day),
+        );
+
+        let df = client.search_all(
+            target,
+            &options,
+            limit,
+        ).await;
+
+        let mut out_df = match df {
+            Ok(frame) => frame,
+            Err(err) if err.is_skippable() => {
+                info!("main|nothing to land for {}|{}", day, err);
+                day = next_day;
+                continue;
+            },
+            Err(err) => {
+                update_step_status(&conn, js_id, &err);
+                return Err(err.into());
+            },
+        };
+
+        let out_path = format!("{}/{}_nlp_topic_land.parquet", output_dir, day);
+        if Path::new(&out_path).exists() {
+            info!("main|{} exists|attempting remove", out_path);
+            std::fs::remove_file(&out_path)?;
+        }
+
+        let written = File::create(&out_path)
+            .map_err(|e| e.to_string())
+            .and_then(|x| ParquetWriter::new(x).finish(&mut out_df).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            info!("main|ERR: unable to write to file|out_path={}|e={}", out_path, e);
+            let _ = std::fs::remove_file(&out_path);
+            let err = CtNlpError::Parse(format!("unable to write {}|{}", out_path, e));
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        }
+        info!("main|{} created successfully|rows={}", out_path, out_df.height());
+        n += 1;
+
+        day = next_day;
+    }
+
+    // the raw copy is part of the landing, without it the step has not landed
+    match raw.finish() {
+        Ok(pages) => info!("main|raw pages landed|pages={}", pages),
+        Err(err) => {
+            info!("main|ERR: unable to finish raw landing|e={}", err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    }
+
+    // update flow
+    let result = diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq("C"),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(&conn);
+
+    match result {
+        Ok(_) => info!("main|nlp_topic_land completed for job_step_id={}|{} file(s) landed", js_id, n),
+        Err(err) => info!("main|ERR: failed to update db for job_step_id={}|e={}", js_id, err),
+    }
 
     info!("main|completed");
     Ok(())
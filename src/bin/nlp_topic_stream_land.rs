@@ -0,0 +1,237 @@
+use conf::{parse_nlp_topic_stream_land_args, init_logger, get_config};
+use ct_nlp::{TwitterClient, RawSink, ReconnectPolicy, StreamControl, StreamEvent, Tweet, User, SearchScope, tweets_to_df, topic_query};
+use ct_nlp::stream::{topic_rule, rule_topic_id};
+
+use diesel::query_dsl::{QueryDsl, RunQueryDsl};
+
+use base_diesel::{
+    schema::topic::dsl::topic,
+    schema::topic::{id as topic_id, search_text, landing_dir, search_query},
+    get_conn,
+};
+
+use std::{
+    collections::BTreeMap,
+    result::Result,
+    path::Path,
+    fs::File,
+    sync::Arc,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::info;
+use clap::ArgMatches;
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+
+#[allow(dead_code)]
+fn usage() {
+    println!("Usage: cargo run
+    --bin nlp_topic_stream_land
+    --
+    --config <config>");
+}
+
+/// Tweets buffered for one topic until its time window rolls over
+struct TopicLanding {
+    topic_id: i32,
+    dir: String,
+    window: i64,
+    tweets: Vec<Tweet>,
+    users: Vec<User>,
+    /// stream lines as received, landed raw next to the window's parquet
+    raw: Vec<String>,
+}
+
+impl TopicLanding {
+    /// Write the buffered window to {dir}/{window start}_nlp_topic_stream_land.parquet
+    /// and its stream lines to {dir}/raw/topic_id={topic_id}/ under the same name as .ndjson.gz
+    /// a restart inside the same window gets a numbered file instead of overwriting
+    fn flush(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.tweets.is_empty() { return Ok(()); }
+
+        let mut df = tweets_to_df(&self.tweets, &self.users)?;
+
+        let start = DateTime::<Utc>::from_timestamp(self.window, 0).unwrap_or_default().to_rfc3339();
+        let mut stem = format!("{}_nlp_topic_stream_land", &start[0..19]);
+        let mut n = 1;
+        while Path::new(&format!("{}/{}.parquet", self.dir, stem)).exists() {
+            stem = format!("{}_{}_nlp_topic_stream_land", &start[0..19], n);
+            n += 1;
+        }
+        let out_path = format!("{}/{}.parquet", self.dir, stem);
+
+        // untouched lines first, the sink finishes itself if a write fails on the way
+        let raw_path = Path::new(&self.dir).join("raw").join(format!("topic_id={}", self.topic_id)).join(format!("{}.ndjson.gz", stem));
+        let raw = RawSink::create(&raw_path)?;
+        for line in &self.raw {
+            raw.write_page("GET", "/2/tweets/search/stream", &[], 200, line)?;
+        }
+        info!("flush|topic_id={}|raw lines landed|lines={}", self.topic_id, raw.finish()?);
+
+        let output_file = File::create(&out_path)?;
+        ParquetWriter::new(output_file).finish(&mut df)?;
+        info!("flush|topic_id={}|{} created successfully|rows={}", self.topic_id, out_path, df.height());
+
+        self.tweets.clear();
+        self.users.clear();
+        self.raw.clear();
+        Ok(())
+    }
+
+    /// Flush if now is past this landing's window
+    fn roll(&mut self, window: i64) {
+        if window == self.window { return; }
+
+        if let Err(err) = self.flush() {
+            info!("roll|ERR: unable to write window|topic_id={}|e={}", self.topic_id, err);
+        }
+        self.window = window;
+    }
+}
+
+fn current_window(roll_secs: i64) -> i64 {
+    let now = Utc::now().timestamp();
+    now - now.rem_euclid(roll_secs)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli_args: ArgMatches = parse_nlp_topic_stream_land_args();
+    let config_name = cli_args.value_of("conf").expect("ERR: cli [configuration] is invalid");
+
+    let config: BTreeMap<String, String> = get_config(config_name);
+
+    let dt = Utc::now().to_rfc3339();
+    let log_dir = String::from(config.get("log_dir").expect("ERR: log_dir is invalid"));
+    let log_path = format!("{}/{}_nlp_topic_stream_land.log", &log_dir, &dt[0..19]);
+
+    init_logger(&log_path);
+    info!("main|starting");
+
+    let roll_secs: i64 = config.get("stream_roll_secs").map_or(Ok(3600), |x| x.parse())
+        .expect("ERR: conf [stream_roll_secs] <i64> parse failed");
+    if roll_secs <= 0 {
+        panic!("main|ERR: stream_roll_secs must be positive|stream_roll_secs={}", roll_secs);
+    }
+    info!("main|stream_roll_secs={}", roll_secs);
+
+    let conn = match get_conn(
+        config.get("pg_db").expect("ERR: conf [pg_db] is invalid"),
+        config.get("pg_user").expect("ERR: conf [pg_user] is invalid"),
+        config.get("pg_secret").expect("ERR: conf [pg_secret] is invalid"),
+        config.get("pg_host").expect("ERR: conf [pg_host] is invalid"),
+        config.get("pg_port").expect("ERR: conf [pg_port] is invalid"),
+    ) {
+        Ok(connection) => {
+            info!("main|conn established");
+            connection
+        },
+        Err(err) => {
+            panic!("main|ERR: failed to connect to db|err={}", err);
+        }
+    };
+
+    // every topic with a landing_dir gets a rule
+    let topics = topic
+        .select((topic_id, search_text, landing_dir, search_query))
+        .load::<(i32, String, Option<String>, Option<String>)>(&conn)
+        .unwrap_or_else(|e| panic!("main|ERR: unable to load topics|e={}", e));
+
+    let window = current_window(roll_secs);
+    let mut landings: BTreeMap<i32, TopicLanding> = BTreeMap::new();
+    let mut rules = vec![];
+    for (t_id, text, dir, query) in topics {
+        let dir = match dir {
+            Some(x) if !x.is_empty() => x,
+            _ => {
+                info!("main|topic_id={} has no landing_dir|skipping", t_id);
+                continue;
+            },
+        };
+
+        // rules share the recent search length limit
+        let text = match topic_query(&text, query.as_deref(), SearchScope::Recent) {
+            Ok(x) => x,
+            Err(err) => {
+                info!("main|ERR: search_query cannot be rendered for topic_id={}|skipping|{}", t_id, err);
+                continue;
+            },
+        };
+
+        if !Path::new(&dir).exists() {
+            std::fs::create_dir_all(&dir)?;
+            info!("main|{} created successfully", dir);
+        }
+
+        info!("main|topic_id={}|rule={}|landing_dir={}", t_id, text, dir);
+        rules.push(topic_rule(t_id, &text));
+        landings.insert(t_id, TopicLanding { topic_id: t_id, dir, window, tweets: vec![], users: vec![], raw: vec![] });
+    }
+
+    if rules.is_empty() {
+        panic!("main|ERR: no topics with a landing_dir to stream");
+    }
+
+    let client = TwitterClient::from_config(&config)?;
+    let sync = client.sync_stream_rules(&rules).await?;
+    info!("main|rules synced|added={}|deleted={}", sync.added.len(), sync.deleted.len());
+
+    // ctrl-c hangs up the stream, buffered windows are flushed on the way out
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let signal = Arc::clone(&shutdown);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("main|shutdown requested");
+            signal.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let policy = ReconnectPolicy {
+        max_reconnects: config.get("stream_max_reconnects").and_then(|x| x.parse().ok()),
+        ..ReconnectPolicy::default()
+    };
+
+    let result = client.filtered_stream(&policy, |event| {
+        if let StreamEvent::Tweet(tweet) = event {
+            let users = tweet.includes.map(|x| x.users).unwrap_or_default();
+            for t_id in tweet.matching_rules.iter().filter_map(|x| rule_topic_id(x.tag.as_deref())) {
+                match landings.get_mut(&t_id) {
+                    Some(landing) => {
+                        landing.tweets.push(tweet.data.clone());
+                        landing.users.extend(users.iter().cloned());
+                        landing.raw.push(tweet.raw.clone());
+                    },
+                    None => info!("main|tweet_id={} matched unknown topic_id={}", tweet.data.id, t_id),
+                }
+            }
+        }
+
+        let window = current_window(roll_secs);
+        for landing in landings.values_mut() {
+            landing.roll(window);
+        }
+
+        match shutdown.load(Ordering::SeqCst) {
+            true => StreamControl::Stop,
+            false => StreamControl::Continue,
+        }
+    }).await;
+
+    for landing in landings.values_mut() {
+        if let Err(err) = landing.flush() {
+            info!("main|ERR: unable to write window|topic_id={}|e={}", landing.topic_id, err);
+        }
+    }
+
+    match result {
+        Ok(stats) => info!("main|stream closed|connections={}|tweets={}|heartbeats={}", stats.connections, stats.tweets, stats.heartbeats),
+        Err(err) => {
+            info!("main|ERR: stream failed|{}", err);
+            return Err(err.into());
+        },
+    }
+
+    info!("main|completed");
+    Ok(())
+}
@@ -1,10 +1,11 @@
 use conf::{parse_args1, init_logger, get_config};
-use ct_nlp::{users_lookup, user_timeline};
+use ct_nlp::{TwitterClient, RawSink, raw_path, PageLimit, QueryOptions, CtNlpError};
 
 use diesel::{
     query_dsl::{QueryDsl, RunQueryDsl},
     expression::dsl::now,
     ExpressionMethods,
+    PgConnection,
 };
 
 use base_diesel::{
@@ -13,6 +14,7 @@ use base_diesel::{
         topic::dsl::topic,
         topic::id as topic_id,
         topic::search_text,
+        topic::landing_dir,
     },
     schema::{
         job_step::dsl::*,
@@ -28,6 +30,7 @@ use std::{
     result::Result,
     path::Path,
     fs::File,
+    sync::Arc,
 };
 
 use log::info;
@@ -46,6 +49,22 @@ fn usage() {
     --output_dir <output_dir>");
 }
 
+/// Record the job_step status matching a ct_nlp error kind
+fn update_step_status(conn: &PgConnection, js_id: i32, err: &CtNlpError) {
+    let step_status = err.step_status();
+    match diesel::update(job_step)
+        .filter(id.eq(js_id))
+        .set((
+            status.eq(step_status),
+            updated_dt.eq(now),
+        ))
+        .get_result::<JobStep>(conn)
+    {
+        Ok(_) => info!("update_step_status|job_step_id={} set to status={}|{}", js_id, step_status, err),
+        Err(db_err) => info!("update_step_status|ERR: failed to update db for job_step_id={}|e={}", js_id, db_err),
+    }
+}
+
 // TODO: not sure if flow_step failure update 
 // should happen here or just propagate back to controller 
 // and handle there...tbd
@@ -90,9 +109,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let topics = topic
         .filter(topic_id.eq(t_id))
-        .select(search_text)  
+        .select((search_text, landing_dir))  
         .limit(1)
-        .load::<String>(&conn)
+        .load::<(String, Option<String>)>(&conn)
         .expect(&format!("main|ERR: topic not found for topic_id={}", t_id));
 
     let target = match topics.is_empty() {
@@ -100,35 +119,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             panic!("main|ERR: topic not found for topic_id={}", t_id);
             // update failure 
         },
-        false => &topics[0],
+        false => &topics[0].0,
     }; 
 
-    let result = users_lookup(
-        config.get("bearer_token").expect("ERR: conf [bearer_token] is invalid)"),
-        target,
+    // untouched response pages land next to the parquet, keyed by job_step_id
+    let raw_dir = match topics[0].1.as_deref() {
+        Some(x) if !x.is_empty() => x,
+        _ => output_dir,
+    };
+    let raw = Arc::new(RawSink::create(&raw_path(raw_dir, js_id, "nlp_user_timeline_land", &dt[0..19]))?);
+    let client = TwitterClient::from_config(&config)?.with_raw_sink(Arc::clone(&raw));
+
+    // search_text is one username or several persons of interest, comma separated
+    let usernames: Vec<&str> = target.split(',')
+        .map(|x| x.trim().trim_start_matches('@'))
+        .filter(|x| !x.is_empty())
+        .collect();
+    info!("main|usernames={}", usernames.len());
+
+    let result = client.users_lookup_batch(
+        &usernames,
     ).await;
 
-    let user_id = match result {
-        Ok(data) =>  { 
-            let user = match data["data"].as_array() {
-                Some(user) => user,
-                None => panic!("main|ERR: unable to parse data object"),
-            };
-
-            let mut user_id = user[0]["id"].to_string();
-            user_id = user_id[1..user_id.len()-1].to_string();
-            user_id
-        },
+    let lookup = match result {
+        Ok(data) => data,
         Err(err) => {
-            // update failure
-            panic!("main|username {} cannot be looked up|err={}", target, err)
+            info!("main|usernames {} cannot be looked up", target);
+            update_step_status(&conn, js_id, &err);
+            return match err.is_skippable() {
+                true => Ok(()),
+                false => Err(err.into()),
+            };
         },
     };
 
-    let df = user_timeline(
-        config.get("bearer_token").expect("ERR: bearer_token is invalid"),
-        &user_id,
-    ).await;
+    for x in lookup.missing_ids() {
+        info!("main|username {} cannot be looked up", x);
+    }
+
+    let user_ids: Vec<&str> = lookup.df.column("user_id")?
+        .utf8()?
+        .into_no_null_iter()
+        .collect();
+
+    if user_ids.is_empty() {
+        let err = CtNlpError::NotFound(format!("users_lookup_batch|usernames={}", target));
+        update_step_status(&conn, js_id, &err);
+        return Ok(());
+    }
+
+    let limit = PageLimit {
+        max_pages: config.get("max_pages").and_then(|x| x.parse().ok()),
+        max_rows: config.get("max_rows").and_then(|x| x.parse().ok()),
+    };
+
+    // timelines are fetched concurrently, conf [twitter_concurrency] bounds the requests in flight
+    let df = client.user_timelines(
+        &user_ids,
+        &QueryOptions::default(),
+        limit,
+    ).await.into_frame();
 
     match Path::new(&output_dir).exists() {
         true => info!("main|output_dir={}", output_dir),
@@ -149,19 +199,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     match df {
-        Ok(frame) => {
+        Ok(Some(frame)) => {
             let mut out_df: polars::frame::DataFrame = frame;
-            let output_file = File::create(out_path).expect("main|ERR: cannot create output file");
-            match ParquetWriter::new(output_file)
-                .finish(&mut out_df) 
-            {
-                Ok(_) => info!("main|file created successfully"),
-                Err(err) => info!("main|ERR: unable to write to file|err={}", err),
+            let written = File::create(&out_path)
+                .map_err(|e| e.to_string())
+                .and_then(|x| ParquetWriter::new(x).finish(&mut out_df).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                info!("main|ERR: unable to write to file|out_path={}|e={}", out_path, e);
+                let _ = std::fs::remove_file(&out_path);
+                let err = CtNlpError::Parse(format!("unable to write {}|{}", out_path, e));
+                update_step_status(&conn, js_id, &err);
+                return Err(err.into());
             }
+            info!("main|file created successfully");
         },
-        Err(err) => { 
-            // update failure
-            panic!("main|ERR: unable to write to file|err={}", err);
+        Ok(None) => info!("main|nothing to land"),
+        Err(err) => {
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
+        },
+    }
+
+    // the raw copy is part of the landing, without it the step has not landed
+    match raw.finish() {
+        Ok(pages) => info!("main|raw pages landed|pages={}", pages),
+        Err(err) => {
+            info!("main|ERR: unable to finish raw landing|e={}", err);
+            update_step_status(&conn, js_id, &err);
+            return Err(err.into());
         },
     }
 
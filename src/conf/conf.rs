@@ -39,6 +39,9 @@ pub fn init_logger(file_path: &str) {
 
 /// Utility method to parse custom 
 /// cli args for cli tool
+/// start_time / end_time are RFC3339, since_id / until_id are tweet ids
+/// granularity is minute, hour or day, scope is recent or all
+/// file is one tweet id / username per line for the batch lookups
 pub fn parse_args() -> clap::ArgMatches {
     info!("parse_args|starting");
 
@@ -74,6 +77,37 @@ pub fn parse_args() -> clap::ArgMatches {
                 .short('d')
                 .takes_value(true)
                 .required(false),
+            Arg::new("start_time")
+                .long("start_time")
+                .takes_value(true)
+                .required(false),
+            Arg::new("end_time")
+                .long("end_time")
+                .takes_value(true)
+                .required(false),
+            Arg::new("since_id")
+                .long("since_id")
+                .takes_value(true)
+                .required(false),
+            Arg::new("until_id")
+                .long("until_id")
+                .takes_value(true)
+                .required(false),
+            Arg::new("granularity")
+                .long("granularity")
+                .short('g')
+                .takes_value(true)
+                .required(false),
+            Arg::new("scope")
+                .long("scope")
+                .short('s')
+                .takes_value(true)
+                .required(false),
+            Arg::new("file")
+                .long("file")
+                .short('f')
+                .takes_value(true)
+                .required(false),
             Arg::new("help")
                 .long("help")
                 .short('h'),])
@@ -86,39 +120,142 @@ pub fn parse_args() -> clap::ArgMatches {
 /// Utility method to parse custom 
 /// cli args for nlp_topic_land 
 pub fn parse_args1() -> clap::ArgMatches {
+    parse_bin_args("nlp_topic_land", base_args())
+}
+
+/// Utility fn for the args job_controller fills in for every landing step
+/// config, topic_id, output_dir and job_step_id
+pub fn base_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("conf")
+            .long("config")
+            .short('c')
+            .takes_value(true)
+            .required(true),
+        Arg::new("topic")
+            .long("topic_id")
+            .short('t')
+            .takes_value(true)
+            .required(true),
+        Arg::new("output")
+            .long("output_dir")
+            .short('o')
+            .takes_value(true)
+            .required(true),
+        Arg::new("job_step")
+            .long("job_step_id")
+            .short('j')
+            .takes_value(true)
+            .required(true),
+    ]
+}
+
+/// Utility fn for an optional day window
+/// start_date / end_date are YYYY-MM-DD, end_date is exclusive
+pub fn window_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("start_date")
+            .long("start_date")
+            .short('s')
+            .takes_value(true)
+            .required(false),
+        Arg::new("end_date")
+            .long("end_date")
+            .short('e')
+            .takes_value(true)
+            .required(false),
+    ]
+}
+
+/// Utility method to parse a bin's cli args, --help is added to args
+pub fn parse_bin_args(name: &str, mut args: Vec<Arg<'static>>) -> clap::ArgMatches {
     info!("parse_args|starting");
 
-    let cli_args = Command::new("nlp_topic_land")
-        .args(&[
-            Arg::new("conf")
-                .long("config")
-                .short('c')
-                .takes_value(true)
-                .required(true),
-            Arg::new("topic")
-                .long("topic_id")
-                .short('t')
-                .takes_value(true)
-                .required(true),
-            Arg::new("output")
-                .long("output_dir")
-                .short('o')
-                .takes_value(true)
-                .required(true),
-            Arg::new("job_step")
-                .long("job_step_id")
-                .short('j')
-                .takes_value(true)
-                .required(true),
-            Arg::new("help")
-                .long("help")
-                .short('h'),])
+    args.push(Arg::new("help")
+        .long("help")
+        .short('h'));
+    let cli_args = Command::new(name)
+        .args(args)
         .get_matches();
 
     info!("parse_args|completed");
     cli_args
 }
 
+/// Utility method to parse custom
+/// cli args for nlp_topic_land and nlp_topic_counts_land
+pub fn parse_nlp_topic_land_args() -> clap::ArgMatches {
+    parse_bin_args("nlp_topic_land", [base_args(), window_args()].concat())
+}
+
+/// Utility method to parse custom
+/// cli args for long-lived stream binaries
+pub fn parse_nlp_topic_stream_land_args() -> clap::ArgMatches {
+    parse_bin_args("nlp_topic_stream_land", vec![
+        Arg::new("conf")
+            .long("config")
+            .short('c')
+            .takes_value(true)
+            .required(true),
+    ])
+}
+
+/// Utility method to parse custom
+/// cli args for nlp_graph_land
+/// relation is followers, following, liking_users, retweeted_by or quote_tweets,
+/// defaults to --relation in the flow step's script_parameters
+pub fn parse_nlp_graph_land_args() -> clap::ArgMatches {
+    let mut args = base_args();
+    args.push(Arg::new("relation")
+        .long("relation")
+        .short('r')
+        .takes_value(true)
+        .required(false));
+    parse_bin_args("nlp_graph_land", args)
+}
+
+/// Utility method to parse custom
+/// cli args for land
+/// source is a registered source name, defaults to --source in the flow step's script_parameters
+/// start_date / end_date are YYYY-MM-DD, end_date is exclusive
+pub fn parse_land_args() -> clap::ArgMatches {
+    let mut args = base_args();
+    args.push(Arg::new("source")
+        .long("source")
+        .short('S')
+        .takes_value(true)
+        .required(false));
+    parse_bin_args("land", [args, window_args()].concat())
+}
+
+/// Flags a flow step can carry in script_parameters besides the standard ones job_controller fills in
+/// (flag, conf key), --abi <path> lands in config as evm_abi
+pub const SCRIPT_OPTIONS: [(&str, &str); 4] = [("--source", "source"), ("--abi", "evm_abi"), ("--events", "evm_events"), ("--relation", "relation")];
+
+/// Utility fn to read one flag out of a flow_step's script_parameters
+/// flags are space separated, the value follows as --flag value or --flag=value
+pub fn script_option(script_parameters: &str, flag: &str) -> Option<String> {
+    let mut tokens = script_parameters.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token == flag {
+            return tokens.next().filter(|x| !x.starts_with("--")).map(String::from);
+        }
+        if let Some(value) = token.strip_prefix(flag).and_then(|x| x.strip_prefix('=')) {
+            return Some(String::from(value));
+        }
+    }
+    None
+}
+
+/// Utility fn to overlay SCRIPT_OPTIONS found in script_parameters onto configuration.yaml
+pub fn apply_script_options(config: &mut BTreeMap<String, String>, script_parameters: &str) {
+    for (flag, key) in SCRIPT_OPTIONS {
+        if let Some(value) = script_option(script_parameters, flag) {
+            info!("apply_script_options|{}={}", key, value);
+            config.insert(String::from(key), value);
+        }
+    }
+}
 
 /// Utility fn to read and parse configuration.yaml
 pub fn get_config(config_name: &str) -> BTreeMap<String, String> {
@@ -0,0 +1,351 @@
+//! App-only credentials: static bearer tokens and api key / secret pairs
+//! Key / secret pairs are exchanged for a bearer token through oauth2/token
+//! (client credentials) and cached. The pool rotates to the next credential when
+//! one is rate limited and drops credentials that are revoked. Cassette replay skips the exchange
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::info;
+use reqwest::StatusCode;
+
+use crate::error::CtNlpError;
+use crate::rate_limit::MAX_RESET_WAIT;
+use crate::{parse_body, CassetteMode, TwitterClient};
+
+/// Shortest bench for a rate limited credential, whatever its reset says
+const MIN_BENCH: Duration = Duration::from_secs(1);
+
+/// One set of app-only credentials
+#[derive(Clone, PartialEq, Eq)]
+pub enum Credential {
+    /// bearer token issued up front, cannot be refreshed
+    Bearer(String),
+    /// api key / secret, exchanged for a bearer token on first use
+    App { api_key: String, api_secret: String },
+}
+
+impl Credential {
+    /// Safe to log, secrets are never printed
+    pub fn label(&self) -> String {
+        match self {
+            Credential::Bearer(token) => {
+                let tail: String = token.chars().rev().take(4).collect::<Vec<char>>().into_iter().rev().collect();
+                format!("bearer:...{}", tail)
+            },
+            Credential::App { api_key, .. } => format!("app:{}", api_key),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            Credential::Bearer(token) => !token.is_empty(),
+            Credential::App { api_key, api_secret } => !api_key.is_empty() && !api_secret.is_empty(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Credential({})", self.label())
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    credential: Credential,
+    /// bearer token in use, fetched for App credentials
+    token: Option<String>,
+    /// epoch seconds the credential's rate limit window resets
+    benched_until: Option<u64>,
+    revoked: bool,
+    /// an App token already re-fetched after a 401 since the last success
+    refetched: bool,
+}
+
+/// What the client should do to get a usable bearer token
+#[derive(Debug)]
+pub(crate) enum Next {
+    Ready(usize, String),
+    /// exchange the key / secret of this slot for a token
+    Fetch(usize, String, String),
+    /// every credential is rate limited, the soonest resets after this wait
+    Wait(Duration),
+    Exhausted,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    slots: Vec<Slot>,
+    current: usize,
+}
+
+/// Credentials shared by every request a TwitterClient sends
+#[derive(Debug)]
+pub struct CredentialPool {
+    state: Mutex<PoolState>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_secs())
+}
+
+/// comma separated conf value, blanks dropped
+fn conf_list<'a>(config: &'a BTreeMap<String, String>, key: &str) -> Vec<&'a str> {
+    config.get(key)
+        .map(|x| x.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+impl CredentialPool {
+    pub fn new(credentials: Vec<Credential>) -> Result<Self, CtNlpError> {
+        if credentials.is_empty() {
+            return Err(CtNlpError::Auth("CredentialPool|no credentials".into()));
+        }
+
+        if let Some(x) = credentials.iter().find(|x| !x.is_valid()) {
+            return Err(CtNlpError::Auth(format!("CredentialPool|credential is not valid|{}", x.label())));
+        }
+
+        let slots = credentials.into_iter()
+            .map(|credential| Slot {
+                token: match &credential {
+                    Credential::Bearer(token) => Some(token.clone()),
+                    Credential::App { .. } => None,
+                },
+                credential,
+                benched_until: None,
+                revoked: false,
+                refetched: false,
+            })
+            .collect();
+
+        Ok(Self { state: Mutex::new(PoolState { slots, current: 0 }) })
+    }
+
+    /// Read credentials from configuration.yaml, in this order:
+    /// bearer_token, bearer_tokens (comma separated),
+    /// twitter_api_key + twitter_api_secret, twitter_credentials (comma separated key:secret)
+    pub fn from_config(config: &BTreeMap<String, String>) -> Result<Self, CtNlpError> {
+        let mut credentials = vec![];
+
+        for token in conf_list(config, "bearer_token").into_iter().chain(conf_list(config, "bearer_tokens")) {
+            credentials.push(Credential::Bearer(token.to_string()));
+        }
+
+        match (config.get("twitter_api_key"), config.get("twitter_api_secret")) {
+            (Some(key), Some(secret)) => credentials.push(Credential::App { api_key: key.clone(), api_secret: secret.clone() }),
+            (None, None) => (),
+            _ => return Err(CtNlpError::Auth("CredentialPool|conf [twitter_api_key] and [twitter_api_secret] go together".into())),
+        }
+
+        for pair in conf_list(config, "twitter_credentials") {
+            match pair.split_once(':') {
+                Some((key, secret)) => credentials.push(Credential::App { api_key: key.to_string(), api_secret: secret.to_string() }),
+                None => return Err(CtNlpError::Auth("CredentialPool|conf [twitter_credentials] expects key:secret pairs".into())),
+            }
+        }
+
+        if credentials.is_empty() {
+            return Err(CtNlpError::Auth("CredentialPool|conf [bearer_token] or [twitter_api_key] is invalid".into()));
+        }
+
+        Self::new(credentials)
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().map_or(0, |x| x.slots.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Credentials neither revoked nor waiting on a rate limit reset
+    pub fn available(&self) -> usize {
+        let now = now_secs();
+        self.state.lock().map_or(0, |x| x.slots.iter().filter(|s| s.is_usable(now)).count())
+    }
+
+    /// Credential requests are currently sent with
+    pub fn current(&self) -> Option<Credential> {
+        let state = self.state.lock().ok()?;
+        state.slots.get(state.current).map(|x| x.credential.clone())
+    }
+
+    /// Pick the current credential, rotating past revoked and rate limited ones
+    pub(crate) fn next(&self) -> Next {
+        let now = now_secs();
+        let mut state = match self.state.lock() {
+            Ok(x) => x,
+            Err(_) => return Next::Exhausted,
+        };
+
+        let n = state.slots.len();
+        let start = state.current;
+        for i in (0..n).map(|x| (start + x) % n) {
+            let slot = &state.slots[i];
+            if !slot.is_usable(now) { continue; }
+
+            let next = match (&slot.token, &slot.credential) {
+                (Some(token), _) => Next::Ready(i, token.clone()),
+                (None, Credential::App { api_key, api_secret }) => Next::Fetch(i, api_key.clone(), api_secret.clone()),
+                (None, Credential::Bearer(_)) => continue,
+            };
+
+            if i != start {
+                info!("CredentialPool|rotated|{} -> {}", state.slots[start].credential.label(), state.slots[i].credential.label());
+                state.current = i;
+            }
+            return next;
+        }
+
+        match state.slots.iter().filter(|s| !s.revoked).filter_map(|s| s.benched_until).min() {
+            Some(reset) => Next::Wait(Duration::from_secs(reset.saturating_sub(now) + 1).min(MAX_RESET_WAIT)),
+            None => Next::Exhausted,
+        }
+    }
+
+    pub(crate) fn set_token(&self, i: usize, token: String) {
+        if let Ok(mut state) = self.state.lock() {
+            state.slots[i].token = Some(token);
+        }
+    }
+
+    /// The request sent with slot i succeeded
+    pub(crate) fn succeeded(&self, i: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            state.slots[i].refetched = false;
+        }
+    }
+
+    /// Slot i hit its rate limit, bench it until reset
+    /// true when another credential can take the request right away
+    pub(crate) fn rate_limited(&self, i: usize, reset: Option<u64>) -> bool {
+        let now = now_secs();
+        let mut state = match self.state.lock() {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+
+        // a missing, past or stale reset still benches the slot for MIN_BENCH
+        let until = reset.filter(|x| *x > now).unwrap_or(now + MIN_BENCH.as_secs());
+        state.slots[i].benched_until = Some(until);
+        info!("CredentialPool|rate limited|{}|reset={}", state.slots[i].credential.label(), until);
+
+        state.slots.iter().any(|s| s.is_usable(now))
+    }
+
+    /// Slot i was refused with a 401
+    /// An App token is re-fetched once, anything else is revoked for the life of the pool
+    /// true when the request should be sent again
+    pub(crate) fn unauthorized(&self, i: usize) -> bool {
+        let now = now_secs();
+        let mut state = match self.state.lock() {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+
+        let slot = &mut state.slots[i];
+        match (&slot.credential, slot.refetched) {
+            (Credential::App { .. }, false) => {
+                info!("CredentialPool|token refused|{}|re-fetching", slot.credential.label());
+                slot.token = None;
+                slot.refetched = true;
+                true
+            },
+            _ => {
+                info!("CredentialPool|ERR: credential revoked|{}", slot.credential.label());
+                slot.revoked = true;
+                slot.token = None;
+                state.slots.iter().any(|s| s.is_usable(now))
+            },
+        }
+    }
+
+    /// Key / secret refused by oauth2/token
+    pub(crate) fn revoke(&self, i: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            info!("CredentialPool|ERR: credential revoked|{}", state.slots[i].credential.label());
+            state.slots[i].revoked = true;
+        }
+    }
+}
+
+impl Slot {
+    fn is_usable(&self, now: u64) -> bool {
+        !self.revoked && self.benched_until.is_none_or(|x| x < now)
+    }
+}
+
+/// oauth2/token reply
+#[derive(Debug, serde::Deserialize)]
+struct TokenResponse {
+    token_type: String,
+    access_token: String,
+}
+
+impl TwitterClient {
+    /// Bearer token for the next request and the pool slot it came from
+    /// Key / secret pairs are exchanged on first use, refused ones are revoked
+    /// When every credential is rate limited this waits for the soonest reset
+    pub(crate) async fn bearer(&self) -> Result<(usize, String), CtNlpError> {
+        loop {
+            match self.auth.next() {
+                Next::Ready(i, token) => return Ok((i, token)),
+                Next::Fetch(i, api_key, api_secret) => {
+                    if let Some(token) = self.fetch_token(&api_key, &api_secret).await? {
+                        self.auth.set_token(i, token.clone());
+                        return Ok((i, token));
+                    }
+                    self.auth.revoke(i);
+                },
+                Next::Wait(wait) => {
+                    info!("bearer|every credential is rate limited|waiting {:?}", wait);
+                    tokio::time::sleep(wait).await;
+                },
+                Next::Exhausted => return Err(CtNlpError::Auth("bearer|every credential is revoked".into())),
+            }
+        }
+    }
+
+    /// Exchange an api key / secret for an app-only bearer token (client credentials)
+    /// None when the key / secret is refused
+    /// Replay never sends the token it gets, so no token is fetched (or recorded) for it
+    async fn fetch_token(&self, api_key: &str, api_secret: &str) -> Result<Option<String>, CtNlpError> {
+        info!("fetch_token|starting|api_key={}", api_key);
+
+        if self.cassette().is_some_and(|x| x.mode() == CassetteMode::Replay) {
+            info!("fetch_token|replay|no token request|api_key={}", api_key);
+            return Ok(Some(format!("replay_{}", api_key)));
+        }
+
+        let url = self.url("/oauth2/token");
+        let response = self.http.post(&url)
+            .basic_auth(api_key, Some(api_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                info!("fetch_token|ERR: credentials refused|status={}|api_key={}", response.status(), api_key);
+                return Ok(None);
+            },
+            s => {
+                info!("fetch_token|ERR: status={}", s);
+                return Err(CtNlpError::from_status(s, None));
+            },
+        }
+
+        let body: TokenResponse = parse_body(response).await?;
+        if !body.token_type.eq_ignore_ascii_case("bearer") {
+            return Err(CtNlpError::Parse(format!("fetch_token|unexpected token_type={}", body.token_type)));
+        }
+
+        info!("fetch_token|completed");
+        Ok(Some(body.access_token))
+    }
+}
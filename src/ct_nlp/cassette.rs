@@ -0,0 +1,182 @@
+//! Record / replay of api traffic
+//! Record writes the final response of every get_response / post_response call to
+//! {dir}/{METHOD}_{path}_{hash}.json, replay serves those files without touching the network
+//! Files are keyed on method, path, sorted query and body, never on base url or credentials,
+//! so a recording replays against any base url. Repeats of the same request are kept
+//! in order and replayed in order, the last one repeating once they run out
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CtNlpError;
+
+/// How a client treats cassettes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CassetteMode {
+    #[default]
+    Off,
+    Record,
+    Replay,
+}
+
+impl CassetteMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CassetteMode::Off => "off",
+            CassetteMode::Record => "record",
+            CassetteMode::Replay => "replay",
+        }
+    }
+}
+
+impl std::str::FromStr for CassetteMode {
+    type Err = CtNlpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(CassetteMode::Off),
+            "record" => Ok(CassetteMode::Record),
+            "replay" => Ok(CassetteMode::Replay),
+            x => Err(CtNlpError::Invalid(format!("CassetteMode|expected off, record or replay, cassette_mode={}", x))),
+        }
+    }
+}
+
+/// One request and the raw response it got
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<serde_json::Value>,
+    pub status: u16,
+    /// x-rate-limit-reset, kept so replayed 429s carry it
+    pub reset: Option<u64>,
+    pub response: String,
+}
+
+/// Every recorded repeat of one request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// A directory of cassette files
+#[derive(Debug)]
+pub struct Cassette {
+    mode: CassetteMode,
+    dir: PathBuf,
+    /// replay position per file
+    played: Mutex<HashMap<String, usize>>,
+    /// files written by this cassette, concurrent requests append to them one at a time
+    recording: Mutex<HashSet<String>>,
+}
+
+/// 64-bit FNV-1a, stable across builds unlike DefaultHasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+impl Cassette {
+    pub fn new(mode: CassetteMode, dir: &str) -> Result<Self, CtNlpError> {
+        if mode != CassetteMode::Off && dir.is_empty() {
+            return Err(CtNlpError::Invalid(format!("Cassette|dir is not valid for cassette_mode={}", mode.as_str())));
+        }
+
+        if mode == CassetteMode::Record {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| CtNlpError::Invalid(format!("Cassette|unable to create dir={}|e={}", dir, e)))?;
+        }
+
+        if mode == CassetteMode::Replay && !Path::new(dir).is_dir() {
+            return Err(CtNlpError::Invalid(format!("Cassette|dir does not exist, dir={}", dir)));
+        }
+
+        Ok(Self { mode, dir: PathBuf::from(dir), played: Mutex::new(HashMap::new()), recording: Mutex::new(HashSet::new()) })
+    }
+
+    /// cassette_mode (off, record, replay, default off) and cassette_dir from configuration.yaml
+    /// None when cassettes are off
+    pub fn from_config(config: &std::collections::BTreeMap<String, String>) -> Result<Option<Self>, CtNlpError> {
+        let mode: CassetteMode = config.get("cassette_mode").map_or(Ok(CassetteMode::Off), |x| x.parse())?;
+        if mode == CassetteMode::Off {
+            return Ok(None);
+        }
+
+        let dir = config.get("cassette_dir")
+            .ok_or_else(|| CtNlpError::Invalid(format!("Cassette|conf [cassette_dir] is required for cassette_mode={}", mode.as_str())))?;
+
+        info!("Cassette|cassette_mode={}|cassette_dir={}", mode.as_str(), dir);
+        Self::new(mode, dir).map(Some)
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File name for a request, e.g. GET_2_tweets_search_recent_8c1f0e2d3a4b5c6d.json
+    pub fn file_name(method: &str, path: &str, query: &[(String, String)], body: Option<&serde_json::Value>) -> String {
+        let mut sorted = query.to_vec();
+        sorted.sort();
+
+        let key = format!("{} {} {:?} {}", method, path, sorted, body.map(|x| x.to_string()).unwrap_or_default());
+        let slug: String = path.trim_matches('/')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        format!("{}_{}_{:016x}.json", method, slug, fnv1a(key.as_bytes()))
+    }
+
+    /// Append an interaction to its file
+    /// The first interaction a cassette records for a file replaces what an earlier session left there
+    pub fn record(&self, interaction: Interaction) -> Result<(), CtNlpError> {
+        let name = Self::file_name(&interaction.method, &interaction.path, &interaction.query, interaction.body.as_ref());
+        let path = self.dir.join(&name);
+
+        let mut recorded = self.recording.lock()
+            .map_err(|_| CtNlpError::Invalid("Cassette|record state poisoned".into()))?;
+        let mut file: CassetteFile = match recorded.insert(name.clone()) {
+            true => CassetteFile::default(),
+            false => serde_json::from_str(&std::fs::read_to_string(&path)
+                .map_err(|e| CtNlpError::Invalid(format!("Cassette|unable to read {}|e={}", path.display(), e)))?)?,
+        };
+        file.interactions.push(interaction);
+
+        std::fs::write(&path, serde_json::to_string_pretty(&file)?)
+            .map_err(|e| CtNlpError::Invalid(format!("Cassette|unable to write {}|e={}", path.display(), e)))?;
+
+        info!("Cassette|recorded|{}|interactions={}", name, file.interactions.len());
+        Ok(())
+    }
+
+    /// Next recorded interaction for a request
+    pub fn replay(&self, method: &str, path: &str, query: &[(String, String)], body: Option<&serde_json::Value>) -> Result<Interaction, CtNlpError> {
+        let name = Self::file_name(method, path, query, body);
+        let file_path = self.dir.join(&name);
+
+        let file: CassetteFile = match std::fs::read_to_string(&file_path) {
+            Ok(x) => serde_json::from_str(&x)?,
+            Err(_) => return Err(CtNlpError::Invalid(format!("Cassette|no recording for {} {}|file={}", method, path, name))),
+        };
+
+        let mut played = self.played.lock()
+            .map_err(|_| CtNlpError::Invalid("Cassette|replay state poisoned".into()))?;
+        let n = played.entry(name.clone()).or_insert(0);
+
+        let interaction = file.interactions.get(*n).or(file.interactions.last()).cloned()
+            .ok_or_else(|| CtNlpError::Invalid(format!("Cassette|empty recording|file={}", name)))?;
+        *n += 1;
+
+        info!("Cassette|replayed|{}|status={}", name, interaction.status);
+        Ok(interaction)
+    }
+}
@@ -1,285 +1,1105 @@
 use reqwest::StatusCode;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use serde::de::DeserializeOwned;
 use log::info;
 
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
 use polars::prelude::NamedFrom;
 use polars::series::Series;
 use polars::frame::DataFrame;
 
-pub type ResponseMap = HashMap<String, Vec<serde_json::Value>>;
+pub mod auth;
+pub mod cassette;
+pub mod error;
+pub mod fanout;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod models;
+pub mod query;
+pub mod rate_limit;
+pub mod raw;
+pub mod stream;
+pub mod thread;
+
+pub use auth::{Credential, CredentialPool};
+pub use cassette::{Cassette, CassetteMode};
+pub use error::CtNlpError;
+pub use fanout::{FanOut, DEFAULT_CONCURRENCY};
+pub use models::{ApiError, Includes, MatchingRule, Meta, PublicMetrics, ReferencedTweet, Response, StreamRule, StreamTweet, Tweet, TweetCount, User, UserMetrics};
+pub use query::{topic_query, SearchQuery};
+pub use rate_limit::{rate_limit, rate_limits, RateLimit};
+pub use raw::{raw_path, RawSink};
+pub use stream::{ReconnectPolicy, RuleSync, StreamControl, StreamEvent, StreamStats};
+pub use thread::threads_to_df;
+
+use rate_limit::{backoff, MAX_RETRIES};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.twitter.com";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_USER_AGENT: &str = concat!("honey-faucet/", env!("CARGO_PKG_VERSION"));
+
+/// ids / usernames the lookup endpoints accept per request
+pub const LOOKUP_BATCH_SIZE: usize = 100;
+
+/// tweet.fields requested by every tweet endpoint, see tweets_to_df
+pub const TWEET_FIELDS: &str = "author_id,created_at,id,text,public_metrics,lang,conversation_id,in_reply_to_user_id,referenced_tweets";
+
+/// user.fields requested alongside expansions=author_id, see tweets_to_df
+pub const USER_FIELDS: &str = "created_at,description,name,public_metrics,username,verified";
+
+/// Caller-specified bounds on a paginated query
+/// None on both follows next_token until the results are exhausted
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageLimit {
+    pub max_pages: Option<usize>,
+    pub max_rows: Option<usize>,
+}
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Tweet {
-    timestamp: u32,
-    id: u32,
-    user: String,
+impl PageLimit {
+    pub fn pages(n: usize) -> Self {
+        Self { max_pages: Some(n), max_rows: None }
+    }
+
+    pub fn rows(n: usize) -> Self {
+        Self { max_pages: None, max_rows: Some(n) }
+    }
 }
 
-pub async fn get_response(
-    bearer_token: &str, 
-    url: &str, 
-    params: Vec<(&str, &str)>,
-) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    info!("get_response|starting");
+/// Bucket size for the counts (v2) endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    Minute,
+    Hour,
+    #[default]
+    Day,
+}
+
+impl Granularity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Granularity::Minute => "minute",
+            Granularity::Hour => "hour",
+            Granularity::Day => "day",
+        }
+    }
+}
 
-    let tw_client = reqwest::Client::new();
-    let mut response = tw_client.get(url)
-        .query(&params)
-        .header("Authorization", format!("Bearer {}", bearer_token))
-        .send()?;
+impl std::str::FromStr for Granularity {
+    type Err = CtNlpError;
 
-    match response.status() {
-        StatusCode::OK => info!("get_response|query success"),
-        s => return Err(format!("get_response|status={}", s).into()),
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minute" => Ok(Granularity::Minute),
+            "hour" => Ok(Granularity::Hour),
+            "day" => Ok(Granularity::Day),
+            x => Err(CtNlpError::Invalid(format!("Granularity|expected minute, hour or day, granularity={}", x))),
+        }
     }
+}
 
-    let result: serde_json::Value = match response.text() {
-        Ok(x) => serde_json::from_str(&x)?,
-        Err(e) => return Err(format!("get_response|ERR: unable to parse response object|e={}", e).into()),
-    };
+/// Which search index a query runs against
+/// Recent covers the last 7 days, All is full-archive (academic access)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchScope {
+    #[default]
+    Recent,
+    All,
+}
 
-    info!("get_response|completed");
-    Ok(result)
+impl SearchScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchScope::Recent => "recent",
+            SearchScope::All => "all",
+        }
+    }
 }
 
+impl std::str::FromStr for SearchScope {
+    type Err = CtNlpError;
 
-/// Utility method to query the users_lookup (v2) endpoint
-pub async fn users_lookup(bearer_token: &str, username: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    info!("users_lookup|starting");
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "recent" => Ok(SearchScope::Recent),
+            "all" => Ok(SearchScope::All),
+            x => Err(CtNlpError::Invalid(format!("SearchScope|expected recent or all, scope={}", x))),
+        }
+    }
+}
 
-    if bearer_token == "" {
-        return Err(format!("users_lookup|ERR: bearer token is not valid, bearer_token={}", bearer_token).into());
+/// Account / engagement graph endpoints, landed as edge lists
+/// followers and following take a user id, the rest a tweet id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Followers,
+    Following,
+    LikingUsers,
+    RetweetedBy,
+    QuoteTweets,
+}
+
+impl Relation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relation::Followers => "followers",
+            Relation::Following => "following",
+            Relation::LikingUsers => "liking_users",
+            Relation::RetweetedBy => "retweeted_by",
+            Relation::QuoteTweets => "quote_tweets",
+        }
     }
 
-    if username == "" {
-        return Err(format!("users_lookup|ERR: username is not valid, username={}", username).into());
+    /// edge_type written to the edge list, src <edge_type> dst
+    pub fn edge_type(&self) -> &'static str {
+        match self {
+            Relation::Followers | Relation::Following => "follows",
+            Relation::LikingUsers => "likes",
+            Relation::RetweetedBy => "retweets",
+            Relation::QuoteTweets => "quotes",
+        }
     }
 
-    let url = String::from("https://api.twitter.com/2/users/by");
-    info!("users_lookup|url={}", url);
+    /// true when the endpoint is keyed by a tweet id rather than a user id
+    pub fn is_tweet_relation(&self) -> bool {
+        !matches!(self, Relation::Followers | Relation::Following)
+    }
 
-    let params = vec![("usernames", username)];
-    let result = get_response(bearer_token, &url, params).await?;
+    fn path(&self, id: &str) -> String {
+        match self.is_tweet_relation() {
+            true => format!("/2/tweets/{}/{}", id, self.as_str()),
+            false => format!("/2/users/{}/{}", id, self.as_str()),
+        }
+    }
+}
 
-    info!("users_lookup|completed");
-    Ok(result)
+impl std::str::FromStr for Relation {
+    type Err = CtNlpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "followers" => Ok(Relation::Followers),
+            "following" => Ok(Relation::Following),
+            "liking_users" => Ok(Relation::LikingUsers),
+            "retweeted_by" => Ok(Relation::RetweetedBy),
+            "quote_tweets" => Ok(Relation::QuoteTweets),
+            x => Err(CtNlpError::Invalid(format!("Relation|expected followers, following, liking_users, retweeted_by or quote_tweets, relation={}", x))),
+        }
+    }
 }
 
+/// Only the id of a user or tweet, enough to build an edge
+#[derive(Debug, Clone, serde::Deserialize)]
+struct Node {
+    id: String,
+}
 
-/// Utility method to query the mentions timeline (v2) endpoint
-pub async fn mentions_timeline(bearer_token: &str, user_id: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
-    info!("mentions_timeline|starting");
+/// Optional bounds sent with search and timeline queries
+/// start_time is inclusive, end_time exclusive, both RFC3339
+/// since_id / until_id are exclusive tweet ids
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub since_id: Option<String>,
+    pub until_id: Option<String>,
+}
 
-    if bearer_token == "" {
-        return Err(format!("mentions_timeline|ERR: bearer token is not valid, bearer_token={}", bearer_token).into());
+impl QueryOptions {
+    pub fn window(start_time: &str, end_time: &str) -> Self {
+        Self {
+            start_time: Some(start_time.to_string()),
+            end_time: Some(end_time.to_string()),
+            ..Self::default()
+        }
     }
 
-    if user_id == "" {
-        return Err(format!("mentions_timeline|ERR: user id is not valid, user_id={}", user_id).into());
+    pub fn since(since_id: &str) -> Self {
+        Self { since_id: Some(since_id.to_string()), ..Self::default() }
     }
 
-    let url = format!("https://api.twitter.com/2/users/{id}/mentions", id=user_id);
-    info!("mentions_timeline|url={:?}", url);
+    /// Query params for the bounds that are set, empty values are rejected
+    pub fn params(&self) -> Result<Vec<(&str, &str)>, CtNlpError> {
+        let fields = [
+            ("start_time", &self.start_time),
+            ("end_time", &self.end_time),
+            ("since_id", &self.since_id),
+            ("until_id", &self.until_id),
+        ];
+
+        let mut params = vec![];
+        for (name, value) in fields {
+            match value.as_deref() {
+                Some("") => return Err(CtNlpError::Invalid(format!("QueryOptions|{} is not valid", name))),
+                Some(x) => params.push((name, x)),
+                None => (),
+            }
+        }
+
+        Ok(params)
+    }
+}
 
-    let params = vec![
-        ("expansions", "author_id"),
-        ("tweet.fields", "author_id,created_at,text"),
-        ("max_results", "100"),
-    ];
+/// Result of a batch lookup
+/// df holds what was found, missing the not-found / suspended errors per id
+#[derive(Debug, Clone)]
+pub struct BatchLookup {
+    pub df: DataFrame,
+    pub missing: Vec<ApiError>,
+}
 
-    let result = get_response(&bearer_token, &url, params).await?;
+impl BatchLookup {
+    /// ids / usernames the api had no data for
+    pub fn missing_ids(&self) -> Vec<&str> {
+        self.missing.iter()
+            .filter_map(|e| e.resource_id.as_deref().or(e.value.as_deref()))
+            .collect()
+    }
+}
 
-    let data = match result["data"].as_array() {
-        Some(x) => x,
-        _ => return Err(format!("mentions_timeline|ERR: unable to parse data object").into()),
-    };
+/// Twitter (v2) api client
+/// Holds the pooled http client and credentials, so one instance should be shared per process
+/// clones share the same credential pool
+#[derive(Debug, Clone)]
+pub struct TwitterClient {
+    http: reqwest::Client,
+    /// no overall timeout, the filtered stream stays open for hours
+    stream_http: reqwest::Client,
+    auth: Arc<CredentialPool>,
+    cassette: Option<Arc<Cassette>>,
+    raw: Option<Arc<RawSink>>,
+    base_url: String,
+    timeout: Duration,
+    user_agent: String,
+    /// requests in flight for the fan-out methods, see fanout
+    concurrency: usize,
+}
 
-    let mut author_vec: Vec<String> = vec![];
-    //let mut username_vec: Vec<String> = vec![];
-    let mut created_vec: Vec<String> = vec![];
-    let mut id_vec: Vec<String> = vec![];
-    let mut text_vec: Vec<String> = vec![];
+impl TwitterClient {
+    pub fn new(
+        bearer_token: &str,
+        base_url: &str,
+        timeout: Duration,
+        user_agent: &str,
+    ) -> Result<Self, CtNlpError> {
+        if bearer_token.is_empty() {
+            return Err(CtNlpError::Auth("TwitterClient|bearer token is not valid".into()));
+        }
+
+        Self::with_credentials(CredentialPool::new(vec![Credential::Bearer(bearer_token.to_string())])?, base_url, timeout, user_agent)
+    }
 
-    for tweet in data {
-        let mut author_id = tweet["author_id"].to_string();
-        let mut created_at = tweet["created_at"].to_string();
-        let mut id = tweet["id"].to_string();
-        let mut text = tweet["text"].to_string();
+    /// Client over a pool of credentials, rotated on 429 and revocation
+    pub fn with_credentials(
+        pool: CredentialPool,
+        base_url: &str,
+        timeout: Duration,
+        user_agent: &str,
+    ) -> Result<Self, CtNlpError> {
+        if base_url.is_empty() {
+            return Err(CtNlpError::Invalid("TwitterClient|base url is not valid".into()));
+        }
+
+        let mut headers = HeaderMap::new();
+        let agent = HeaderValue::from_str(user_agent)
+            .map_err(|e| CtNlpError::Invalid(format!("TwitterClient|user agent is not valid|e={}", e)))?;
+        headers.insert(USER_AGENT, agent);
+
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .default_headers(headers.clone())
+            .build()?;
+        let stream_http = reqwest::Client::builder()
+            .connect_timeout(timeout)
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Self {
+            http,
+            stream_http,
+            auth: Arc::new(pool),
+            cassette: None,
+            raw: None,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            timeout,
+            user_agent: user_agent.to_string(),
+            concurrency: DEFAULT_CONCURRENCY,
+        })
+    }
 
-        if (author_id == "") || (created_at == "") || (id == "") || (text == "") { continue; }
+    /// Build a client from configuration.yaml
+    /// bearer_token or twitter_api_key / twitter_api_secret is required, see CredentialPool::from_config,
+    /// twitter_base_url, twitter_timeout_secs, twitter_user_agent and twitter_concurrency are optional,
+    /// cassette_mode / cassette_dir record or replay traffic, see Cassette::from_config
+    pub fn from_config(config: &BTreeMap<String, String>) -> Result<Self, CtNlpError> {
+        let pool = CredentialPool::from_config(config)?;
+        let base_url = config.get("twitter_base_url").map_or(DEFAULT_BASE_URL, |x| x.as_str());
+        let user_agent = config.get("twitter_user_agent").map_or(DEFAULT_USER_AGENT, |x| x.as_str());
+        let timeout = match config.get("twitter_timeout_secs") {
+            Some(x) => Duration::from_secs(x.parse().map_err(|_| CtNlpError::Invalid(format!("TwitterClient|conf [twitter_timeout_secs] is invalid|value={}", x)))?),
+            None => DEFAULT_TIMEOUT,
+        };
+
+        let concurrency = match config.get("twitter_concurrency") {
+            Some(x) => x.parse().ok().filter(|n| *n > 0)
+                .ok_or_else(|| CtNlpError::Invalid(format!("TwitterClient|conf [twitter_concurrency] is invalid|value={}", x)))?,
+            None => DEFAULT_CONCURRENCY,
+        };
+
+        let client = Self::with_credentials(pool, base_url, timeout, user_agent)?.with_concurrency(concurrency);
+        match Cassette::from_config(config)? {
+            Some(cassette) => Ok(client.with_cassette(cassette)),
+            None => Ok(client),
+        }
+    }
 
-        author_id = author_id[1..author_id.len()-1].to_string();
-        created_at = created_at[1..created_at.len()-1].to_string();
-        id = id[1..id.len()-1].to_string();
-        text = text[1..text.len()-1].to_string();
+    /// Record to or replay from cassette for every get_response / post_response call
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = match cassette.mode() {
+            CassetteMode::Off => None,
+            _ => Some(Arc::new(cassette)),
+        };
+        self
+    }
 
-        author_vec.push(author_id);
-        created_vec.push(created_at);
-        id_vec.push(id);
-        text_vec.push(text);
+    pub fn cassette(&self) -> Option<&Cassette> {
+        self.cassette.as_deref()
     }
 
-    let df = DataFrame::new(vec![
-        Series::new("tweet_id", id_vec),
-        Series::new("author_id", author_vec),
-        Series::new("text", text_vec),
-        Series::new("created_at", created_vec),
-    ])?;
+    /// Append every successful response page to sink, untouched
+    pub fn with_raw_sink(mut self, sink: Arc<RawSink>) -> Self {
+        self.raw = Some(sink);
+        self
+    }
 
-    println!("{:?}", df);
-    info!("mentions_timeline|completed");
-    Ok(df)
-}
+    /// Most requests the fan-out methods keep in flight, at least 1
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
 
-/// Utility method to query the user timeline (v2) endpoint
-pub async fn user_timeline(bearer_token: &str, user_id: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
-    info!("user_timeline|starting");
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
 
-    if bearer_token == "" {
-        return Err(format!("user_timeline|ERR: bearer token is not valid, bearer_token={}", bearer_token).into());
+    pub fn credentials(&self) -> &CredentialPool {
+        &self.auth
     }
 
-    if user_id == "" {
-       return Err(format!("user_timeline|ERR: user id is not valid, user_id={}", user_id).into());
+    pub fn base_url(&self) -> &str {
+        &self.base_url
     }
 
-    let url = format!("https://api.twitter.com/2/users/{id}/tweets", id=user_id);
-    info!("user_timeline|url={:?}", url);
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
 
-    let params = vec![
-        ("expansions", "author_id"),
-        ("tweet.fields", "author_id,created_at,text"),
-        ("max_results", "100"),
-    ];
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
 
-    let result = get_response(&bearer_token, &url, params).await?;
+    /// Absolute url for an api path, e.g. /2/tweets/search/recent
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
 
-    let data = match result["data"].as_array() {
-        Some(x) => x,
-        _ => return Err(format!("user_timeline|ERR: unable to parse data object").into()),
-    };
+    /// Utility method to GET a (v2) endpoint and deserialize the body
+    /// 429 waits for x-rate-limit-reset, 5xx backs off exponentially,
+    /// both are retried up to MAX_RETRIES times
+    pub async fn get_response<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: Vec<(&str, &str)>,
+    ) -> Result<T, CtNlpError> {
+        info!("get_response|starting");
+
+        let body = self.exchange("GET", url, &params, None, || self.http.get(url).query(&params)).await?;
+        let result = serde_json::from_str(&body)?;
+
+        info!("get_response|completed");
+        Ok(result)
+    }
 
-    let mut author_vec: Vec<String> = vec![];
-    //let mut username_vec: Vec<String> = vec![];
-    let mut created_vec: Vec<String> = vec![];
-    let mut id_vec: Vec<String> = vec![];
-    let mut text_vec: Vec<String> = vec![];
+    /// Utility method to POST a json body to a (v2) endpoint and deserialize the reply
+    /// retried like get_response
+    pub async fn post_response<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: Vec<(&str, &str)>,
+        body: &serde_json::Value,
+    ) -> Result<T, CtNlpError> {
+        info!("post_response|starting");
+
+        let reply = self.exchange("POST", url, &params, Some(body), || self.http.post(url).query(&params).json(body)).await?;
+        let result = serde_json::from_str(&reply)?;
+
+        info!("post_response|completed");
+        Ok(result)
+    }
 
-    for tweet in data {
-        let mut author_id = tweet["author_id"].to_string();
-        let mut created_at = tweet["created_at"].to_string();
-        let mut id = tweet["id"].to_string();
-        let mut text = tweet["text"].to_string();
+    /// Raw body for a request, through the cassette when one is set
+    /// Recorded non-2xx statuses come back as the same CtNlpError a live call would give
+    /// Successful pages also go to the raw sink, when one is set
+    async fn exchange<F>(
+        &self,
+        method: &str,
+        url: &str,
+        params: &[(&str, &str)],
+        body: Option<&serde_json::Value>,
+        build: F,
+    ) -> Result<String, CtNlpError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let path = url.strip_prefix(self.base_url.as_str()).unwrap_or(url);
+
+        let (status, text) = match self.cassette.as_deref() {
+            Some(cassette) => self.exchange_cassette(cassette, method, url, params, body, build).await?,
+            None => {
+                let response = self.send(url, build).await?;
+                (response.status().as_u16(), read_body(response).await?)
+            },
+        };
+
+        if let Some(raw) = self.raw.as_deref() {
+            raw.write_page(method, path, params, status, &text)?;
+        }
+
+        Ok(text)
+    }
 
-        if (author_id == "") || (created_at == "") || (id == "") || (text == "") { continue; }
+    /// Record or replay one exchange, only 2xx bodies come back
+    async fn exchange_cassette<F>(
+        &self,
+        cassette: &Cassette,
+        method: &str,
+        url: &str,
+        params: &[(&str, &str)],
+        body: Option<&serde_json::Value>,
+        build: F,
+    ) -> Result<(u16, String), CtNlpError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let path = url.strip_prefix(self.base_url.as_str()).unwrap_or(url);
+        let query: Vec<(String, String)> = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        let (status, reset, text) = match cassette.mode() {
+            CassetteMode::Replay => {
+                let interaction = cassette.replay(method, path, &query, body)?;
+                (interaction.status, interaction.reset, interaction.response)
+            },
+            _ => {
+                let response = self.send_raw(url, build).await?;
+                let status = response.status().as_u16();
+                let reset = RateLimit::from_headers(response.headers()).reset;
+                let text = read_body(response).await?;
+
+                cassette.record(cassette::Interaction {
+                    method: method.to_string(),
+                    path: path.to_string(),
+                    query,
+                    body: body.cloned(),
+                    status,
+                    reset,
+                    response: text.clone(),
+                })?;
+                (status, reset, text)
+            },
+        };
+
+        match StatusCode::from_u16(status) {
+            Ok(s) if s.is_success() => Ok((status, text)),
+            Ok(s) => Err(CtNlpError::from_status(s, reset)),
+            Err(_) => Err(CtNlpError::Http(status)),
+        }
+    }
 
-        author_id = author_id[1..author_id.len()-1].to_string();
-        created_at = created_at[1..created_at.len()-1].to_string();
-        id = id[1..id.len()-1].to_string();
-        text = text[1..text.len()-1].to_string();
+    /// Send a request built by build, retrying 429 and 5xx up to MAX_RETRIES
+    /// Any 2xx comes back as the response, other statuses as a CtNlpError
+    async fn send<F>(&self, url: &str, build: F) -> Result<reqwest::Response, CtNlpError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let response = self.send_raw(url, build).await?;
+
+        match response.status() {
+            s if s.is_success() => info!("send|query success|status={}", s),
+            s => {
+                info!("send|ERR: status={}", s);
+                return Err(CtNlpError::from_status(s, rate_limit(url).and_then(|x| x.reset)));
+            },
+        }
+
+        Ok(response)
+    }
 
-        author_vec.push(author_id);
-        created_vec.push(created_at);
-        id_vec.push(id);
-        text_vec.push(text);
+    /// send without the status check, the final response after retries whatever its status
+    /// 429 and 401 move on to the next pooled credential first, if there is one
+    async fn send_raw<F>(&self, url: &str, build: F) -> Result<reqwest::Response, CtNlpError>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        if let Some(wait) = rate_limit(url).filter(|x| x.is_exhausted()).and_then(|x| x.until_reset()) {
+            let (slot, _) = self.bearer().await?;
+            if !self.auth.rate_limited(slot, rate_limit(url).and_then(|x| x.reset)) {
+                info!("send|rate limit exhausted|url={}|waiting {:?}", url, wait);
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        let mut attempt = 0;
+
+        let response = loop {
+            let (slot, token) = self.bearer().await?;
+            let response = build()
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await?;
+
+            let state = RateLimit::from_headers(response.headers());
+            rate_limit::record(url, state);
+
+            let status = response.status();
+            let wait = match status {
+                // a rotation is a retry too, a pool that keeps answering 429 cannot spin
+                StatusCode::TOO_MANY_REQUESTS if self.auth.rate_limited(slot, state.reset) && attempt < MAX_RETRIES => {
+                    attempt += 1;
+                    info!("send|status={}|retry {}/{} with the next credential", status, attempt, MAX_RETRIES);
+                    continue;
+                },
+                StatusCode::UNAUTHORIZED if self.auth.unauthorized(slot) => {
+                    info!("send|status={}|retrying with a fresh credential", status);
+                    continue;
+                },
+                StatusCode::TOO_MANY_REQUESTS => state.until_reset().unwrap_or_else(|| backoff(attempt)),
+                s if s.is_server_error() => backoff(attempt),
+                s => {
+                    if s.is_success() { self.auth.succeeded(slot); }
+                    break response;
+                },
+            };
+
+            if attempt >= MAX_RETRIES {
+                info!("send|ERR: retries exhausted|status={}|attempts={}", status, attempt + 1);
+                return Err(CtNlpError::from_status(status, state.reset));
+            }
+
+            attempt += 1;
+            info!("send|status={}|retry {}/{} in {:?}", status, attempt, MAX_RETRIES, wait);
+            tokio::time::sleep(wait).await;
+        };
+
+        Ok(response)
     }
 
-    let df = DataFrame::new(vec![
-        Series::new("tweet_id", id_vec),
-        Series::new("author_id", author_vec),
-        Series::new("text", text_vec),
-        Series::new("created_at", created_vec),
-    ])?;
+    /// Utility method to walk a paginated (v2) endpoint
+    /// token_param is next_token for search, pagination_token for timelines
+    /// Pages are merged into a single response, data is truncated to max_rows
+    pub async fn get_pages<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: Vec<(&str, &str)>,
+        token_param: &str,
+        limit: PageLimit,
+    ) -> Result<Response<Vec<T>>, CtNlpError> {
+        self.fetch_pages(url, params, token_param, limit, true).await
+    }
 
-    info!("user_timeline|completed");
-    Ok(df)
-}
+    /// sized=false leaves out max_results, the counts endpoints reject it
+    async fn fetch_pages<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: Vec<(&str, &str)>,
+        token_param: &str,
+        limit: PageLimit,
+        sized: bool,
+    ) -> Result<Response<Vec<T>>, CtNlpError> {
+        info!("get_pages|starting");
+
+        let mut result: Response<Vec<T>> = Response::empty();
+        let mut next_token: Option<String> = None;
+        let mut n = 0;
+
+        loop {
+            let remaining = limit.max_rows.map(|x| x.saturating_sub(result.len()));
+            let per_page = remaining.unwrap_or(100).clamp(10, 100).to_string();
+
+            let mut page_params = params.clone();
+            if sized {
+                page_params.push(("max_results", &per_page));
+            }
+            if let Some(token) = &next_token {
+                page_params.push((token_param, token));
+            }
+
+            let page: Response<Vec<T>> = self.get_response(url, page_params).await?;
+            result.extend(page);
+            n += 1;
+
+            next_token = result.meta.as_ref().and_then(|m| m.next_token.clone());
+            info!("get_pages|page={}|rows={}|next_token={:?}", n, result.len(), next_token);
+
+            if next_token.is_none() { break; }
+            if limit.max_pages.is_some_and(|x| n >= x) { break; }
+            if limit.max_rows.is_some_and(|x| result.len() >= x) { break; }
+        }
+
+        if let (Some(max_rows), Some(data)) = (limit.max_rows, result.data.as_mut()) {
+            data.truncate(max_rows);
+        }
+
+        info!("get_pages|completed");
+        Ok(result)
+    }
+
+    /// Utility method to query the users_lookup (v2) endpoint
+    pub async fn users_lookup(&self, username: &str) -> Result<Response<Vec<User>>, CtNlpError> {
+        info!("users_lookup|starting");
+
+        if username.is_empty() {
+            return Err(CtNlpError::Invalid(format!("users_lookup|username is not valid, username={}", username)));
+        }
+
+        let url = self.url("/2/users/by");
+        info!("users_lookup|url={}", url);
 
-/// Utility method to query tweet_lookup (v2) endpoint
-/// The response object is returned if valid
-pub async fn tweet_lookup(bearer_token: &str, tweet_id: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    info!("tweet_lookup|starting");
+        let params = vec![
+            ("usernames", username),
+            ("user.fields", USER_FIELDS),
+        ];
+        let result: Response<Vec<User>> = self.get_response(&url, params).await?;
+        let result = result.check("users_lookup")?;
 
-    if bearer_token == "" { 
-        return Err(format!("tweet_lookup|ERR: bearer token is not valid, bearer_token={}", bearer_token).into());
+        info!("users_lookup|completed");
+        Ok(result)
     }
-    
-    if tweet_id == "" { 
-        return Err(format!("tweet_lookup|ERR: tweet_id is not valid, tweet_id={}", tweet_id).into());
+
+    /// Utility method to query the mentions timeline (v2) endpoint
+    pub async fn mentions_timeline(
+        &self,
+        user_id: &str,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<DataFrame, CtNlpError> {
+        info!("mentions_timeline|starting");
+
+        if user_id.is_empty() {
+            return Err(CtNlpError::Invalid(format!("mentions_timeline|user id is not valid, user_id={}", user_id)));
+        }
+
+        let url = self.url(&format!("/2/users/{id}/mentions", id=user_id));
+        info!("mentions_timeline|url={:?}", url);
+
+        let mut params = vec![
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+        params.extend(options.params()?);
+
+        let result: Response<Vec<Tweet>> = self.get_pages(&url, params, "pagination_token", limit).await?;
+
+        let result = result.check("mentions_timeline")?;
+        let data = result.data.as_deref().unwrap_or_default();
+
+        let df = tweets_to_df(data, result.users())?;
+
+        info!("mentions_timeline|completed");
+        Ok(df)
+    }
+
+    /// Utility method to query the user timeline (v2) endpoint
+    pub async fn user_timeline(
+        &self,
+        user_id: &str,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<DataFrame, CtNlpError> {
+        info!("user_timeline|starting");
+
+        if user_id.is_empty() {
+           return Err(CtNlpError::Invalid(format!("user_timeline|user id is not valid, user_id={}", user_id)));
+        }
+
+        let url = self.url(&format!("/2/users/{id}/tweets", id=user_id));
+        info!("user_timeline|url={:?}", url);
+
+        let mut params = vec![
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+        params.extend(options.params()?);
+
+        let result: Response<Vec<Tweet>> = self.get_pages(&url, params, "pagination_token", limit).await?;
+
+        let result = result.check("user_timeline")?;
+        let data = result.data.as_deref().unwrap_or_default();
+
+        let df = tweets_to_df(data, result.users())?;
+
+        info!("user_timeline|completed");
+        Ok(df)
+    }
+
+    /// Utility method to query tweet_lookup (v2) endpoint
+    /// The response object is returned if valid
+    pub async fn tweet_lookup(&self, tweet_id: &str) -> Result<Response<Tweet>, CtNlpError> {
+        info!("tweet_lookup|starting");
+
+        if tweet_id.is_empty() {
+            return Err(CtNlpError::Invalid(format!("tweet_lookup|tweet_id is not valid, tweet_id={}", tweet_id)));
+        }
+
+        let url = self.url(&format!("/2/tweets/{}", tweet_id));
+        info!("tweet_lookup|url={:?}", url);
+
+        let params = vec![
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+
+        let result: Response<Tweet> = self.get_response(&url, params).await?;
+        let result = result.check("tweet_lookup")?;
+
+        info!("tweet_lookup|completed");
+        Ok(result)
+    }
+
+    /// Utility method to walk a lookup (v2) endpoint in LOOKUP_BATCH_SIZE chunks
+    /// param is the comma separated list, e.g. ids or usernames
+    async fn lookup_batches<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        param: &str,
+        values: &[&str],
+        params: Vec<(&str, &str)>,
+    ) -> Result<Response<Vec<T>>, CtNlpError> {
+        if values.is_empty() || values.iter().any(|x| x.is_empty()) {
+            return Err(CtNlpError::Invalid(format!("lookup_batches|{} is not valid, {}={:?}", param, param, values)));
+        }
+
+        let mut result: Response<Vec<T>> = Response::empty();
+        for (n, chunk) in values.chunks(LOOKUP_BATCH_SIZE).enumerate() {
+            let joined = chunk.join(",");
+            let mut batch_params = params.clone();
+            batch_params.push((param, &joined));
+
+            let batch: Response<Vec<T>> = self.get_response(url, batch_params).await?;
+            result.extend(batch);
+            info!("lookup_batches|batch={}|{}={}|found={}|errors={}", n + 1, param, chunk.len(), result.len(), result.errors.len());
+        }
+
+        Ok(result)
+    }
+
+    /// Utility method to query tweet lookup (v2) for many ids
+    /// ids are chunked to LOOKUP_BATCH_SIZE per request
+    /// df cols: see tweets_to_df
+    pub async fn tweet_lookup_batch(&self, tweet_ids: &[&str]) -> Result<BatchLookup, CtNlpError> {
+        info!("tweet_lookup_batch|starting");
+        info!("tweet_lookup_batch|ids={}", tweet_ids.len());
+
+        let url = self.url("/2/tweets");
+
+        let params = vec![
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+
+        let result: Response<Vec<Tweet>> = self.lookup_batches(&url, "ids", tweet_ids, params).await?;
+        let df = tweets_to_df(result.data.as_deref().unwrap_or_default(), result.users())?;
+        let lookup = BatchLookup { df, missing: result.errors };
+
+        info!("tweet_lookup_batch|found={}|missing={}", lookup.df.height(), lookup.missing.len());
+        info!("tweet_lookup_batch|completed");
+        Ok(lookup)
     }
 
-    let url = format!("https://api.twitter.com/2/tweets/{}", tweet_id);
-    info!("tweet_lookup|url={:?}", url);
+    /// Utility method to query users lookup (v2) for many usernames
+    /// usernames are chunked to LOOKUP_BATCH_SIZE per request
+    /// df cols: see users_to_df
+    pub async fn users_lookup_batch(&self, usernames: &[&str]) -> Result<BatchLookup, CtNlpError> {
+        info!("users_lookup_batch|starting");
+        info!("users_lookup_batch|usernames={}", usernames.len());
+
+        let url = self.url("/2/users/by");
 
-    let params = vec![
-        ("expansions", "author_id"),
-        ("tweet.fields", "author_id,created_at,text"),
-        ("user.fields", "name,username"),
-    ];
+        let params = vec![("user.fields", USER_FIELDS)];
 
-    let result = get_response(&bearer_token, &url, params).await?;
+        let result: Response<Vec<User>> = self.lookup_batches(&url, "usernames", usernames, params).await?;
+        let df = users_to_df(result.data.as_deref().unwrap_or_default())?;
+        let lookup = BatchLookup { df, missing: result.errors };
 
-    info!("tweet_lookup|completed");
-    Ok(result)
+        info!("users_lookup_batch|found={}|missing={}", lookup.df.height(), lookup.missing.len());
+        info!("users_lookup_batch|completed");
+        Ok(lookup)
+    }
+
+    /// Utility method to walk a graph (v2) endpoint into an edge list
+    /// followers: follower follows id, following: id follows user,
+    /// liking_users / retweeted_by: user likes / retweets id, quote_tweets: quote quotes id
+    /// cols: see edges_to_df
+    pub async fn relation_edges(&self, relation: Relation, id: &str, limit: PageLimit) -> Result<DataFrame, CtNlpError> {
+        info!("relation_edges|starting");
+        info!("relation_edges|relation={}|id={}", relation.as_str(), id);
+
+        if id.is_empty() {
+            return Err(CtNlpError::Invalid(format!("relation_edges|id is not valid, id={}", id)));
+        }
+
+        let url = self.url(&relation.path(id));
+        let observed_at = chrono::Utc::now().to_rfc3339();
+
+        let result: Response<Vec<Node>> = self.get_pages(&url, vec![], "pagination_token", limit).await?;
+        let result = result.check(relation.as_str())?;
+        let nodes = result.data.as_deref().unwrap_or_default();
+
+        let edges: Vec<(&str, &str)> = nodes.iter()
+            .map(|n| match relation {
+                Relation::Following => (id, n.id.as_str()),
+                _ => (n.id.as_str(), id),
+            })
+            .collect();
+
+        let df = edges_to_df(&edges, relation.edge_type(), &observed_at)?;
+
+        info!("relation_edges|edges={}", df.height());
+        info!("relation_edges|completed");
+        Ok(df)
+    }
+
+    /// Utility method to query the followers (v2) endpoint as edges
+    pub async fn followers(&self, user_id: &str, limit: PageLimit) -> Result<DataFrame, CtNlpError> {
+        self.relation_edges(Relation::Followers, user_id, limit).await
+    }
+
+    /// Utility method to query the following (v2) endpoint as edges
+    pub async fn following(&self, user_id: &str, limit: PageLimit) -> Result<DataFrame, CtNlpError> {
+        self.relation_edges(Relation::Following, user_id, limit).await
+    }
+
+    /// Utility method to query the liking_users (v2) endpoint as edges
+    pub async fn liking_users(&self, tweet_id: &str, limit: PageLimit) -> Result<DataFrame, CtNlpError> {
+        self.relation_edges(Relation::LikingUsers, tweet_id, limit).await
+    }
+
+    /// Utility method to query the retweeted_by (v2) endpoint as edges
+    pub async fn retweeted_by(&self, tweet_id: &str, limit: PageLimit) -> Result<DataFrame, CtNlpError> {
+        self.relation_edges(Relation::RetweetedBy, tweet_id, limit).await
+    }
+
+    /// Utility method to query the quote_tweets (v2) endpoint as edges
+    pub async fn quote_tweets(&self, tweet_id: &str, limit: PageLimit) -> Result<DataFrame, CtNlpError> {
+        self.relation_edges(Relation::QuoteTweets, tweet_id, limit).await
+    }
+
+    /// Utility method to query recents (v2) endpoint
+    /// Pages are followed up to limit and concatenated into one DataFrame
+    /// cols: see tweets_to_df
+    pub async fn get_recent_tweets(
+        &self,
+        topic: &str,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<DataFrame, CtNlpError> {
+        info!("get_recent_tweets|starting");
+        info!("get_recent_tweets|topic: {}", topic);
+
+        if topic.is_empty() {
+            return Err(CtNlpError::Invalid(format!("get_recent_tweets|topic is not valid, topic={}", topic)));
+        }
+
+        let url = self.url("/2/tweets/search/recent");
+
+        let mut params = vec![
+            ("query", topic),
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+        params.extend(options.params()?);
+
+        let result: Response<Vec<Tweet>> = self.get_pages(&url, params, "next_token", limit).await?;
+
+        let result = result.check("get_recent_tweets")?;
+        let data = result.data.as_deref().unwrap_or_default();
+
+        let df = tweets_to_df(data, result.users())?;
+
+        info!("get_recent_tweets|completed");
+        Ok(df)
+    }
+
+    /// Utility method to query the full-archive search (v2) endpoint
+    /// options must carry a start_time, the api otherwise defaults to the last 30 days
+    /// cols: see tweets_to_df
+    pub async fn search_all(
+        &self,
+        topic: &str,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<DataFrame, CtNlpError> {
+        info!("search_all|starting");
+        info!("search_all|topic={}|options={:?}", topic, options);
+
+        if topic.is_empty() {
+            return Err(CtNlpError::Invalid(format!("search_all|topic is not valid, topic={}", topic)));
+        }
+
+        if options.start_time.is_none() {
+            return Err(CtNlpError::Invalid("search_all|start_time is required".into()));
+        }
+
+        let url = self.url("/2/tweets/search/all");
+
+        let mut params = vec![
+            ("query", topic),
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+        params.extend(options.params()?);
+
+        let result: Response<Vec<Tweet>> = self.get_pages(&url, params, "next_token", limit).await?;
+        let result = result.check("search_all")?;
+        let data = result.data.as_deref().unwrap_or_default();
+
+        let df = tweets_to_df(data, result.users())?;
+
+        info!("search_all|completed");
+        Ok(df)
+    }
+
+    /// Utility method to query the counts (v2) endpoints
+    /// scope picks counts/recent or counts/all, pages are followed up to limit
+    /// cols: start, end, tweet_count
+    pub async fn get_tweet_counts(
+        &self,
+        topic: &str,
+        granularity: Granularity,
+        scope: SearchScope,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<DataFrame, CtNlpError> {
+        info!("get_tweet_counts|starting");
+        info!("get_tweet_counts|topic={}|granularity={}|scope={}|options={:?}", topic, granularity.as_str(), scope.as_str(), options);
+
+        if topic.is_empty() {
+            return Err(CtNlpError::Invalid(format!("get_tweet_counts|topic is not valid, topic={}", topic)));
+        }
+
+        let url = self.url(&format!("/2/tweets/counts/{}", scope.as_str()));
+
+        let mut params = vec![
+            ("query", topic),
+            ("granularity", granularity.as_str()),
+        ];
+        params.extend(options.params()?);
+
+        let result: Response<Vec<TweetCount>> = self.fetch_pages(&url, params, "next_token", limit, false).await?;
+        let result = result.check("get_tweet_counts")?;
+        info!("get_tweet_counts|buckets={}|total_tweet_count={:?}", result.len(), result.meta.as_ref().and_then(|m| m.total_tweet_count));
+
+        let df = counts_to_df(result.data.as_deref().unwrap_or_default())?;
+
+        info!("get_tweet_counts|completed");
+        Ok(df)
+    }
 }
 
+/// Utility method to flatten count buckets into a DataFrame
+/// cols: start, end, tweet_count (u64)
+pub fn counts_to_df(counts: &[TweetCount]) -> Result<DataFrame, CtNlpError> {
+    let df = DataFrame::new(vec![
+        Series::new("start", counts.iter().map(|x| x.start.as_str()).collect::<Vec<&str>>()),
+        Series::new("end", counts.iter().map(|x| x.end.as_str()).collect::<Vec<&str>>()),
+        Series::new("tweet_count", counts.iter().map(|x| x.tweet_count).collect::<Vec<u64>>()),
+    ])?;
 
-/// Utility method to query recents (v2) endpoint
-/// Additional parsing is done to seed a DataFrame
-/// cols: author_id, created_at, tweet_id, text
-pub async fn get_recent_tweets(bearer_token: &str, topic: &str, count: &str) -> Result<DataFrame, Box<dyn std::error::Error>> {
-    info!("get_recent_tweets|starting");
-    info!("get_recent_tweets|topic: {}", topic);
-
-    let mut author_vec: Vec<String> = vec![];
-    //let mut username_vec: Vec<String> = vec![];
-    let mut created_vec: Vec<String> = vec![];
-    let mut id_vec: Vec<String> = vec![];
-    let mut text_vec: Vec<String> = vec![];
-
-    if bearer_token == "" { panic!("error: bearer_token is not valid");  }
-        
-    let url = String::from("https://api.twitter.com/2/tweets/search/recent");
-
-    let params = vec![
-        ("query", topic),
-        ("tweet.fields", "author_id,created_at,id,text"),
-        ("user.fields", "name,username"),
-        ("max_results", "100"),
-    ];
-
-    let result = get_response(&bearer_token, &url, params).await?;    
-
-    /*
-    let meta = &tmp["meta"]; 
-    let next_token = &tmp["next_token"]; 
-    let oldest_id =  &tmp["oldest_id"];
-    let result_count = &tmp["result_count"]; 
-
-    let users = match tmp["includes"]["users"].as_array() {
-        Some(x) => x,
-        _ => panic!("error: unable to parse includes object from response"),
-    };
-    */
-
-    let data = match result["data"].as_array() {
-        Some(x) => x,
-        _ => panic!("error: unable to parse data object from response"),
-    };
-
-    for tweet in data {
-        let author_id = tweet["author_id"].to_string();
-        let created_at = tweet["created_at"].to_string();
-        let id = tweet["id"].to_string();
-        let text = tweet["text"].to_string();
-
-        if (author_id == "") || (created_at == "") || (id == "") || (text == "") { continue; }
+    Ok(df)
+}
+
+/// Utility method to read and deserialize a response body
+async fn parse_body<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, CtNlpError> {
+    Ok(serde_json::from_str(&read_body(response).await?)?)
+}
+
+async fn read_body(response: reqwest::Response) -> Result<String, CtNlpError> {
+    response.text()
+        .await
+        .map_err(|e| CtNlpError::Parse(format!("parse_body|unable to read response body|e={}", e)))
+}
+
+/// Utility method to flatten tweets into a DataFrame
+/// tweets missing author_id or created_at are skipped
+/// cols: tweet_id, author_id, text, created_at, lang, conversation_id,
+/// in_reply_to_user_id, referenced_tweet_type (first reference),
+/// like_count, retweet_count, reply_count, quote_count (u64, null without public_metrics),
+/// then the author from users: username, name, followers_count, following_count,
+/// verified, user_created_at, description (null when the author was not expanded)
+pub fn tweets_to_df(tweets: &[Tweet], users: &[User]) -> Result<DataFrame, CtNlpError> {
+    // users repeat across pages, keep the first expansion of each author
+    let mut authors: HashMap<&str, &User> = HashMap::new();
+    for user in users {
+        authors.entry(user.id.as_str()).or_insert(user);
+    }
+
+    let mut author_vec: Vec<&str> = vec![];
+    let mut created_vec: Vec<&str> = vec![];
+    let mut id_vec: Vec<&str> = vec![];
+    let mut text_vec: Vec<&str> = vec![];
+    let mut lang_vec: Vec<Option<&str>> = vec![];
+    let mut conversation_vec: Vec<Option<&str>> = vec![];
+    let mut reply_to_vec: Vec<Option<&str>> = vec![];
+    let mut reference_vec: Vec<Option<&str>> = vec![];
+    let mut like_vec: Vec<Option<u64>> = vec![];
+    let mut retweet_vec: Vec<Option<u64>> = vec![];
+    let mut reply_vec: Vec<Option<u64>> = vec![];
+    let mut quote_vec: Vec<Option<u64>> = vec![];
+    let mut username_vec: Vec<Option<&str>> = vec![];
+    let mut name_vec: Vec<Option<&str>> = vec![];
+    let mut followers_vec: Vec<Option<u64>> = vec![];
+    let mut following_vec: Vec<Option<u64>> = vec![];
+    let mut verified_vec: Vec<Option<bool>> = vec![];
+    let mut user_created_vec: Vec<Option<&str>> = vec![];
+    let mut description_vec: Vec<Option<&str>> = vec![];
+
+    for tweet in tweets {
+        let (author_id, created_at) = match (&tweet.author_id, &tweet.created_at) {
+            (Some(a), Some(c)) => (a, c),
+            _ => continue,
+        };
 
         author_vec.push(author_id);
         created_vec.push(created_at);
-        id_vec.push(id);
-        text_vec.push(text);
+        id_vec.push(&tweet.id);
+        text_vec.push(&tweet.text);
+        lang_vec.push(tweet.lang.as_deref());
+        conversation_vec.push(tweet.conversation_id.as_deref());
+        reply_to_vec.push(tweet.in_reply_to_user_id.as_deref());
+        reference_vec.push(tweet.referenced_tweets.first().map(|x| x.reference_type.as_str()));
+
+        let metrics = tweet.public_metrics.as_ref();
+        like_vec.push(metrics.map(|x| x.like_count));
+        retweet_vec.push(metrics.map(|x| x.retweet_count));
+        reply_vec.push(metrics.map(|x| x.reply_count));
+        quote_vec.push(metrics.map(|x| x.quote_count));
+
+        let author = authors.get(author_id.as_str());
+        let author_metrics = author.and_then(|u| u.public_metrics.as_ref());
+        username_vec.push(author.map(|u| u.username.as_str()));
+        name_vec.push(author.map(|u| u.name.as_str()));
+        followers_vec.push(author_metrics.map(|x| x.followers_count));
+        following_vec.push(author_metrics.map(|x| x.following_count));
+        verified_vec.push(author.and_then(|u| u.verified));
+        user_created_vec.push(author.and_then(|u| u.created_at.as_deref()));
+        description_vec.push(author.and_then(|u| u.description.as_deref()));
     }
 
     let df = DataFrame::new(vec![
@@ -287,36 +1107,79 @@ pub async fn get_recent_tweets(bearer_token: &str, topic: &str, count: &str) ->
         Series::new("author_id", author_vec),
         Series::new("text", text_vec),
         Series::new("created_at", created_vec),
+        Series::new("lang", lang_vec),
+        Series::new("conversation_id", conversation_vec),
+        Series::new("in_reply_to_user_id", reply_to_vec),
+        Series::new("referenced_tweet_type", reference_vec),
+        Series::new("like_count", like_vec),
+        Series::new("retweet_count", retweet_vec),
+        Series::new("reply_count", reply_vec),
+        Series::new("quote_count", quote_vec),
+        Series::new("username", username_vec),
+        Series::new("name", name_vec),
+        Series::new("followers_count", followers_vec),
+        Series::new("following_count", following_vec),
+        Series::new("verified", verified_vec),
+        Series::new("user_created_at", user_created_vec),
+        Series::new("description", description_vec),
     ])?;
 
-    info!("get_recent_tweets|completed");
     Ok(df)
 }
 
-/// Utility method to query counts (v2) endpoint
-/// The response object is returned if valid
-pub async fn get_tweet_counts(bearer_token: &str, topic: &str) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    info!("get_tweet_counts|starting");
-    info!("get_tweet_counts|topic={}", topic);
-
-    if bearer_token == "" { 
-        return Err(format!("get_tweet_counts|ERR: bearer_token is not valid, bearer_token={}", bearer_token).into()); 
-    }
+/// Utility method to build an edge list DataFrame
+/// cols: src_id, dst_id, edge_type, observed_at
+pub fn edges_to_df(edges: &[(&str, &str)], edge_type: &str, observed_at: &str) -> Result<DataFrame, CtNlpError> {
+    let df = DataFrame::new(vec![
+        Series::new("src_id", edges.iter().map(|(src, _)| *src).collect::<Vec<&str>>()),
+        Series::new("dst_id", edges.iter().map(|(_, dst)| *dst).collect::<Vec<&str>>()),
+        Series::new("edge_type", vec![edge_type; edges.len()]),
+        Series::new("observed_at", vec![observed_at; edges.len()]),
+    ])?;
 
-    if topic == "" {
-        return Err(format!("get_tweet_counts|ERR: topic is not valid, topic={}", topic).into());
-    }
+    Ok(df)
+}
 
-    let url = String::from("https://api.twitter.com/2/tweets/counts/recent");
+/// Utility method to flatten users into a DataFrame
+/// cols: user_id, username, name, followers_count, following_count,
+/// tweet_count, listed_count (u64, null without public_metrics),
+/// verified, created_at, description
+pub fn users_to_df(users: &[User]) -> Result<DataFrame, CtNlpError> {
+    let metrics: Vec<Option<&UserMetrics>> = users.iter().map(|u| u.public_metrics.as_ref()).collect();
 
+    let df = DataFrame::new(vec![
+        Series::new("user_id", users.iter().map(|u| u.id.as_str()).collect::<Vec<&str>>()),
+        Series::new("username", users.iter().map(|u| u.username.as_str()).collect::<Vec<&str>>()),
+        Series::new("name", users.iter().map(|u| u.name.as_str()).collect::<Vec<&str>>()),
+        Series::new("followers_count", metrics.iter().map(|x| x.map(|m| m.followers_count)).collect::<Vec<Option<u64>>>()),
+        Series::new("following_count", metrics.iter().map(|x| x.map(|m| m.following_count)).collect::<Vec<Option<u64>>>()),
+        Series::new("tweet_count", metrics.iter().map(|x| x.map(|m| m.tweet_count)).collect::<Vec<Option<u64>>>()),
+        Series::new("listed_count", metrics.iter().map(|x| x.map(|m| m.listed_count)).collect::<Vec<Option<u64>>>()),
+        Series::new("verified", users.iter().map(|u| u.verified).collect::<Vec<Option<bool>>>()),
+        Series::new("created_at", users.iter().map(|u| u.created_at.as_deref()).collect::<Vec<Option<&str>>>()),
+        Series::new("description", users.iter().map(|u| u.description.as_deref()).collect::<Vec<Option<&str>>>()),
+    ])?;
 
-    let params = vec![
-        ("query", topic),
-        ("granularity", "day"),
-    ];
+    Ok(df)
+}
 
-    let result = get_response(&bearer_token, &url, params).await?;    
+/// Utility method to find the newest tweet in a tweets_to_df frame
+/// ids are compared numerically, returns (tweet_id, created_at)
+pub fn newest_tweet(df: &DataFrame) -> Result<Option<(String, String)>, CtNlpError> {
+    let ids = df.column("tweet_id")?.utf8()?;
+    let created = df.column("created_at")?.utf8()?;
+
+    let mut newest: Option<(u64, &str)> = None;
+    for (tweet_id, created_at) in ids.into_iter().zip(created) {
+        let tweet_id = match tweet_id {
+            Some(x) => x.parse::<u64>().map_err(|_| CtNlpError::Parse(format!("newest_tweet|tweet_id is not numeric, tweet_id={}", x)))?,
+            None => continue,
+        };
+
+        if newest.is_none_or(|(max_id, _)| tweet_id > max_id) {
+            newest = Some((tweet_id, created_at.unwrap_or_default()));
+        }
+    }
 
-    info!("get_tweet_counts|completed");
-    Ok(result)
+    Ok(newest.map(|(tweet_id, created_at)| (tweet_id.to_string(), created_at.to_string())))
 }
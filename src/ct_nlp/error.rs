@@ -0,0 +1,121 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+use crate::models::ApiError;
+
+/// problem type the api uses for unknown ids / usernames
+const NOT_FOUND_TYPE: &str = "https://api.twitter.com/2/problems/resource-not-found";
+
+#[derive(Debug)]
+pub enum CtNlpError {
+    /// 401/403, or a missing/empty bearer token
+    Auth(String),
+    /// 429 that outlived every retry, reset is epoch seconds
+    RateLimited { reset: Option<u64> },
+    /// unknown id, username or route
+    NotFound(String),
+    /// the query succeeded but matched nothing
+    EmptyResult(String),
+    /// any other non-200 status
+    Http(u16),
+    /// body could not be deserialized or shaped into a frame
+    Parse(String),
+    /// errors array returned in place of data
+    ApiErrors(Vec<ApiError>),
+    /// connection, timeout or client build failures
+    Request(String),
+    /// bad arguments or configuration, nothing was sent
+    Invalid(String),
+}
+
+impl CtNlpError {
+    pub fn from_status(status: StatusCode, reset: Option<u64>) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => CtNlpError::Auth(format!("status={}", status)),
+            StatusCode::NOT_FOUND => CtNlpError::NotFound(format!("status={}", status)),
+            StatusCode::TOO_MANY_REQUESTS => CtNlpError::RateLimited { reset },
+            s => CtNlpError::Http(s.as_u16()),
+        }
+    }
+
+    /// Classify a response that came back without data
+    pub fn from_api_errors(errors: Vec<ApiError>, context: &str) -> Self {
+        if errors.is_empty() {
+            return CtNlpError::EmptyResult(context.to_string());
+        }
+
+        if errors.iter().all(|e| e.error_type.as_deref() == Some(NOT_FOUND_TYPE)) {
+            let ids: Vec<&str> = errors.iter().filter_map(|e| e.resource_id.as_deref()).collect();
+            return CtNlpError::NotFound(format!("{}|ids={}", context, ids.join(",")));
+        }
+
+        CtNlpError::ApiErrors(errors)
+    }
+
+    /// Transient failures, the same request may succeed later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            CtNlpError::RateLimited { .. } | CtNlpError::Request(_) => true,
+            CtNlpError::Http(s) => *s >= 500,
+            _ => false,
+        }
+    }
+
+    /// Nothing to land, the step can complete without output
+    pub fn is_skippable(&self) -> bool {
+        matches!(self, CtNlpError::EmptyResult(_) | CtNlpError::NotFound(_))
+    }
+
+    /// job_step status a landing step should record for this error
+    /// S re-queues the step, C completes it, F fails it
+    pub fn step_status(&self) -> &'static str {
+        if self.is_retryable() { "S" }
+        else if self.is_skippable() { "C" }
+        else { "F" }
+    }
+}
+
+impl fmt::Display for CtNlpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CtNlpError::Auth(x) => write!(f, "ERR: not authorized|{}", x),
+            CtNlpError::RateLimited { reset } => write!(f, "ERR: rate limited|reset={:?}", reset),
+            CtNlpError::NotFound(x) => write!(f, "ERR: not found|{}", x),
+            CtNlpError::EmptyResult(x) => write!(f, "ERR: empty result|{}", x),
+            CtNlpError::Http(s) => write!(f, "ERR: unexpected status|status={}", s),
+            CtNlpError::Parse(x) => write!(f, "ERR: unable to parse response|{}", x),
+            CtNlpError::ApiErrors(errors) => {
+                let details: Vec<&str> = errors.iter()
+                    .map(|e| e.detail.as_deref().or(e.title.as_deref()).unwrap_or("unknown"))
+                    .collect();
+                write!(f, "ERR: api returned errors|{}", details.join("; "))
+            },
+            CtNlpError::Request(x) => write!(f, "ERR: request failed|{}", x),
+            CtNlpError::Invalid(x) => write!(f, "ERR: invalid input|{}", x),
+        }
+    }
+}
+
+impl std::error::Error for CtNlpError {}
+
+impl From<reqwest::Error> for CtNlpError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(s) => CtNlpError::from_status(s, None),
+            None => CtNlpError::Request(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for CtNlpError {
+    fn from(e: serde_json::Error) -> Self {
+        CtNlpError::Parse(e.to_string())
+    }
+}
+
+impl From<polars::error::PolarsError> for CtNlpError {
+    fn from(e: polars::error::PolarsError) -> Self {
+        CtNlpError::Parse(e.to_string())
+    }
+}
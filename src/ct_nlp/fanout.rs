@@ -0,0 +1,120 @@
+//! Many topics / users at once: one fetch per key with at most
+//! TwitterClient::concurrency() requests in flight
+//! Every fetch shares the client's credential pool and rate limit state,
+//! a key that fails does not stop the others
+
+use futures::stream::{self, StreamExt};
+use log::info;
+use polars::frame::DataFrame;
+use std::future::Future;
+
+use crate::error::CtNlpError;
+use crate::{PageLimit, QueryOptions, Relation, TwitterClient};
+
+/// Requests in flight when conf [twitter_concurrency] is not set
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Run fetch for every key, at most concurrency at once
+/// Results come back in key order
+pub async fn bounded<'a, T, F, Fut>(keys: &'a [&'a str], concurrency: usize, fetch: F) -> Vec<(&'a str, Result<T, CtNlpError>)>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = Result<T, CtNlpError>>,
+{
+    stream::iter(keys.iter().map(|x| {
+        let fut = fetch(x);
+        async move { (*x, fut.await) }
+    }))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// One DataFrame or error per key, in key order
+#[derive(Debug)]
+pub struct FanOut {
+    pub results: Vec<(String, Result<DataFrame, CtNlpError>)>,
+}
+
+impl FanOut {
+    fn new(results: Vec<(&str, Result<DataFrame, CtNlpError>)>) -> Self {
+        Self { results: results.into_iter().map(|(k, v)| (k.to_string(), v)).collect() }
+    }
+
+    /// Every successful frame stacked into one, None when no key had data
+    pub fn stacked(&self) -> Result<Option<DataFrame>, CtNlpError> {
+        let mut out: Option<DataFrame> = None;
+        for df in self.results.iter().filter_map(|(_, x)| x.as_ref().ok()) {
+            out = match out {
+                None => Some(df.clone()),
+                Some(mut acc) => {
+                    acc.vstack_mut(df)?;
+                    Some(acc)
+                },
+            };
+        }
+
+        Ok(out)
+    }
+
+    /// Keys whose fetch failed, skippable errors (nothing found) included
+    pub fn failed(&self) -> Vec<(&str, &CtNlpError)> {
+        self.results.iter()
+            .filter_map(|(k, x)| x.as_ref().err().map(|e| (k.as_str(), e)))
+            .collect()
+    }
+
+    /// Stacked frame for a landing step
+    /// Keys with nothing to land are logged and dropped, any other failure is returned
+    pub fn into_frame(mut self) -> Result<Option<DataFrame>, CtNlpError> {
+        if let Some(i) = self.results.iter().position(|(_, x)| x.as_ref().is_err_and(|e| !e.is_skippable())) {
+            let (key, result) = self.results.swap_remove(i);
+            info!("FanOut|ERR: fetch failed|key={}", key);
+            return result.map(Some);
+        }
+
+        for (key, err) in self.failed() {
+            info!("FanOut|nothing to land|key={}|{}", key, err);
+        }
+
+        self.stacked()
+    }
+}
+
+impl TwitterClient {
+    /// Utility method to query recents (v2) for many topics at once
+    /// df cols: see tweets_to_df
+    pub async fn get_recent_tweets_many(&self, topics: &[&str], options: &QueryOptions, limit: PageLimit) -> FanOut {
+        info!("get_recent_tweets_many|starting|topics={}|concurrency={}", topics.len(), self.concurrency());
+
+        let results = bounded(topics, self.concurrency(), |x| self.get_recent_tweets(x, options, limit)).await;
+        let fanout = FanOut::new(results);
+
+        info!("get_recent_tweets_many|completed|failed={}", fanout.failed().len());
+        fanout
+    }
+
+    /// Utility method to query user timelines (v2) for many user ids at once
+    /// df cols: see tweets_to_df
+    pub async fn user_timelines(&self, user_ids: &[&str], options: &QueryOptions, limit: PageLimit) -> FanOut {
+        info!("user_timelines|starting|user_ids={}|concurrency={}", user_ids.len(), self.concurrency());
+
+        let results = bounded(user_ids, self.concurrency(), |x| self.user_timeline(x, options, limit)).await;
+        let fanout = FanOut::new(results);
+
+        info!("user_timelines|completed|failed={}", fanout.failed().len());
+        fanout
+    }
+
+    /// Utility method to walk a graph (v2) endpoint for many ids at once
+    /// cols: see edges_to_df
+    pub async fn relation_edges_many(&self, relation: Relation, ids: &[&str], limit: PageLimit) -> FanOut {
+        info!("relation_edges_many|starting|relation={}|ids={}|concurrency={}", relation.as_str(), ids.len(), self.concurrency());
+
+        let results = bounded(ids, self.concurrency(), |x| self.relation_edges(relation, x, limit)).await;
+        let fanout = FanOut::new(results);
+
+        info!("relation_edges_many|completed|failed={}", fanout.failed().len());
+        fanout
+    }
+}
@@ -0,0 +1,586 @@
+//! Local stand-in for the Twitter (v2) api, serving canned fixtures
+//! Point a TwitterClient at MockServer::url() to run offline
+//! Only built for tests and with the mock feature
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::info;
+use serde_json::{json, Value};
+
+/// The only bearer token the mock accepts, anything else is a 401
+pub const MOCK_BEARER_TOKEN: &str = "mock-bearer-token";
+
+/// The only api key / secret oauth2/token exchanges (for MOCK_BEARER_TOKEN), anything else is a 403
+pub const MOCK_API_KEY: &str = "mock-api-key";
+pub const MOCK_API_SECRET: &str = "mock-api-secret";
+
+/// Known ids and usernames in the fixtures
+pub const MOCK_USERNAME: &str = "TwitterDev";
+pub const MOCK_USER_ID: &str = "2244994945";
+pub const MOCK_TWEET_ID: &str = "1529001000000000003";
+
+/// batch lookup ids / usernames with these prefixes come back as errors,
+/// every other tweet id in a batch is found
+pub const MOCK_MISSING_PREFIX: &str = "404";
+pub const MOCK_SUSPENDED_PREFIX: &str = "403";
+
+/// ids / usernames accepted per batch request, more is a 400
+pub const MOCK_BATCH_LIMIT: usize = 100;
+
+/// tweets and heartbeats each filtered stream connection sends before it drops
+pub const MOCK_STREAM_TWEETS: usize = 3;
+pub const MOCK_STREAM_HEARTBEATS: usize = 2;
+
+/// search/recent query that returns zero results
+pub const MOCK_EMPTY_QUERY: &str = "no results";
+
+/// root of the fixture reply thread, search/recent for conversation_id:<this> returns its replies
+pub const MOCK_CONVERSATION_ID: &str = "1529002000000000000";
+
+/// the only day search/all has tweets for, every other window is empty
+pub const MOCK_ARCHIVE_DATE: &str = "2022-05-22";
+
+const RECENT_PAGE1: &str = include_str!("fixtures/recent_page1.json");
+const RECENT_PAGE2: &str = include_str!("fixtures/recent_page2.json");
+const RECENT_NEXT_TOKEN: &str = "b26v89c19zqg8o3fpz";
+const SEARCH_ALL: &str = include_str!("fixtures/search_all.json");
+const COUNTS_RECENT: &str = include_str!("fixtures/counts_recent.json");
+const COUNTS_ALL_PAGE1: &str = include_str!("fixtures/counts_all_page1.json");
+const COUNTS_ALL_PAGE2: &str = include_str!("fixtures/counts_all_page2.json");
+const COUNTS_ALL_NEXT_TOKEN: &str = "1jzu9lk96gu5npw2";
+const USERS_BY: &str = include_str!("fixtures/users_by.json");
+const USERS_BY_NOT_FOUND: &str = include_str!("fixtures/users_by_not_found.json");
+const TWEET_LOOKUP: &str = include_str!("fixtures/tweet_lookup.json");
+const TWEET_NOT_FOUND: &str = include_str!("fixtures/tweet_not_found.json");
+const USER_TIMELINE_PAGE1: &str = include_str!("fixtures/user_timeline_page1.json");
+const USER_TIMELINE_PAGE2: &str = include_str!("fixtures/user_timeline_page2.json");
+const USER_TIMELINE_NEXT_TOKEN: &str = "7140dibdnow9c7btw3w29";
+const MENTIONS_TIMELINE: &str = include_str!("fixtures/mentions_timeline.json");
+const FOLLOWERS_PAGE1: &str = include_str!("fixtures/followers_page1.json");
+const FOLLOWERS_PAGE2: &str = include_str!("fixtures/followers_page2.json");
+const FOLLOWERS_NEXT_TOKEN: &str = "DFEDBNRFT3MHCZZZ";
+const FOLLOWING: &str = include_str!("fixtures/following.json");
+const LIKING_USERS: &str = include_str!("fixtures/liking_users.json");
+const RETWEETED_BY: &str = include_str!("fixtures/retweeted_by.json");
+const QUOTE_TWEETS: &str = include_str!("fixtures/quote_tweets.json");
+const CONVERSATION: &str = include_str!("fixtures/conversation.json");
+const EMPTY: &str = include_str!("fixtures/empty.json");
+const UNAUTHORIZED: &str = include_str!("fixtures/unauthorized.json");
+const TOO_MANY_REQUESTS: &str = include_str!("fixtures/too_many_requests.json");
+const INVALID_REQUEST: &str = include_str!("fixtures/invalid_request.json");
+const STREAM: &str = include_str!("fixtures/stream.ndjson");
+
+/// A request as seen by the mock, query values are url-decoded
+#[derive(Debug, Clone, Default)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: String,
+    pub authorization: Option<String>,
+}
+
+impl MockRequest {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    requests: Vec<MockRequest>,
+    failures: VecDeque<u16>,
+    served: u64,
+    rules: Vec<Value>,
+    rule_seq: u64,
+    /// held before every reply, stands in for api round trip time
+    latency: Duration,
+    in_flight: usize,
+    max_in_flight: usize,
+}
+
+/// Body to send back, Stream is the chunked filtered stream
+enum Reply {
+    Body(u16, Cow<'static, str>),
+    Stream,
+}
+
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    /// Bind to an ephemeral port on localhost and serve on a background thread, a thread per connection
+    pub fn start() -> std::io::Result<Self> {
+        Self::bind("127.0.0.1:0")
+    }
+
+    pub fn bind(addr: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let t_state = Arc::clone(&state);
+        let t_shutdown = Arc::clone(&shutdown);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if t_shutdown.load(Ordering::SeqCst) { break; }
+                match stream {
+                    Ok(s) => {
+                        let c_state = Arc::clone(&t_state);
+                        thread::spawn(move || {
+                            if let Err(e) = handle(s, &c_state) {
+                                info!("mock|ERR: connection failed|e={}", e);
+                            }
+                        });
+                    },
+                    Err(e) => info!("mock|ERR: accept failed|e={}", e),
+                }
+            }
+        });
+
+        info!("mock|listening on {}", addr);
+        Ok(Self { addr, state, shutdown })
+    }
+
+    /// Base url to hand to TwitterClient
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Answer the next n requests with status instead of the fixture
+    /// 429 responses carry x-rate-limit-remaining=0 and a reset of now
+    pub fn fail_next(&self, status: u16, n: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.failures.extend(std::iter::repeat_n(status, n));
+    }
+
+    /// Every request served so far, in order
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Hold every reply for latency, so concurrent clients overlap
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    /// Most requests the mock was answering at once
+    pub fn max_in_flight(&self) -> usize {
+        self.state.lock().unwrap().max_in_flight
+    }
+
+    /// Block the calling thread, used by the standalone binary
+    pub fn wait(&self) {
+        while !self.shutdown.load(Ordering::SeqCst) {
+            thread::park();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake the accept loop so the thread can exit
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<MockState>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    if request_line.trim().is_empty() { return Ok(()); }
+
+    let mut authorization = None;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 { break; }
+        let line = line.trim_end();
+        if line.is_empty() { break; }
+        if let Some((k, v)) = line.split_once(':') {
+            if k.eq_ignore_ascii_case("authorization") {
+                authorization = Some(v.trim().to_string());
+            } else if k.eq_ignore_ascii_case("content-length") {
+                content_length = v.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("GET");
+    let target = parts.next().unwrap_or("/");
+    let request = MockRequest {
+        method: method.to_string(),
+        body: String::from_utf8_lossy(&body).into_owned(),
+        authorization: authorization.clone(),
+        ..parse_target(target)
+    };
+    info!("mock|{} {}", method, target);
+
+    let latency = {
+        let mut state = state.lock().unwrap();
+        state.in_flight += 1;
+        state.max_in_flight = state.max_in_flight.max(state.in_flight);
+        state.latency
+    };
+    thread::sleep(latency);
+
+    let (reply, remaining) = {
+        let mut state = state.lock().unwrap();
+        state.in_flight -= 1;
+        state.requests.push(request.clone());
+        state.served += 1;
+        let remaining = 450u64.saturating_sub(state.served);
+
+        let reply = match state.failures.pop_front() {
+            Some(429) => Reply::Body(429, TOO_MANY_REQUESTS.into()),
+            Some(code) => Reply::Body(code, r#"{"title": "Server Error", "detail": "injected failure"}"#.into()),
+            None if request.path == "/oauth2/token" => oauth2_token(&request),
+            None if authorization.as_deref() != Some(&format!("Bearer {}", MOCK_BEARER_TOKEN)) => Reply::Body(401, UNAUTHORIZED.into()),
+            None if request.path == "/2/tweets/search/stream" => Reply::Stream,
+            None if request.path == "/2/tweets/search/stream/rules" => stream_rules(&request, &mut state),
+            None => {
+                let (status, body) = route(&request);
+                Reply::Body(status, body)
+            },
+        };
+
+        let remaining = match reply {
+            Reply::Body(429, _) => 0,
+            _ => remaining,
+        };
+        (reply, remaining)
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+
+    let (status, body) = match reply {
+        Reply::Body(status, body) => (status, body),
+        Reply::Stream => return write_stream(stream, remaining, now + 900),
+    };
+    let reset = if status == 429 { now } else { now + 900 };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nx-rate-limit-limit: 450\r\nx-rate-limit-remaining: {}\r\nx-rate-limit-reset: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        remaining,
+        reset,
+        body,
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// Send the stream fixture one line per chunk, then end the body
+/// The client sees a dropped connection and has to reconnect
+fn write_stream(mut stream: TcpStream, remaining: u64, reset: u64) -> std::io::Result<()> {
+    let headers = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=utf-8\r\nTransfer-Encoding: chunked\r\nx-rate-limit-limit: 50\r\nx-rate-limit-remaining: {}\r\nx-rate-limit-reset: {}\r\nConnection: close\r\n\r\n",
+        remaining.min(50),
+        reset,
+    );
+    stream.write_all(headers.as_bytes())?;
+
+    for line in STREAM.lines() {
+        let chunk = format!("{}\r\n", line.trim());
+        write!(stream, "{:x}\r\n{}\r\n", chunk.len(), chunk)?;
+        stream.flush()?;
+    }
+
+    stream.write_all(b"0\r\n\r\n")?;
+    stream.flush()
+}
+
+/// POST grant_type=client_credentials with basic auth MOCK_API_KEY:MOCK_API_SECRET
+fn oauth2_token(request: &MockRequest) -> Reply {
+    let expected = format!("Basic {}", base64::encode(format!("{}:{}", MOCK_API_KEY, MOCK_API_SECRET)));
+
+    if request.method != "POST" || request.body != "grant_type=client_credentials" {
+        return Reply::Body(400, INVALID_REQUEST.into());
+    }
+
+    match request.authorization.as_deref() == Some(expected.as_str()) {
+        true => Reply::Body(200, json!({"token_type": "bearer", "access_token": MOCK_BEARER_TOKEN}).to_string().into()),
+        false => Reply::Body(403, r#"{"errors":[{"code":99,"message":"Unable to verify your credentials","label":"authenticity_token_error"}]}"#.into()),
+    }
+}
+
+/// GET lists the rules, POST takes {"add": [...]} or {"delete": {"ids": [...]}}
+fn stream_rules(request: &MockRequest, state: &mut MockState) -> Reply {
+    let sent = "2022-05-24T12:00:00.000Z";
+
+    if request.method != "POST" {
+        let body = match state.rules.is_empty() {
+            true => json!({"meta": {"sent": sent, "result_count": 0}}),
+            false => json!({"data": state.rules, "meta": {"sent": sent, "result_count": state.rules.len()}}),
+        };
+        return Reply::Body(200, body.to_string().into());
+    }
+
+    let payload: Value = match serde_json::from_str(&request.body) {
+        Ok(x) => x,
+        Err(_) => return Reply::Body(400, INVALID_REQUEST.into()),
+    };
+
+    if let Some(add) = payload["add"].as_array() {
+        let mut created = vec![];
+        for rule in add {
+            state.rule_seq += 1;
+            let mut rule = rule.clone();
+            rule["id"] = Value::String(format!("152900390000000{:04}", state.rule_seq));
+            created.push(rule);
+        }
+        state.rules.extend(created.iter().cloned());
+
+        let body = json!({"data": created, "meta": {"sent": sent, "summary": {"created": created.len(), "not_created": 0, "valid": created.len(), "invalid": 0}}});
+        return Reply::Body(201, body.to_string().into());
+    }
+
+    if let Some(ids) = payload["delete"]["ids"].as_array() {
+        let before = state.rules.len();
+        state.rules.retain(|x| !ids.contains(&x["id"]));
+        let deleted = before - state.rules.len();
+
+        let body = json!({"meta": {"sent": sent, "summary": {"deleted": deleted, "not_deleted": ids.len() - deleted}}});
+        return Reply::Body(200, body.to_string().into());
+    }
+
+    Reply::Body(400, INVALID_REQUEST.into())
+}
+
+fn route(request: &MockRequest) -> (u16, Cow<'static, str>) {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+    let (status, body) = match segments.as_slice() {
+        ["2", "tweets"] => return batch_tweets(request.param("ids").unwrap_or_default()),
+        ["2", "users", "by"] if request.param("usernames").is_some_and(|x| x.contains(',')) => {
+            return batch_users(request.param("usernames").unwrap_or_default())
+        },
+        ["2", "tweets", "search", "recent"] => match (request.param("query"), request.param("next_token")) {
+            (Some(MOCK_EMPTY_QUERY), _) => (200, EMPTY),
+            (Some(x), _) if x.starts_with("conversation_id:") => match x.strip_prefix("conversation_id:") {
+                Some(MOCK_CONVERSATION_ID) => (200, CONVERSATION),
+                _ => (200, EMPTY),
+            },
+            (_, Some(RECENT_NEXT_TOKEN)) => (200, RECENT_PAGE2),
+            _ => (200, RECENT_PAGE1),
+        },
+        ["2", "tweets", "search", "all"] => match request.param("start_time") {
+            Some(x) if x.starts_with(MOCK_ARCHIVE_DATE) => (200, SEARCH_ALL),
+            _ => (200, EMPTY),
+        },
+        // counts endpoints do not take max_results, the real api answers 400
+        ["2", "tweets", "counts", _] if request.param("max_results").is_some() => (400, INVALID_REQUEST),
+        ["2", "tweets", "counts", "recent"] => (200, COUNTS_RECENT),
+        ["2", "tweets", "counts", "all"] => match request.param("next_token") {
+            Some(COUNTS_ALL_NEXT_TOKEN) => (200, COUNTS_ALL_PAGE2),
+            _ => (200, COUNTS_ALL_PAGE1),
+        },
+        ["2", "users", "by"] => match request.param("usernames") {
+            Some(x) if x.eq_ignore_ascii_case(MOCK_USERNAME) => (200, USERS_BY),
+            _ => (200, USERS_BY_NOT_FOUND),
+        },
+        ["2", "tweets", id] => match *id {
+            MOCK_TWEET_ID => (200, TWEET_LOOKUP),
+            _ => (200, TWEET_NOT_FOUND),
+        },
+        ["2", "users", _, "tweets"] => match request.param("pagination_token") {
+            Some(USER_TIMELINE_NEXT_TOKEN) => (200, USER_TIMELINE_PAGE2),
+            _ => (200, USER_TIMELINE_PAGE1),
+        },
+        ["2", "users", _, "mentions"] => (200, MENTIONS_TIMELINE),
+        ["2", "users", _, "followers"] => match request.param("pagination_token") {
+            Some(FOLLOWERS_NEXT_TOKEN) => (200, FOLLOWERS_PAGE2),
+            _ => (200, FOLLOWERS_PAGE1),
+        },
+        ["2", "users", _, "following"] => (200, FOLLOWING),
+        ["2", "tweets", MOCK_TWEET_ID, "liking_users"] => (200, LIKING_USERS),
+        ["2", "tweets", MOCK_TWEET_ID, "retweeted_by"] => (200, RETWEETED_BY),
+        ["2", "tweets", MOCK_TWEET_ID, "quote_tweets"] => (200, QUOTE_TWEETS),
+        ["2", "tweets", _, "liking_users" | "retweeted_by" | "quote_tweets"] => (200, EMPTY),
+        _ => (404, r#"{"title": "Not Found", "status": 404}"#),
+    };
+
+    (status, body.into())
+}
+
+/// /2/tweets?ids= with every id synthesized unless it carries an error prefix
+/// replies from the conversation fixture come back as-is, any other id is its own conversation root
+fn batch_tweets(ids: &str) -> (u16, Cow<'static, str>) {
+    let ids: Vec<&str> = ids.split(',').filter(|x| !x.is_empty()).collect();
+    if ids.is_empty() || ids.len() > MOCK_BATCH_LIMIT {
+        return (400, INVALID_REQUEST.into());
+    }
+
+    let conversation: Value = serde_json::from_str(CONVERSATION).unwrap_or_default();
+    let replies = conversation["data"].as_array().cloned().unwrap_or_default();
+
+    let mut data = vec![];
+    let mut errors = vec![];
+    for id in ids {
+        if let Some(reply) = replies.iter().find(|x| x["id"] == id) {
+            data.push(reply.clone());
+            continue;
+        }
+
+        match batch_error(id, "tweet", "ids") {
+            Some(e) => errors.push(e),
+            None => data.push(json!({
+                "id": id,
+                "author_id": MOCK_USER_ID,
+                "created_at": "2022-05-24T12:00:00.000Z",
+                "text": format!("batch tweet {}", id),
+                "conversation_id": id,
+            })),
+        }
+    }
+
+    let users = json!([{"id": MOCK_USER_ID, "name": "Twitter Dev", "username": MOCK_USERNAME}]);
+    (200, batch_body(data, errors, Some(users)).into())
+}
+
+/// /2/users/by?usernames= with more than one username
+/// MOCK_USERNAME is found, the prefixes are errors, anything else is not found
+fn batch_users(usernames: &str) -> (u16, Cow<'static, str>) {
+    let usernames: Vec<&str> = usernames.split(',').filter(|x| !x.is_empty()).collect();
+    if usernames.len() > MOCK_BATCH_LIMIT {
+        return (400, INVALID_REQUEST.into());
+    }
+
+    let mut data = vec![];
+    let mut errors = vec![];
+    for username in usernames {
+        if username.eq_ignore_ascii_case(MOCK_USERNAME) {
+            data.push(json!({"id": MOCK_USER_ID, "name": "Twitter Dev", "username": MOCK_USERNAME}));
+            continue;
+        }
+
+        let error = batch_error(username, "user", "usernames");
+        errors.push(error.unwrap_or_else(|| not_found(username, "user", "usernames")));
+    }
+
+    (200, batch_body(data, errors, None).into())
+}
+
+fn batch_error(value: &str, resource_type: &str, parameter: &str) -> Option<Value> {
+    if value.starts_with(MOCK_MISSING_PREFIX) {
+        return Some(not_found(value, resource_type, parameter));
+    }
+
+    if value.starts_with(MOCK_SUSPENDED_PREFIX) {
+        return Some(json!({
+            "value": value,
+            "detail": format!("User has been suspended: [{}].", value),
+            "title": "Forbidden",
+            "resource_type": resource_type,
+            "parameter": parameter,
+            "resource_id": value,
+            "type": "https://api.twitter.com/2/problems/resource-not-found",
+            "section": "data",
+        }));
+    }
+
+    None
+}
+
+fn not_found(value: &str, resource_type: &str, parameter: &str) -> Value {
+    json!({
+        "value": value,
+        "detail": format!("Could not find {} with {}: [{}].", resource_type, parameter, value),
+        "title": "Not Found Error",
+        "resource_type": resource_type,
+        "parameter": parameter,
+        "resource_id": value,
+        "type": "https://api.twitter.com/2/problems/resource-not-found",
+    })
+}
+
+fn batch_body(data: Vec<Value>, errors: Vec<Value>, users: Option<Value>) -> String {
+    let mut body = json!({});
+    if !data.is_empty() {
+        body["data"] = Value::Array(data);
+        if let Some(users) = users {
+            body["includes"] = json!({"users": users});
+        }
+    }
+    if !errors.is_empty() {
+        body["errors"] = Value::Array(errors);
+    }
+
+    body.to_string()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+fn parse_target(target: &str) -> MockRequest {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|x| !x.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (url_decode(k), url_decode(v))
+        })
+        .collect();
+
+    MockRequest { path: path.to_string(), query, ..MockRequest::default() }
+}
+
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|x| u8::from_str_radix(x, 16).ok()) {
+                    Some(b) => { out.push(b); i += 2; },
+                    None => out.push(b'%'),
+                }
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
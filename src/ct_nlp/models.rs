@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::CtNlpError;
+
+/// Top level envelope returned by every v2 endpoint
+/// data is a single object or an array depending on the endpoint
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Response<T> {
+    pub data: Option<T>,
+    pub includes: Option<Includes>,
+    pub meta: Option<Meta>,
+    #[serde(default)]
+    pub errors: Vec<ApiError>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Tweet {
+    pub id: String,
+    pub text: String,
+    pub author_id: Option<String>,
+    pub created_at: Option<String>,
+    pub public_metrics: Option<PublicMetrics>,
+    pub lang: Option<String>,
+    pub conversation_id: Option<String>,
+    pub in_reply_to_user_id: Option<String>,
+    #[serde(default)]
+    pub referenced_tweets: Vec<ReferencedTweet>,
+}
+
+/// Engagement counts (tweet.fields=public_metrics)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PublicMetrics {
+    pub retweet_count: u64,
+    pub reply_count: u64,
+    pub like_count: u64,
+    pub quote_count: u64,
+}
+
+/// type is one of retweeted, quoted, replied_to
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ReferencedTweet {
+    #[serde(rename = "type")]
+    pub reference_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    pub username: String,
+    pub created_at: Option<String>,
+    pub description: Option<String>,
+    pub verified: Option<bool>,
+    pub public_metrics: Option<UserMetrics>,
+}
+
+/// Account counts (user.fields=public_metrics)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UserMetrics {
+    pub followers_count: u64,
+    pub following_count: u64,
+    pub tweet_count: u64,
+    pub listed_count: u64,
+}
+
+/// Expanded objects referenced from data (expansions=author_id, ...)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Includes {
+    #[serde(default)]
+    pub users: Vec<User>,
+    #[serde(default)]
+    pub tweets: Vec<Tweet>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Meta {
+    pub result_count: Option<u64>,
+    pub newest_id: Option<String>,
+    pub oldest_id: Option<String>,
+    pub next_token: Option<String>,
+    pub total_tweet_count: Option<u64>,
+}
+
+/// Partial errors, returned next to (or instead of) data
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ApiError {
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    pub parameter: Option<String>,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TweetCount {
+    pub start: String,
+    pub end: String,
+    pub tweet_count: u64,
+}
+
+/// Filtered stream rule, id is assigned by the api on add
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StreamRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// Rule that matched a streamed tweet
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MatchingRule {
+    pub id: String,
+    pub tag: Option<String>,
+}
+
+/// One line of the filtered stream
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct StreamTweet {
+    pub data: Tweet,
+    pub includes: Option<Includes>,
+    #[serde(default)]
+    pub matching_rules: Vec<MatchingRule>,
+    /// the line as the stream sent it, for raw landing
+    #[serde(skip)]
+    pub raw: String,
+}
+
+impl<T> Response<T> {
+    /// Look up an expanded user by id
+    pub fn find_user(&self, user_id: &str) -> Option<&User> {
+        self.includes.as_ref()?.users.iter().find(|u| u.id == user_id)
+    }
+
+    /// Every expanded user, empty without expansions=author_id
+    pub fn users(&self) -> &[User] {
+        self.includes.as_ref().map_or(&[], |x| x.users.as_slice())
+    }
+}
+
+impl<T> Response<Vec<T>> {
+    pub fn empty() -> Self {
+        Self { data: None, includes: None, meta: None, errors: vec![] }
+    }
+
+    /// Append a later page onto this response
+    /// newest_id is kept from the first page, everything else from the last
+    pub fn extend(&mut self, page: Response<Vec<T>>) {
+        if let Some(rows) = page.data {
+            self.data.get_or_insert_with(Vec::new).extend(rows);
+        }
+
+        if let Some(inc) = page.includes {
+            let cur = self.includes.get_or_insert_with(Includes::default);
+            cur.users.extend(inc.users);
+            cur.tweets.extend(inc.tweets);
+        }
+
+        if let Some(meta) = page.meta {
+            let cur = self.meta.get_or_insert_with(Meta::default);
+            cur.result_count = Some(cur.result_count.unwrap_or(0) + meta.result_count.unwrap_or(0));
+            cur.newest_id = cur.newest_id.take().or(meta.newest_id);
+            cur.oldest_id = meta.oldest_id.or(cur.oldest_id.take());
+            cur.next_token = meta.next_token;
+            cur.total_tweet_count = match (cur.total_tweet_count, meta.total_tweet_count) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            };
+        }
+
+        self.errors.extend(page.errors);
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.as_ref().map_or(0, |x| x.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Response<T> {
+    /// Pass the response through only if it carries data
+    /// otherwise the errors array decides NotFound, ApiErrors or EmptyResult
+    pub fn check(self, context: &str) -> Result<Self, CtNlpError> {
+        match self.data {
+            Some(_) => Ok(self),
+            None => Err(CtNlpError::from_api_errors(self.errors, context)),
+        }
+    }
+}
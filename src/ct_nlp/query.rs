@@ -0,0 +1,293 @@
+//! Typed search queries: keywords, accounts, exclusions and filters composed and checked
+//! against the (v2) query rules, then rendered to the string search / counts / stream rules take
+//! A SearchQuery round-trips through json so topic.search_query can store one
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CtNlpError;
+use crate::SearchScope;
+
+/// Query length the api accepts, recent search and stream rules
+pub const MAX_QUERY_LEN: usize = 512;
+/// Query length the api accepts, full-archive search
+pub const MAX_ARCHIVE_QUERY_LEN: usize = 1024;
+
+/// Characters the query grammar gives a meaning to, not allowed inside a bare keyword
+const RESERVED: &[char] = &['(', ')', '"', ':', '#', '$', '@'];
+
+/// A search query, every populated part is ANDed together
+/// all: keywords that must all match, any: keywords where one must match,
+/// phrases: exact "quoted" matches, accounts in from / to / mentions are ORed within their group
+/// unknown fields are an error, a typo in topic.search_query must not run the query unfiltered
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchQuery {
+    pub all: Vec<String>,
+    pub any: Vec<String>,
+    pub phrases: Vec<String>,
+    pub hashtags: Vec<String>,
+    pub cashtags: Vec<String>,
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+    pub mentions: Vec<String>,
+    pub exclude: Vec<String>,
+    pub exclude_from: Vec<String>,
+    pub lang: Option<String>,
+    /// Some(false) renders -is:retweet
+    pub retweets: Option<bool>,
+    pub replies: Option<bool>,
+    pub quotes: Option<bool>,
+    pub verified: Option<bool>,
+    pub has_links: Option<bool>,
+    pub has_media: Option<bool>,
+}
+
+fn trim_prefix(value: &str, prefix: char) -> &str {
+    value.trim().trim_start_matches(prefix)
+}
+
+fn check_keyword(value: &str) -> Result<&str, CtNlpError> {
+    let value = value.trim();
+    if value.is_empty()
+        || value.starts_with('-')
+        || value.chars().any(|c| c.is_whitespace() || RESERVED.contains(&c))
+        || value.eq_ignore_ascii_case("or")
+    {
+        return Err(CtNlpError::Invalid(format!("SearchQuery|keyword is not valid, use phrases for multiple words, keyword={:?}", value)));
+    }
+    Ok(value)
+}
+
+fn check_username(value: &str) -> Result<&str, CtNlpError> {
+    let name = trim_prefix(value, '@');
+    if name.is_empty() || name.len() > 15 || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(CtNlpError::Invalid(format!("SearchQuery|username is not valid, username={:?}", value)));
+    }
+    Ok(name)
+}
+
+fn check_tag(value: &str, prefix: char) -> Result<&str, CtNlpError> {
+    let tag = trim_prefix(value, prefix);
+    let valid = match prefix {
+        // cashtags are tickers, letters first
+        '$' => tag.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || c == '_'),
+    };
+
+    if !valid {
+        return Err(CtNlpError::Invalid(format!("SearchQuery|{} is not valid, value={:?}", if prefix == '$' { "cashtag" } else { "hashtag" }, value)));
+    }
+    Ok(tag)
+}
+
+fn check_lang(value: &str) -> Result<&str, CtNlpError> {
+    // BCP 47 as the api reports it: en, und, zh-cn
+    let lang = value.trim();
+    let mut parts = lang.split('-');
+    let primary = parts.next().unwrap_or_default();
+    let valid = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_lowercase())
+        && parts.all(|x| (2..=4).contains(&x.len()) && x.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    if !valid {
+        return Err(CtNlpError::Invalid(format!("SearchQuery|lang is not valid, lang={:?}", value)));
+    }
+    Ok(lang)
+}
+
+/// (a OR b) for several terms, a for one
+fn or_group(terms: Vec<String>) -> Option<String> {
+    match terms.len() {
+        0 => None,
+        1 => terms.into_iter().next(),
+        _ => Some(format!("({})", terms.join(" OR "))),
+    }
+}
+
+fn flag(name: &str, value: Option<bool>) -> Option<String> {
+    value.map(|x| match x {
+        true => name.to_string(),
+        false => format!("-{}", name),
+    })
+}
+
+impl SearchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keyword(mut self, keyword: &str) -> Self {
+        self.all.push(keyword.to_string());
+        self
+    }
+
+    pub fn any_of(mut self, keywords: &[&str]) -> Self {
+        self.any.extend(keywords.iter().map(|x| x.to_string()));
+        self
+    }
+
+    pub fn phrase(mut self, phrase: &str) -> Self {
+        self.phrases.push(phrase.to_string());
+        self
+    }
+
+    pub fn hashtag(mut self, hashtag: &str) -> Self {
+        self.hashtags.push(hashtag.to_string());
+        self
+    }
+
+    pub fn cashtag(mut self, cashtag: &str) -> Self {
+        self.cashtags.push(cashtag.to_string());
+        self
+    }
+
+    pub fn from_user(mut self, username: &str) -> Self {
+        self.from.push(username.to_string());
+        self
+    }
+
+    pub fn to_user(mut self, username: &str) -> Self {
+        self.to.push(username.to_string());
+        self
+    }
+
+    pub fn mention(mut self, username: &str) -> Self {
+        self.mentions.push(username.to_string());
+        self
+    }
+
+    pub fn exclude(mut self, keyword: &str) -> Self {
+        self.exclude.push(keyword.to_string());
+        self
+    }
+
+    pub fn exclude_user(mut self, username: &str) -> Self {
+        self.exclude_from.push(username.to_string());
+        self
+    }
+
+    pub fn lang(mut self, lang: &str) -> Self {
+        self.lang = Some(lang.to_string());
+        self
+    }
+
+    pub fn retweets(mut self, include: bool) -> Self {
+        self.retweets = Some(include);
+        self
+    }
+
+    pub fn replies(mut self, include: bool) -> Self {
+        self.replies = Some(include);
+        self
+    }
+
+    pub fn quotes(mut self, include: bool) -> Self {
+        self.quotes = Some(include);
+        self
+    }
+
+    pub fn verified(mut self, only: bool) -> Self {
+        self.verified = Some(only);
+        self
+    }
+
+    pub fn has_links(mut self, has: bool) -> Self {
+        self.has_links = Some(has);
+        self
+    }
+
+    pub fn has_media(mut self, has: bool) -> Self {
+        self.has_media = Some(has);
+        self
+    }
+
+    /// Parse the json stored in topic.search_query
+    pub fn from_json(value: &str) -> Result<Self, CtNlpError> {
+        serde_json::from_str(value)
+            .map_err(|e| CtNlpError::Invalid(format!("SearchQuery|search_query is not a valid SearchQuery json|e={}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<String, CtNlpError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Render the query string, checked against the length limit for scope
+    /// Every term is validated, and the query needs at least one positive standalone term:
+    /// the api rejects queries made only of exclusions, is:, has: or lang:
+    pub fn render(&self, scope: SearchScope) -> Result<String, CtNlpError> {
+        let mut standalone: Vec<String> = vec![];
+
+        for x in &self.all {
+            standalone.push(check_keyword(x)?.to_string());
+        }
+
+        let any = self.any.iter().map(|x| check_keyword(x).map(String::from)).collect::<Result<Vec<_>, _>>()?;
+        standalone.extend(or_group(any));
+
+        for x in &self.phrases {
+            let phrase = x.trim();
+            if phrase.is_empty() || phrase.contains('"') {
+                return Err(CtNlpError::Invalid(format!("SearchQuery|phrase is not valid, phrase={:?}", x)));
+            }
+            standalone.push(format!("\"{}\"", phrase));
+        }
+
+        for x in &self.hashtags {
+            standalone.push(format!("#{}", check_tag(x, '#')?));
+        }
+
+        for x in &self.cashtags {
+            standalone.push(format!("${}", check_tag(x, '$')?));
+        }
+
+        for (op, users) in [("from:", &self.from), ("to:", &self.to), ("@", &self.mentions)] {
+            let terms = users.iter().map(|x| check_username(x).map(|u| format!("{}{}", op, u))).collect::<Result<Vec<_>, _>>()?;
+            standalone.extend(or_group(terms));
+        }
+
+        if standalone.is_empty() {
+            return Err(CtNlpError::Invalid("SearchQuery|needs a keyword, phrase, hashtag, cashtag or account, filters cannot stand alone".into()));
+        }
+
+        let mut terms = standalone;
+
+        for x in &self.exclude {
+            terms.push(format!("-{}", check_keyword(x)?));
+        }
+
+        for x in &self.exclude_from {
+            terms.push(format!("-from:{}", check_username(x)?));
+        }
+
+        terms.extend(flag("is:retweet", self.retweets));
+        terms.extend(flag("is:reply", self.replies));
+        terms.extend(flag("is:quote", self.quotes));
+        terms.extend(flag("is:verified", self.verified));
+        terms.extend(flag("has:links", self.has_links));
+        terms.extend(flag("has:media", self.has_media));
+
+        if let Some(x) = &self.lang {
+            terms.push(format!("lang:{}", check_lang(x)?));
+        }
+
+        let query = terms.join(" ");
+        let max = match scope {
+            SearchScope::Recent => MAX_QUERY_LEN,
+            SearchScope::All => MAX_ARCHIVE_QUERY_LEN,
+        };
+
+        if query.chars().count() > max {
+            return Err(CtNlpError::Invalid(format!("SearchQuery|query is {} characters, scope={} allows {}", query.chars().count(), scope.as_str(), max)));
+        }
+
+        Ok(query)
+    }
+}
+
+/// Query for a topic row: the structured search_query when set, otherwise search_text verbatim
+pub fn topic_query(search_text: &str, search_query: Option<&str>, scope: SearchScope) -> Result<String, CtNlpError> {
+    match search_query.map(str::trim).filter(|x| !x.is_empty()) {
+        Some(json) => SearchQuery::from_json(json)?.render(scope),
+        None => Ok(search_text.to_string()),
+    }
+}
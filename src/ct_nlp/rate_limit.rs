@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::HeaderMap;
+
+pub const MAX_RETRIES: u32 = 5;
+pub const BASE_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Rate-limit windows are 15 minutes, never wait longer than one window
+pub const MAX_RESET_WAIT: Duration = Duration::from_secs(15 * 60 + 1);
+
+/// Last seen x-rate-limit-* headers for an endpoint
+/// reset is the window reset time in epoch seconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset: Option<u64>,
+}
+
+static RATE_LIMITS: Mutex<BTreeMap<String, RateLimit>> = Mutex::new(BTreeMap::new());
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            limit: header_u64(headers, "x-rate-limit-limit"),
+            remaining: header_u64(headers, "x-rate-limit-remaining"),
+            reset: header_u64(headers, "x-rate-limit-reset"),
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    /// Time left until the window resets, capped at MAX_RESET_WAIT
+    pub fn until_reset(&self) -> Option<Duration> {
+        let reset = self.reset?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let wait = Duration::from_secs(reset.saturating_sub(now) + 1);
+        Some(wait.min(MAX_RESET_WAIT))
+    }
+}
+
+/// Exponential backoff for the nth retry (0 based), capped at MAX_BACKOFF
+pub fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+pub fn record(endpoint: &str, state: RateLimit) {
+    if let Ok(mut limits) = RATE_LIMITS.lock() {
+        limits.insert(endpoint.to_string(), state);
+    }
+}
+
+/// Current rate-limit state for an endpoint url, if it has been queried
+pub fn rate_limit(endpoint: &str) -> Option<RateLimit> {
+    RATE_LIMITS.lock().ok()?.get(endpoint).copied()
+}
+
+/// Snapshot of every endpoint queried so far
+pub fn rate_limits() -> BTreeMap<String, RateLimit> {
+    RATE_LIMITS.lock().map(|x| x.clone()).unwrap_or_default()
+}
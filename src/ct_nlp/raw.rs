@@ -0,0 +1,125 @@
+//! Bronze layer: every response page a client receives, untouched, as gzipped NDJSON
+//! One line per page: {"method", "path", "query", "status", "observed_at", "response"}
+//! response is the api body as parsed json, so later fields can be pulled out
+//! without hitting the api again
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use serde_json::json;
+
+use crate::error::CtNlpError;
+
+/// {landing_dir}/raw/job_step_id={job_step_id}/{dt}_{step}.ndjson.gz
+pub fn raw_path(landing_dir: &str, job_step_id: i32, step: &str, dt: &str) -> PathBuf {
+    Path::new(landing_dir)
+        .join("raw")
+        .join(format!("job_step_id={}", job_step_id))
+        .join(format!("{}_{}.ndjson.gz", dt, step))
+}
+
+struct RawWriter {
+    gz: GzEncoder<File>,
+    pages: usize,
+}
+
+/// Gzipped NDJSON file that response pages are appended to
+/// shared by a client and the bin that finishes it, dropping it unfinished finishes it
+pub struct RawSink {
+    path: PathBuf,
+    writer: Mutex<Option<RawWriter>>,
+}
+
+impl std::fmt::Debug for RawSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RawSink({})", self.path.display())
+    }
+}
+
+impl RawSink {
+    /// Create (or truncate) the file, parent dirs included
+    pub fn create(path: &Path) -> Result<Self, CtNlpError> {
+        let io_err = |e: std::io::Error| CtNlpError::Invalid(format!("RawSink|unable to create {}|e={}", path.display(), e));
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+        }
+
+        let file = File::create(path).map_err(io_err)?;
+        info!("RawSink|{} created successfully", path.display());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(Some(RawWriter { gz: GzEncoder::new(file, Compression::default()), pages: 0 })),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one response page, a body that is not json is kept as a string
+    pub fn write_page(&self, method: &str, path: &str, query: &[(&str, &str)], status: u16, body: &str) -> Result<(), CtNlpError> {
+        let response: serde_json::Value = serde_json::from_str(body).unwrap_or_else(|_| body.into());
+        let line = json!({
+            "method": method,
+            "path": path,
+            "query": query,
+            "status": status,
+            "observed_at": chrono::Utc::now().to_rfc3339(),
+            "response": response,
+        });
+
+        let mut writer = self.writer.lock()
+            .map_err(|_| CtNlpError::Invalid("RawSink|writer poisoned".into()))?;
+        let writer = writer.as_mut()
+            .ok_or_else(|| CtNlpError::Invalid(format!("RawSink|{} is already finished", self.path.display())))?;
+
+        writeln!(writer.gz, "{}", line)
+            .map_err(|e| CtNlpError::Invalid(format!("RawSink|unable to write {}|e={}", self.path.display(), e)))?;
+        writer.pages += 1;
+        Ok(())
+    }
+
+    /// Write the gzip trailer and close the file, returns the pages written
+    /// a file with no pages is removed
+    pub fn finish(&self) -> Result<usize, CtNlpError> {
+        let io_err = |e: std::io::Error| CtNlpError::Invalid(format!("RawSink|unable to finish {}|e={}", self.path.display(), e));
+
+        let writer = self.writer.lock()
+            .map_err(|_| CtNlpError::Invalid("RawSink|writer poisoned".into()))?
+            .take();
+
+        let pages = match writer {
+            Some(x) => {
+                x.gz.finish().map_err(io_err)?;
+                x.pages
+            },
+            None => return Ok(0),
+        };
+
+        if pages == 0 {
+            std::fs::remove_file(&self.path).map_err(io_err)?;
+            info!("RawSink|{} removed|no pages", self.path.display());
+        } else {
+            info!("RawSink|{} finished|pages={}", self.path.display(), pages);
+        }
+
+        Ok(pages)
+    }
+}
+
+/// A sink the bin did not finish, e.g. after an early return on error, is finished here
+/// like any other: trailer written, failures logged, a file with no pages removed
+impl Drop for RawSink {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            info!("RawSink|ERR: unable to finish on drop|{}", e);
+        }
+    }
+}
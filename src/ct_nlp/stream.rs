@@ -0,0 +1,326 @@
+//! Filtered stream (v2): rule management and a reconnecting consumer
+//! Rules owned by honey-faucet are tagged topic_id=<id>, any other rule
+//! on the app is left alone by sync_stream_rules
+
+use std::time::Duration;
+
+use log::info;
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::error::CtNlpError;
+use crate::models::{Response, StreamRule, StreamTweet};
+use crate::rate_limit::{self, RateLimit};
+use crate::{TwitterClient, TWEET_FIELDS, USER_FIELDS};
+
+/// Tag prefix marking a rule as managed by sync_stream_rules
+pub const RULE_TAG_PREFIX: &str = "topic_id=";
+
+/// Rule for a topic, tagged so matches can be routed back to it
+pub fn topic_rule(topic_id: i32, search_text: &str) -> StreamRule {
+    StreamRule {
+        id: None,
+        value: search_text.to_string(),
+        tag: Some(format!("{}{}", RULE_TAG_PREFIX, topic_id)),
+    }
+}
+
+/// topic_id from a managed rule tag, None for rules we do not own
+pub fn rule_topic_id(tag: Option<&str>) -> Option<i32> {
+    tag?.strip_prefix(RULE_TAG_PREFIX)?.parse().ok()
+}
+
+/// Rules added and deleted by sync_stream_rules
+#[derive(Debug, Clone, Default)]
+pub struct RuleSync {
+    pub added: Vec<StreamRule>,
+    pub deleted: Vec<StreamRule>,
+}
+
+/// Reconnect waits for the filtered stream, defaults follow the api guidance
+/// network errors back off linearly, http errors and 429 exponentially
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub network_base: Duration,
+    pub network_max: Duration,
+    pub http_base: Duration,
+    pub http_max: Duration,
+    pub rate_limit_base: Duration,
+    pub rate_limit_max: Duration,
+    /// consecutive failed reconnects before giving up, None retries forever
+    pub max_reconnects: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            network_base: Duration::from_millis(250),
+            network_max: Duration::from_secs(16),
+            http_base: Duration::from_secs(5),
+            http_max: Duration::from_secs(320),
+            rate_limit_base: Duration::from_secs(60),
+            rate_limit_max: Duration::from_secs(960),
+            max_reconnects: None,
+        }
+    }
+}
+
+/// Why the last connection ended, picks the backoff curve
+#[derive(Debug)]
+enum Disconnect {
+    Network(String),
+    Http(StatusCode),
+    RateLimited(Option<u64>),
+}
+
+impl ReconnectPolicy {
+    fn wait(&self, disconnect: &Disconnect, attempt: usize) -> Duration {
+        let n = attempt.max(1) as u32;
+        match disconnect {
+            Disconnect::Network(_) => (self.network_base * n).min(self.network_max),
+            Disconnect::Http(_) => self.http_base.saturating_mul(2u32.saturating_pow(n - 1)).min(self.http_max),
+            Disconnect::RateLimited(_) => self.rate_limit_base.saturating_mul(2u32.saturating_pow(n - 1)).min(self.rate_limit_max),
+        }
+    }
+}
+
+/// What the stream handed to the caller
+#[derive(Debug)]
+pub enum StreamEvent {
+    Tweet(Box<StreamTweet>),
+    /// keep-alive newline, sent every ~20s while the stream is idle
+    Heartbeat,
+}
+
+/// Returned by the event handler to keep reading or hang up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamControl {
+    Continue,
+    Stop,
+}
+
+/// Totals for a filtered_stream run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamStats {
+    pub connections: usize,
+    pub tweets: usize,
+    pub heartbeats: usize,
+}
+
+impl TwitterClient {
+    /// Utility method to list the filtered stream rules on the app
+    pub async fn stream_rules(&self) -> Result<Vec<StreamRule>, CtNlpError> {
+        info!("stream_rules|starting");
+
+        let url = self.url("/2/tweets/search/stream/rules");
+        let result: Response<Vec<StreamRule>> = self.get_response(&url, vec![]).await?;
+
+        // no rules is an empty list, not an error
+        let rules = result.data.unwrap_or_default();
+
+        info!("stream_rules|rules={}", rules.len());
+        info!("stream_rules|completed");
+        Ok(rules)
+    }
+
+    /// Utility method to add filtered stream rules, the added rules carry their ids
+    pub async fn add_stream_rules(&self, rules: &[StreamRule]) -> Result<Vec<StreamRule>, CtNlpError> {
+        info!("add_stream_rules|starting");
+
+        if rules.iter().any(|x| x.value.is_empty()) {
+            return Err(CtNlpError::Invalid(format!("add_stream_rules|rule value is not valid, rules={:?}", rules)));
+        }
+
+        let url = self.url("/2/tweets/search/stream/rules");
+        let body = json!({ "add": rules });
+        let result: Response<Vec<StreamRule>> = self.post_response(&url, vec![], &body).await?;
+
+        if !result.errors.is_empty() {
+            return Err(CtNlpError::ApiErrors(result.errors));
+        }
+
+        let added = result.data.unwrap_or_default();
+
+        info!("add_stream_rules|added={}", added.len());
+        info!("add_stream_rules|completed");
+        Ok(added)
+    }
+
+    /// Utility method to delete filtered stream rules by id
+    pub async fn delete_stream_rules(&self, ids: &[&str]) -> Result<(), CtNlpError> {
+        info!("delete_stream_rules|starting");
+
+        let url = self.url("/2/tweets/search/stream/rules");
+        let body = json!({ "delete": { "ids": ids } });
+        let result: Response<Vec<StreamRule>> = self.post_response(&url, vec![], &body).await?;
+
+        if !result.errors.is_empty() {
+            return Err(CtNlpError::ApiErrors(result.errors));
+        }
+
+        info!("delete_stream_rules|deleted={}", ids.len());
+        info!("delete_stream_rules|completed");
+        Ok(())
+    }
+
+    /// Make the managed (topic_id=) rules on the app match desired
+    /// Rules are compared on value and tag, unmanaged rules are never touched
+    pub async fn sync_stream_rules(&self, desired: &[StreamRule]) -> Result<RuleSync, CtNlpError> {
+        info!("sync_stream_rules|starting");
+
+        let existing = self.stream_rules().await?;
+        let same = |a: &StreamRule, b: &StreamRule| a.value == b.value && a.tag == b.tag;
+
+        let stale: Vec<StreamRule> = existing.iter()
+            .filter(|x| rule_topic_id(x.tag.as_deref()).is_some())
+            .filter(|x| !desired.iter().any(|d| same(d, x)))
+            .cloned()
+            .collect();
+
+        let missing: Vec<StreamRule> = desired.iter()
+            .filter(|d| !existing.iter().any(|x| same(d, x)))
+            .map(|d| StreamRule { id: None, ..d.clone() })
+            .collect();
+
+        let mut sync = RuleSync::default();
+
+        let stale_ids: Vec<&str> = stale.iter().filter_map(|x| x.id.as_deref()).collect();
+        if !stale_ids.is_empty() {
+            self.delete_stream_rules(&stale_ids).await?;
+            sync.deleted = stale;
+        }
+
+        if !missing.is_empty() {
+            sync.added = self.add_stream_rules(&missing).await?;
+        }
+
+        info!("sync_stream_rules|added={}|deleted={}", sync.added.len(), sync.deleted.len());
+        info!("sync_stream_rules|completed");
+        Ok(sync)
+    }
+
+    /// Utility method to consume the filtered stream (v2)
+    /// Every tweet and heartbeat goes to on_event until it returns Stop
+    /// Dropped connections reconnect per policy, 4xx other than 429 are returned
+    pub async fn filtered_stream<F>(&self, policy: &ReconnectPolicy, mut on_event: F) -> Result<StreamStats, CtNlpError>
+    where
+        F: FnMut(StreamEvent) -> StreamControl,
+    {
+        info!("filtered_stream|starting");
+
+        let url = self.url("/2/tweets/search/stream");
+        let params = vec![
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+
+        let mut stats = StreamStats::default();
+        let mut attempt = 0;
+
+        loop {
+            let (slot, token) = self.bearer().await?;
+            let disconnect = match self.stream_http.get(&url)
+                .query(&params)
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+            {
+                Err(e) => Disconnect::Network(e.to_string()),
+                Ok(response) => {
+                    let state = RateLimit::from_headers(response.headers());
+                    rate_limit::record(&url, state);
+
+                    match response.status() {
+                        StatusCode::OK => {
+                            self.auth.succeeded(slot);
+                            stats.connections += 1;
+                            attempt = 0;
+                            info!("filtered_stream|connected|connections={}", stats.connections);
+
+                            match read_stream(response, self.timeout(), &mut stats, &mut on_event).await {
+                                None => {
+                                    info!("filtered_stream|stopped|tweets={}|heartbeats={}", stats.tweets, stats.heartbeats);
+                                    info!("filtered_stream|completed");
+                                    return Ok(stats);
+                                },
+                                Some(x) => x,
+                            }
+                        },
+                        // another credential can connect straight away
+                        StatusCode::TOO_MANY_REQUESTS if self.auth.rate_limited(slot, state.reset) => continue,
+                        StatusCode::UNAUTHORIZED if self.auth.unauthorized(slot) => continue,
+                        StatusCode::TOO_MANY_REQUESTS => Disconnect::RateLimited(state.reset),
+                        s if s.is_server_error() => Disconnect::Http(s),
+                        s => {
+                            info!("filtered_stream|ERR: status={}", s);
+                            return Err(CtNlpError::from_status(s, state.reset));
+                        },
+                    }
+                },
+            };
+
+            attempt += 1;
+            if policy.max_reconnects.is_some_and(|x| attempt > x) {
+                info!("filtered_stream|ERR: reconnects exhausted|attempts={}|{:?}", attempt, disconnect);
+                return Err(match disconnect {
+                    Disconnect::Network(x) => CtNlpError::Request(x),
+                    Disconnect::Http(s) => CtNlpError::Http(s.as_u16()),
+                    Disconnect::RateLimited(reset) => CtNlpError::RateLimited { reset },
+                });
+            }
+
+            let wait = policy.wait(&disconnect, attempt);
+            info!("filtered_stream|disconnected|{:?}|reconnect {} in {:?}", disconnect, attempt, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Read newline delimited tweets until the connection drops, stalls or the handler stops
+/// A read that waits longer than stall (heartbeats come every ~20s) counts as a drop
+/// None means the handler asked to stop
+async fn read_stream<F>(mut response: reqwest::Response, stall: Duration, stats: &mut StreamStats, on_event: &mut F) -> Option<Disconnect>
+where
+    F: FnMut(StreamEvent) -> StreamControl,
+{
+    let mut buf: Vec<u8> = vec![];
+
+    loop {
+        // chunks can split a line or carry several
+        while let Some(end) = buf.iter().position(|b| *b == b'\n') {
+            let bytes: Vec<u8> = buf.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&bytes);
+
+            let event = match line.trim() {
+                "" => {
+                    stats.heartbeats += 1;
+                    StreamEvent::Heartbeat
+                },
+                x => match serde_json::from_str::<StreamTweet>(x) {
+                    Ok(mut tweet) => {
+                        tweet.raw = x.to_string();
+                        stats.tweets += 1;
+                        StreamEvent::Tweet(Box::new(tweet))
+                    },
+                    Err(_) => {
+                        // operational-disconnect and friends arrive as an errors payload
+                        info!("filtered_stream|ERR: unexpected message|{}", x);
+                        return Some(Disconnect::Network(format!("stream message|{}", x)));
+                    },
+                },
+            };
+
+            if on_event(event) == StreamControl::Stop {
+                return None;
+            }
+        }
+
+        match tokio::time::timeout(stall, response.chunk()).await {
+            Ok(Ok(Some(chunk))) => buf.extend_from_slice(&chunk),
+            Ok(Ok(None)) => return Some(Disconnect::Network("stream closed".into())),
+            Ok(Err(e)) => return Some(Disconnect::Network(e.to_string())),
+            Err(_) => return Some(Disconnect::Network(format!("stream stalled|no data for {:?}", stall))),
+        }
+    }
+}
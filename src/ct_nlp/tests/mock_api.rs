@@ -0,0 +1,398 @@
+use ct_nlp::mock::{MockServer, MOCK_ARCHIVE_DATE, MOCK_MISSING_PREFIX, MOCK_SUSPENDED_PREFIX, MOCK_BEARER_TOKEN, MOCK_EMPTY_QUERY, MOCK_TWEET_ID, MOCK_USERNAME, MOCK_USER_ID};
+use ct_nlp::{newest_tweet, raw_path, rate_limit, CtNlpError, RawSink, Granularity, PageLimit, QueryOptions, Relation, SearchScope, TwitterClient, DEFAULT_USER_AGENT};
+
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn client(server: &MockServer) -> TwitterClient {
+    TwitterClient::new(MOCK_BEARER_TOKEN, &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap()
+}
+
+fn utf8_col(df: &polars::frame::DataFrame, name: &str) -> Vec<String> {
+    df.column(name).unwrap()
+        .utf8().unwrap()
+        .into_no_null_iter()
+        .map(String::from)
+        .collect()
+}
+
+#[tokio::test]
+async fn recent_search_follows_next_token() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    assert_eq!(df.height(), 3);
+    assert_eq!(utf8_col(&df, "tweet_id")[2], "1529001000000000001");
+    assert_eq!(utf8_col(&df, "text")[0], "gm \"frens\" — floor is holding 💎");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].param("query"), Some("nft"));
+    assert_eq!(requests[0].param("next_token"), None);
+    assert_eq!(requests[1].param("next_token"), Some("b26v89c19zqg8o3fpz"));
+}
+
+#[tokio::test]
+async fn engagement_columns_are_typed() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    assert!(server.requests()[0].param("tweet.fields").unwrap().contains("public_metrics"));
+
+    let likes = df.column("like_count").unwrap();
+    assert_eq!(likes.dtype(), &polars::datatypes::DataType::UInt64);
+    let likes: Vec<Option<u64>> = likes.u64().unwrap().into_iter().collect();
+    assert_eq!(likes, vec![Some(87), Some(4), None]);
+
+    let reference: Vec<Option<&str>> = df.column("referenced_tweet_type").unwrap().utf8().unwrap().into_iter().collect();
+    assert_eq!(reference, vec![None, Some("replied_to"), None]);
+    let reply_to: Vec<Option<&str>> = df.column("in_reply_to_user_id").unwrap().utf8().unwrap().into_iter().collect();
+    assert_eq!(reply_to[1], Some(MOCK_USER_ID));
+    assert_eq!(utf8_col(&df.head(Some(2)), "lang"), vec!["en", "en"]);
+}
+
+#[tokio::test]
+async fn author_profile_is_joined() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    assert_eq!(server.requests()[0].param("expansions"), Some("author_id"));
+    assert_eq!(utf8_col(&df, "username"), vec![MOCK_USERNAME, "Twitter", MOCK_USERNAME]);
+
+    let followers: Vec<Option<u64>> = df.column("followers_count").unwrap().u64().unwrap().into_iter().collect();
+    assert_eq!(followers, vec![Some(513958), None, Some(513958)]);
+    let verified: Vec<Option<bool>> = df.column("verified").unwrap().bool().unwrap().into_iter().collect();
+    assert_eq!(verified, vec![Some(true), None, Some(true)]);
+
+    // page 2 expands TwitterDev again without profile fields, the page 1 expansion wins
+    let created: Vec<Option<&str>> = df.column("user_created_at").unwrap().utf8().unwrap().into_iter().collect();
+    assert_eq!(created[2], Some("2013-12-14T04:35:55.000Z"));
+}
+
+#[tokio::test]
+async fn newest_tweet_is_highest_id() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    let newest = newest_tweet(&df).unwrap();
+    assert_eq!(newest, Some((MOCK_TWEET_ID.to_string(), "2022-05-24T10:15:00.000Z".to_string())));
+
+    assert_eq!(newest_tweet(&df.head(Some(0))).unwrap(), None);
+}
+
+#[tokio::test]
+async fn recent_search_stops_at_page_and_row_limits() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let df = tw.get_recent_tweets("nft", &QueryOptions::default(), PageLimit::pages(1)).await.unwrap();
+    assert_eq!(df.height(), 2);
+    assert_eq!(server.requests().len(), 1);
+
+    let df = tw.get_recent_tweets("nft", &QueryOptions::default(), PageLimit::rows(1)).await.unwrap();
+    assert_eq!(df.height(), 1);
+    assert_eq!(server.requests().len(), 2);
+    assert_eq!(server.requests()[1].param("max_results"), Some("10"));
+}
+
+#[tokio::test]
+async fn archive_search_sends_window() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let start = format!("{}T00:00:00Z", MOCK_ARCHIVE_DATE);
+    let df = tw.search_all("nft", &QueryOptions::window(&start, "2022-05-23T00:00:00Z"), PageLimit::default()).await.unwrap();
+    assert_eq!(df.height(), 2);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/2/tweets/search/all");
+    assert_eq!(requests[0].param("start_time"), Some(start.as_str()));
+    assert_eq!(requests[0].param("end_time"), Some("2022-05-23T00:00:00Z"));
+
+    let err = tw.search_all("nft", &QueryOptions::window("2022-05-23T00:00:00Z", "2022-05-24T00:00:00Z"), PageLimit::default()).await.unwrap_err();
+    assert!(err.is_skippable());
+
+    let err = tw.search_all("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Invalid(_)));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn query_options_are_sent_on_every_page() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let options = QueryOptions {
+        since_id: Some("1529000000000000000".into()),
+        until_id: Some("1529009999999999999".into()),
+        ..QueryOptions::window("2022-05-20T00:00:00Z", "2022-05-23T00:00:00Z")
+    };
+    tw.get_recent_tweets("nft", &options, PageLimit::default()).await.unwrap();
+    tw.mentions_timeline(MOCK_USER_ID, &QueryOptions::since("1529000000000000000"), PageLimit::default()).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    for request in &requests[0..2] {
+        assert_eq!(request.param("start_time"), Some("2022-05-20T00:00:00Z"));
+        assert_eq!(request.param("end_time"), Some("2022-05-23T00:00:00Z"));
+        assert_eq!(request.param("since_id"), Some("1529000000000000000"));
+        assert_eq!(request.param("until_id"), Some("1529009999999999999"));
+    }
+    assert_eq!(requests[2].param("since_id"), Some("1529000000000000000"));
+    assert_eq!(requests[2].param("start_time"), None);
+
+    let empty = QueryOptions { until_id: Some(String::new()), ..QueryOptions::default() };
+    let err = tw.user_timeline(MOCK_USER_ID, &empty, PageLimit::default()).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Invalid(_)));
+}
+
+#[tokio::test]
+async fn user_timeline_follows_pagination_token() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).user_timeline(MOCK_USER_ID, &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    assert_eq!(df.height(), 3);
+    assert_eq!(utf8_col(&df, "author_id"), vec![MOCK_USER_ID; 3]);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, format!("/2/users/{}/tweets", MOCK_USER_ID));
+    assert_eq!(requests[1].param("pagination_token"), Some("7140dibdnow9c7btw3w29"));
+}
+
+#[tokio::test]
+async fn mentions_timeline_returns_frame() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).mentions_timeline(MOCK_USER_ID, &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    assert_eq!(df.height(), 1);
+    assert_eq!(utf8_col(&df, "text")[0], "@TwitterDev what's the rate limit on counts?");
+}
+
+#[tokio::test]
+async fn users_lookup_returns_user_or_not_found() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let found = tw.users_lookup(MOCK_USERNAME).await.unwrap();
+    let users = found.data.unwrap();
+    assert_eq!(users[0].id, MOCK_USER_ID);
+    assert_eq!(users[0].username, MOCK_USERNAME);
+
+    let missing = tw.users_lookup("not_a_real_user").await.unwrap_err();
+    assert!(matches!(missing, CtNlpError::NotFound(_)));
+    assert!(missing.is_skippable());
+}
+
+#[tokio::test]
+async fn empty_search_is_empty_result() {
+    let server = MockServer::start().unwrap();
+    let err = client(&server).get_recent_tweets(MOCK_EMPTY_QUERY, &QueryOptions::default(), PageLimit::default()).await.unwrap_err();
+
+    assert!(matches!(err, CtNlpError::EmptyResult(_)));
+    assert_eq!(err.step_status(), "C");
+}
+
+#[tokio::test]
+async fn tweet_lookup_expands_author() {
+    let server = MockServer::start().unwrap();
+    let result = client(&server).tweet_lookup(MOCK_TWEET_ID).await.unwrap();
+
+    let tweet = result.data.as_ref().unwrap();
+    let author = result.find_user(tweet.author_id.as_deref().unwrap()).unwrap();
+    assert_eq!(tweet.id, MOCK_TWEET_ID);
+    assert_eq!(author.name, "Twitter Dev");
+}
+
+#[tokio::test]
+async fn tweet_lookup_batch_chunks_ids() {
+    let server = MockServer::start().unwrap();
+
+    let mut ids: Vec<String> = (0..248).map(|i| format!("15290020000000{:05}", i)).collect();
+    ids.push(format!("{}0000000000000001", MOCK_MISSING_PREFIX));
+    ids.push(format!("{}0000000000000002", MOCK_SUSPENDED_PREFIX));
+    let ids: Vec<&str> = ids.iter().map(|x| x.as_str()).collect();
+
+    let lookup = client(&server).tweet_lookup_batch(&ids).await.unwrap();
+    assert_eq!(lookup.df.height(), 248);
+    assert_eq!(utf8_col(&lookup.df, "username")[0], MOCK_USERNAME);
+    assert_eq!(lookup.missing_ids(), vec![ids[248], ids[249]]);
+    assert_eq!(lookup.missing[1].title.as_deref(), Some("Forbidden"));
+
+    let requests = server.requests();
+    let sizes: Vec<usize> = requests.iter().map(|r| r.param("ids").unwrap().split(',').count()).collect();
+    assert_eq!(sizes, vec![100, 100, 50]);
+    assert!(requests.iter().all(|r| r.path == "/2/tweets"));
+}
+
+#[tokio::test]
+async fn users_lookup_batch_reports_missing() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let suspended = format!("{}_account", MOCK_SUSPENDED_PREFIX);
+    let lookup = tw.users_lookup_batch(&[MOCK_USERNAME, "not_a_real_user", &suspended]).await.unwrap();
+    assert_eq!(utf8_col(&lookup.df, "user_id"), vec![MOCK_USER_ID]);
+    assert_eq!(lookup.missing_ids(), vec!["not_a_real_user", suspended.as_str()]);
+    assert_eq!(server.requests()[0].param("usernames"), Some("TwitterDev,not_a_real_user,403_account"));
+
+    let err = tw.users_lookup_batch(&[]).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Invalid(_)));
+}
+
+#[tokio::test]
+async fn followers_land_as_edges() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let df = tw.followers(MOCK_USER_ID, PageLimit::default()).await.unwrap();
+    assert_eq!(utf8_col(&df, "src_id"), vec!["783214", "6253282", "95731075"]);
+    assert_eq!(utf8_col(&df, "dst_id"), vec![MOCK_USER_ID; 3]);
+    assert_eq!(utf8_col(&df, "edge_type"), vec!["follows"; 3]);
+    assert_eq!(df.get_column_names(), vec!["src_id", "dst_id", "edge_type", "observed_at"]);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, format!("/2/users/{}/followers", MOCK_USER_ID));
+    assert_eq!(requests[1].param("pagination_token"), Some("DFEDBNRFT3MHCZZZ"));
+
+    let df = tw.following(MOCK_USER_ID, PageLimit::default()).await.unwrap();
+    assert_eq!(utf8_col(&df, "src_id"), vec![MOCK_USER_ID]);
+    assert_eq!(utf8_col(&df, "dst_id"), vec!["6253282"]);
+}
+
+#[tokio::test]
+async fn tweet_engagement_lands_as_edges() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let likes = tw.liking_users(MOCK_TWEET_ID, PageLimit::default()).await.unwrap();
+    assert_eq!(utf8_col(&likes, "src_id"), vec!["783214", "1324848235714736129"]);
+    assert_eq!(utf8_col(&likes, "edge_type"), vec!["likes"; 2]);
+
+    let retweets = tw.retweeted_by(MOCK_TWEET_ID, PageLimit::default()).await.unwrap();
+    assert_eq!(utf8_col(&retweets, "edge_type"), vec!["retweets"]);
+
+    let quotes = tw.relation_edges("quote_tweets".parse().unwrap(), MOCK_TWEET_ID, PageLimit::default()).await.unwrap();
+    assert_eq!(utf8_col(&quotes, "src_id"), vec!["1529004000000000001", "1529004000000000002"]);
+    assert_eq!(utf8_col(&quotes, "dst_id"), vec![MOCK_TWEET_ID; 2]);
+
+    let err = tw.quote_tweets("1", PageLimit::default()).await.unwrap_err();
+    assert!(err.is_skippable());
+    assert!(Relation::QuoteTweets.is_tweet_relation() && !Relation::Following.is_tweet_relation());
+}
+
+#[tokio::test]
+async fn tweet_counts_are_typed() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).get_tweet_counts("nft", Granularity::Day, SearchScope::Recent, &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    assert_eq!(df.height(), 3);
+    assert_eq!(df.column("tweet_count").unwrap().dtype(), &polars::datatypes::DataType::UInt64);
+    let counts: Vec<u64> = df.column("tweet_count").unwrap().u64().unwrap().into_no_null_iter().collect();
+    assert_eq!(counts, vec![1204, 1388, 512]);
+    assert_eq!(utf8_col(&df, "start")[0], "2022-05-22T00:00:00.000Z");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/2/tweets/counts/recent");
+    assert_eq!(request.param("granularity"), Some("day"));
+    assert_eq!(request.param("max_results"), None);
+}
+
+#[tokio::test]
+async fn archive_counts_follow_next_token() {
+    let server = MockServer::start().unwrap();
+    let options = QueryOptions::window("2022-05-22T00:00:00Z", "2022-05-23T00:00:00Z");
+    let df = client(&server).get_tweet_counts("nft", Granularity::Hour, SearchScope::All, &options, PageLimit::default()).await.unwrap();
+
+    let counts: Vec<u64> = df.column("tweet_count").unwrap().u64().unwrap().into_no_null_iter().collect();
+    assert_eq!(counts, vec![41, 37, 29]);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path, "/2/tweets/counts/all");
+    assert_eq!(requests[0].param("granularity"), Some("hour"));
+    assert_eq!(requests[0].param("start_time"), Some("2022-05-22T00:00:00Z"));
+    assert_eq!(requests[1].param("next_token"), Some("1jzu9lk96gu5npw2"));
+
+    assert_eq!("minute".parse::<Granularity>().unwrap(), Granularity::Minute);
+    assert!("week".parse::<Granularity>().is_err());
+}
+
+#[tokio::test]
+async fn rate_limited_request_is_retried() {
+    let server = MockServer::start().unwrap();
+    server.fail_next(429, 1);
+
+    let tw = client(&server);
+    let result = tw.users_lookup(MOCK_USERNAME).await.unwrap();
+    assert!(result.data.is_some());
+    assert_eq!(server.requests().len(), 2);
+
+    let state = rate_limit(&tw.url("/2/users/by")).unwrap();
+    assert_eq!(state.limit, Some(450));
+    assert!(state.remaining.unwrap() > 0);
+}
+
+#[tokio::test]
+async fn server_error_is_retried() {
+    let server = MockServer::start().unwrap();
+    server.fail_next(503, 1);
+
+    let df = client(&server).get_tweet_counts("nft", Granularity::Day, SearchScope::Recent, &QueryOptions::default(), PageLimit::default()).await.unwrap();
+    assert_eq!(df.height(), 3);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn invalid_token_is_rejected() {
+    let server = MockServer::start().unwrap();
+    let tw = TwitterClient::new("not-the-token", &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap();
+
+    let err = tw.users_lookup(MOCK_USERNAME).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Auth(_)));
+    assert_eq!(err.step_status(), "F");
+    assert_eq!(server.requests().len(), 1);
+
+    assert!(matches!(
+        TwitterClient::new("", &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT),
+        Err(CtNlpError::Auth(_))
+    ));
+}
+
+#[tokio::test]
+async fn raw_pages_land_as_gzipped_ndjson() {
+    let server = MockServer::start().unwrap();
+    let landing_dir = std::env::temp_dir().join(format!("ct_nlp_raw_{}", std::process::id()));
+    let path = raw_path(landing_dir.to_str().unwrap(), 42, "nlp_recent_topic_land", "2022-05-24T12:00:00");
+    assert!(path.ends_with("raw/job_step_id=42/2022-05-24T12:00:00_nlp_recent_topic_land.ndjson.gz"));
+
+    let raw = Arc::new(RawSink::create(&path).unwrap());
+    let tw = client(&server).with_raw_sink(Arc::clone(&raw));
+    tw.get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+    assert_eq!(raw.finish().unwrap(), 2);
+
+    let reader = BufReader::new(flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap()));
+    let pages: Vec<serde_json::Value> = reader.lines().map(|x| serde_json::from_str(&x.unwrap()).unwrap()).collect();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0]["path"], "/2/tweets/search/recent");
+    assert_eq!(pages[0]["status"], 200);
+    // the whole payload is kept, not just the frame columns
+    assert_eq!(pages[0]["response"]["meta"]["next_token"], "b26v89c19zqg8o3fpz");
+    assert_eq!(pages[0]["response"]["includes"]["users"][0]["public_metrics"]["followers_count"], 513958);
+    assert!(pages[1]["query"].as_array().unwrap().iter().any(|x| x[0] == "next_token"));
+
+    let empty = RawSink::create(&landing_dir.join("empty.ndjson.gz")).unwrap();
+    assert_eq!(empty.finish().unwrap(), 0);
+    assert!(!landing_dir.join("empty.ndjson.gz").exists());
+
+    // an early return drops the sink unfinished, the file still decodes to the end
+    let dropped = landing_dir.join("dropped.ndjson.gz");
+    let raw = RawSink::create(&dropped).unwrap();
+    raw.write_page("GET", "/2/tweets/search/recent", &[], 503, "{}").unwrap();
+    drop(raw);
+    let reader = BufReader::new(flate2::read::GzDecoder::new(std::fs::File::open(&dropped).unwrap()));
+    assert_eq!(reader.lines().collect::<Result<Vec<_>, _>>().unwrap().len(), 1);
+    drop(RawSink::create(&landing_dir.join("dropped_empty.ndjson.gz")).unwrap());
+    assert!(!landing_dir.join("dropped_empty.ndjson.gz").exists());
+    let _ = std::fs::remove_dir_all(&landing_dir);
+}
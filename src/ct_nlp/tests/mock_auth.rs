@@ -0,0 +1,107 @@
+use ct_nlp::mock::{MockServer, MOCK_API_KEY, MOCK_API_SECRET, MOCK_BEARER_TOKEN, MOCK_TWEET_ID};
+use ct_nlp::rate_limit::MAX_RETRIES;
+use ct_nlp::{Credential, CredentialPool, CtNlpError, TwitterClient, DEFAULT_USER_AGENT};
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+fn pooled(server: &MockServer, credentials: Vec<Credential>) -> TwitterClient {
+    let pool = CredentialPool::new(credentials).unwrap();
+    TwitterClient::with_credentials(pool, &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap()
+}
+
+fn app(api_key: &str, api_secret: &str) -> Credential {
+    Credential::App { api_key: api_key.into(), api_secret: api_secret.into() }
+}
+
+#[tokio::test]
+async fn client_credentials_token_is_fetched_once() {
+    let server = MockServer::start().unwrap();
+    let tw = pooled(&server, vec![app(MOCK_API_KEY, MOCK_API_SECRET)]);
+
+    tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap();
+    tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].path, "/oauth2/token");
+    assert_eq!(requests[0].body, "grant_type=client_credentials");
+    assert!(requests[0].authorization.as_deref().unwrap().starts_with("Basic "));
+    assert!(requests[1..].iter().all(|r| r.authorization.as_deref() == Some("Bearer mock-bearer-token")));
+}
+
+#[tokio::test]
+async fn refused_credentials_rotate_to_the_next() {
+    let server = MockServer::start().unwrap();
+    let tw = pooled(&server, vec![
+        app(MOCK_API_KEY, "wrong-secret"),
+        Credential::Bearer("revoked-token".into()),
+        Credential::Bearer(MOCK_BEARER_TOKEN.into()),
+    ]);
+
+    tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap();
+    assert_eq!(tw.credentials().available(), 1);
+    assert_eq!(tw.credentials().current(), Some(Credential::Bearer(MOCK_BEARER_TOKEN.into())));
+
+    let paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, vec!["/oauth2/token".to_string(), format!("/2/tweets/{}", MOCK_TWEET_ID), format!("/2/tweets/{}", MOCK_TWEET_ID)]);
+}
+
+#[tokio::test]
+async fn rate_limited_credential_rotates_without_waiting() {
+    let server = MockServer::start().unwrap();
+    server.fail_next(429, 1);
+    let tw = pooled(&server, vec![Credential::Bearer(MOCK_BEARER_TOKEN.into()), app(MOCK_API_KEY, MOCK_API_SECRET)]);
+
+    let started = Instant::now();
+    tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(matches!(tw.credentials().current(), Some(Credential::App { .. })));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn rate_limited_pool_gives_up_after_max_retries() {
+    let server = MockServer::start().unwrap();
+    // the mock's 429 reset is now, a stale reset must not make the credential usable again at once
+    server.fail_next(429, 100);
+    let tw = pooled(&server, vec![Credential::Bearer("second-token".into()), Credential::Bearer(MOCK_BEARER_TOKEN.into())]);
+
+    let err = tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::RateLimited { .. }));
+    assert_eq!(server.requests().len(), MAX_RETRIES as usize + 1);
+}
+
+#[tokio::test]
+async fn every_credential_revoked_is_an_auth_error() {
+    let server = MockServer::start().unwrap();
+    let tw = pooled(&server, vec![Credential::Bearer("revoked-token".into()), app("unknown-key", MOCK_API_SECRET)]);
+
+    let err = tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Auth(_)));
+    assert_eq!(tw.credentials().available(), 0);
+
+    let err = tw.tweet_lookup(MOCK_TWEET_ID).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Auth(_)));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn pool_reads_every_credential_from_config() {
+    let mut config = BTreeMap::new();
+    assert!(matches!(CredentialPool::from_config(&config), Err(CtNlpError::Auth(_))));
+
+    config.insert("bearer_token".to_string(), "a".to_string());
+    config.insert("bearer_tokens".to_string(), "b, c".to_string());
+    config.insert("twitter_credentials".to_string(), "k1:s1,k2:s2".to_string());
+    assert_eq!(CredentialPool::from_config(&config).unwrap().len(), 5);
+
+    config.insert("twitter_api_key".to_string(), "k".to_string());
+    assert!(CredentialPool::from_config(&config).is_err());
+    config.insert("twitter_api_secret".to_string(), "s".to_string());
+
+    let pool = CredentialPool::from_config(&config).unwrap();
+    assert_eq!(pool.len(), 6);
+    assert_eq!(pool.current(), Some(Credential::Bearer("a".into())));
+    assert_eq!(format!("{:?}", app("k", "s")), "Credential(app:k)");
+}
@@ -0,0 +1,129 @@
+use ct_nlp::mock::{MockServer, MOCK_BEARER_TOKEN, MOCK_TWEET_ID};
+use ct_nlp::stream::topic_rule;
+use ct_nlp::{Cassette, CassetteMode, Credential, CredentialPool, CtNlpError, PageLimit, QueryOptions, TwitterClient, DEFAULT_USER_AGENT};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// nothing listens here, replay must not touch the network
+const DEAD_URL: &str = "http://127.0.0.1:9";
+
+fn cassette_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ct_nlp_cassette_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn client(token: &str, url: &str, mode: CassetteMode, dir: &Path) -> TwitterClient {
+    let cassette = Cassette::new(mode, dir.to_str().unwrap()).unwrap();
+    TwitterClient::new(token, url, Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap().with_cassette(cassette)
+}
+
+#[tokio::test]
+async fn recorded_pages_replay_offline() {
+    let server = MockServer::start().unwrap();
+    let dir = cassette_dir("pages");
+
+    let live = client(MOCK_BEARER_TOKEN, &server.url(), CassetteMode::Record, &dir)
+        .get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    let replayed = client(MOCK_BEARER_TOKEN, DEAD_URL, CassetteMode::Replay, &dir)
+        .get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+    assert!(live.frame_equal_missing(&replayed));
+    assert_eq!(server.requests().len(), 2);
+
+    let err = client(MOCK_BEARER_TOKEN, DEAD_URL, CassetteMode::Replay, &dir)
+        .get_recent_tweets("bayc", &QueryOptions::default(), PageLimit::default()).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Invalid(_)));
+}
+
+#[tokio::test]
+async fn app_credentials_replay_without_a_token_request() {
+    let server = MockServer::start().unwrap();
+    let dir = cassette_dir("app");
+
+    client(MOCK_BEARER_TOKEN, &server.url(), CassetteMode::Record, &dir)
+        .get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    let pool = CredentialPool::new(vec![Credential::App { api_key: "key".into(), api_secret: "secret".into() }]).unwrap();
+    let cassette = Cassette::new(CassetteMode::Replay, dir.to_str().unwrap()).unwrap();
+    let replayed = TwitterClient::with_credentials(pool, DEAD_URL, Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap()
+        .with_cassette(cassette)
+        .get_recent_tweets("nft", &QueryOptions::default(), PageLimit::default()).await.unwrap();
+    assert!(replayed.height() > 0);
+}
+
+#[tokio::test]
+async fn repeated_requests_replay_in_order() {
+    let server = MockServer::start().unwrap();
+    let dir = cassette_dir("rules");
+
+    let tw = client(MOCK_BEARER_TOKEN, &server.url(), CassetteMode::Record, &dir);
+    assert!(tw.stream_rules().await.unwrap().is_empty());
+    tw.add_stream_rules(&[topic_rule(1, "nft")]).await.unwrap();
+    assert_eq!(tw.stream_rules().await.unwrap().len(), 1);
+
+    let tw = client(MOCK_BEARER_TOKEN, DEAD_URL, CassetteMode::Replay, &dir);
+    assert!(tw.stream_rules().await.unwrap().is_empty());
+    assert_eq!(tw.add_stream_rules(&[topic_rule(1, "nft")]).await.unwrap()[0].tag.as_deref(), Some("topic_id=1"));
+    assert_eq!(tw.stream_rules().await.unwrap().len(), 1);
+    // past the end the last recording repeats
+    assert_eq!(tw.stream_rules().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn recording_again_replaces_the_old_cassette() {
+    let server = MockServer::start().unwrap();
+    let dir = cassette_dir("rerecord");
+
+    let tw = client(MOCK_BEARER_TOKEN, &server.url(), CassetteMode::Record, &dir);
+    tw.add_stream_rules(&[topic_rule(1, "nft")]).await.unwrap();
+    assert_eq!(tw.stream_rules().await.unwrap().len(), 1);
+
+    // a new session sees no rules, replay must serve that and not the first session's rule
+    let server = MockServer::start().unwrap();
+    let tw = client(MOCK_BEARER_TOKEN, &server.url(), CassetteMode::Record, &dir);
+    assert!(tw.stream_rules().await.unwrap().is_empty());
+
+    let tw = client(MOCK_BEARER_TOKEN, DEAD_URL, CassetteMode::Replay, &dir);
+    assert!(tw.stream_rules().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn recorded_errors_replay_as_errors() {
+    let server = MockServer::start().unwrap();
+    let dir = cassette_dir("errors");
+
+    let err = client("not-the-token", &server.url(), CassetteMode::Record, &dir)
+        .tweet_lookup(MOCK_TWEET_ID).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Auth(_)));
+
+    let err = client("not-the-token", DEAD_URL, CassetteMode::Replay, &dir)
+        .tweet_lookup(MOCK_TWEET_ID).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Auth(_)));
+
+    let file = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    let body = std::fs::read_to_string(file).unwrap();
+    assert!(body.contains(r#""status": 401"#));
+    assert!(!body.contains("not-the-token"));
+}
+
+#[test]
+fn cassette_mode_comes_from_config() {
+    let mut config = BTreeMap::new();
+    assert!(Cassette::from_config(&config).unwrap().is_none());
+
+    config.insert("cassette_mode".to_string(), "replay".to_string());
+    assert!(matches!(Cassette::from_config(&config), Err(CtNlpError::Invalid(_))));
+
+    let dir = cassette_dir("config");
+    config.insert("cassette_mode".to_string(), "record".to_string());
+    config.insert("cassette_dir".to_string(), dir.to_str().unwrap().to_string());
+    assert_eq!(Cassette::from_config(&config).unwrap().unwrap().mode(), CassetteMode::Record);
+    assert!(dir.is_dir());
+
+    config.insert("cassette_mode".to_string(), "rewind".to_string());
+    assert!(Cassette::from_config(&config).is_err());
+}
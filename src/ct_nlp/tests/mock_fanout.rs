@@ -0,0 +1,70 @@
+use ct_nlp::mock::{MockServer, MOCK_BEARER_TOKEN, MOCK_EMPTY_QUERY, MOCK_USER_ID};
+use ct_nlp::{CtNlpError, PageLimit, QueryOptions, TwitterClient, DEFAULT_USER_AGENT};
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+fn client(server: &MockServer, concurrency: usize) -> TwitterClient {
+    TwitterClient::new(MOCK_BEARER_TOKEN, &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap()
+        .with_concurrency(concurrency)
+}
+
+#[tokio::test]
+async fn timelines_run_concurrently_within_the_bound() {
+    let server = MockServer::start().unwrap();
+    server.set_latency(Duration::from_millis(200));
+
+    let user_ids: Vec<String> = (0..8).map(|i| format!("{}{}", MOCK_USER_ID, i)).collect();
+    let user_ids: Vec<&str> = user_ids.iter().map(|x| x.as_str()).collect();
+
+    let started = Instant::now();
+    let fanout = client(&server, 4).user_timelines(&user_ids, &QueryOptions::default(), PageLimit::pages(1)).await;
+    let elapsed = started.elapsed();
+
+    // 8 requests 4 at a time is two round trips, serially it would be eight
+    assert!(elapsed < Duration::from_millis(1200), "elapsed={:?}", elapsed);
+    assert!(server.max_in_flight() > 1);
+    assert!(server.max_in_flight() <= 4);
+
+    let keys: Vec<&str> = fanout.results.iter().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(keys, user_ids);
+    assert!(fanout.failed().is_empty());
+    assert_eq!(fanout.stacked().unwrap().unwrap().height(), 16);
+}
+
+#[tokio::test]
+async fn failed_keys_do_not_stop_the_rest() {
+    let server = MockServer::start().unwrap();
+    let topics = ["nft", MOCK_EMPTY_QUERY, "", "bayc"];
+
+    let fanout = client(&server, 2).get_recent_tweets_many(&topics, &QueryOptions::default(), PageLimit::pages(1)).await;
+
+    let failed: Vec<&str> = fanout.failed().iter().map(|(k, _)| *k).collect();
+    assert_eq!(failed, vec![MOCK_EMPTY_QUERY, ""]);
+    assert_eq!(fanout.stacked().unwrap().unwrap().height(), 4);
+
+    // the empty topic is a bad argument, not an empty result
+    assert!(matches!(fanout.into_frame(), Err(CtNlpError::Invalid(_))));
+}
+
+#[tokio::test]
+async fn nothing_to_land_is_not_an_error() {
+    let server = MockServer::start().unwrap();
+    let topics = [MOCK_EMPTY_QUERY, MOCK_EMPTY_QUERY];
+
+    let fanout = client(&server, 2).get_recent_tweets_many(&topics, &QueryOptions::default(), PageLimit::pages(1)).await;
+    assert!(fanout.into_frame().unwrap().is_none());
+}
+
+#[test]
+fn concurrency_comes_from_config() {
+    let mut config = BTreeMap::new();
+    config.insert(String::from("bearer_token"), String::from(MOCK_BEARER_TOKEN));
+    assert_eq!(TwitterClient::from_config(&config).unwrap().concurrency(), ct_nlp::DEFAULT_CONCURRENCY);
+
+    config.insert(String::from("twitter_concurrency"), String::from("16"));
+    assert_eq!(TwitterClient::from_config(&config).unwrap().concurrency(), 16);
+
+    config.insert(String::from("twitter_concurrency"), String::from("0"));
+    assert!(matches!(TwitterClient::from_config(&config), Err(CtNlpError::Invalid(_))));
+}
@@ -0,0 +1,105 @@
+use ct_nlp::mock::{MockServer, MOCK_BEARER_TOKEN, MOCK_STREAM_HEARTBEATS, MOCK_STREAM_TWEETS};
+use ct_nlp::stream::{rule_topic_id, topic_rule};
+use ct_nlp::{CtNlpError, ReconnectPolicy, StreamControl, StreamEvent, StreamRule, TwitterClient, DEFAULT_USER_AGENT};
+
+use std::time::Duration;
+
+fn client(server: &MockServer) -> TwitterClient {
+    TwitterClient::new(MOCK_BEARER_TOKEN, &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap()
+}
+
+fn fast_policy(max_reconnects: Option<usize>) -> ReconnectPolicy {
+    let ms = Duration::from_millis(1);
+    ReconnectPolicy {
+        network_base: ms,
+        network_max: ms,
+        http_base: ms,
+        http_max: ms,
+        rate_limit_base: ms,
+        rate_limit_max: ms,
+        max_reconnects,
+    }
+}
+
+#[tokio::test]
+async fn rule_sync_only_touches_managed_rules() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let manual = StreamRule { id: None, value: "from:TwitterDev".into(), tag: Some("manual".into()) };
+    tw.add_stream_rules(&[manual, topic_rule(9, "old topic")]).await.unwrap();
+
+    let desired = vec![topic_rule(1, "nft"), topic_rule(2, "bayc OR mayc")];
+    let sync = tw.sync_stream_rules(&desired).await.unwrap();
+    assert_eq!(sync.added.len(), 2);
+    assert!(sync.added.iter().all(|x| x.id.is_some()));
+    assert_eq!(sync.deleted.len(), 1);
+    assert_eq!(sync.deleted[0].tag.as_deref(), Some("topic_id=9"));
+
+    let rules = tw.stream_rules().await.unwrap();
+    let mut tags: Vec<&str> = rules.iter().filter_map(|x| x.tag.as_deref()).collect();
+    tags.sort();
+    assert_eq!(tags, vec!["manual", "topic_id=1", "topic_id=2"]);
+
+    let sync = tw.sync_stream_rules(&desired).await.unwrap();
+    assert!(sync.added.is_empty() && sync.deleted.is_empty());
+}
+
+#[tokio::test]
+async fn stream_reconnects_until_stopped() {
+    let server = MockServer::start().unwrap();
+    server.fail_next(503, 1);
+
+    let mut tweets = vec![];
+    let mut heartbeats = 0;
+    let stats = client(&server).filtered_stream(&fast_policy(Some(3)), |event| {
+        match event {
+            StreamEvent::Tweet(tweet) => tweets.push(tweet),
+            StreamEvent::Heartbeat => heartbeats += 1,
+        }
+
+        match tweets.len() < MOCK_STREAM_TWEETS + 2 {
+            true => StreamControl::Continue,
+            false => StreamControl::Stop,
+        }
+    }).await.unwrap();
+
+    assert_eq!(stats.connections, 2);
+    assert_eq!(stats.tweets, MOCK_STREAM_TWEETS + 2);
+    // stopped on the second tweet of the second connection, one heartbeat in
+    assert_eq!(heartbeats, MOCK_STREAM_HEARTBEATS + 1);
+    assert_eq!(stats.heartbeats, heartbeats);
+    assert_eq!(tweets[3].data.id, tweets[0].data.id);
+    // the untouched line rides along for raw landing
+    let raw: serde_json::Value = serde_json::from_str(&tweets[0].raw).unwrap();
+    assert_eq!(raw["data"]["id"], tweets[0].data.id.as_str());
+
+    let topics: Vec<i32> = tweets[1].matching_rules.iter().filter_map(|x| rule_topic_id(x.tag.as_deref())).collect();
+    assert_eq!(topics, vec![1, 2]);
+    assert_eq!(tweets[0].includes.as_ref().unwrap().users[0].username, "TwitterDev");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|r| r.path == "/2/tweets/search/stream"));
+    assert!(requests[1].param("tweet.fields").unwrap().contains("public_metrics"));
+}
+
+#[tokio::test]
+async fn stream_gives_up_after_max_reconnects() {
+    let server = MockServer::start().unwrap();
+    server.fail_next(503, 3);
+
+    let err = client(&server).filtered_stream(&fast_policy(Some(2)), |_| StreamControl::Continue).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Http(503)));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn stream_auth_failure_is_not_retried() {
+    let server = MockServer::start().unwrap();
+    let tw = TwitterClient::new("not-the-token", &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap();
+
+    let err = tw.filtered_stream(&fast_policy(None), |_| StreamControl::Continue).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Auth(_)));
+    assert_eq!(server.requests().len(), 1);
+}
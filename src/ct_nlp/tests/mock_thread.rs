@@ -0,0 +1,55 @@
+use ct_nlp::mock::{MockServer, MOCK_BEARER_TOKEN, MOCK_CONVERSATION_ID};
+use ct_nlp::{CtNlpError, PageLimit, QueryOptions, SearchScope, TwitterClient, DEFAULT_USER_AGENT};
+
+use std::time::Duration;
+
+fn client(server: &MockServer) -> TwitterClient {
+    TwitterClient::new(MOCK_BEARER_TOKEN, &server.url(), Duration::from_secs(5), DEFAULT_USER_AGENT).unwrap()
+}
+
+fn opt_col(df: &polars::frame::DataFrame, name: &str) -> Vec<Option<String>> {
+    df.column(name).unwrap()
+        .utf8().unwrap()
+        .into_iter()
+        .map(|x| x.map(String::from))
+        .collect()
+}
+
+#[tokio::test]
+async fn reply_tree_is_rebuilt_from_a_reply() {
+    let server = MockServer::start().unwrap();
+    let df = client(&server).conversation_threads(&["1529002000000000002"], SearchScope::Recent, &QueryOptions::default(), PageLimit::default()).await.unwrap();
+
+    let ids: Vec<String> = opt_col(&df, "tweet_id").into_iter().flatten().collect();
+    assert_eq!(ids, vec!["1529002000000000002", "1529002000000000004", "1529002000000000003", "1529002000000000001", MOCK_CONVERSATION_ID]);
+
+    // replied_to wins over quoted, the root has no parent
+    let parents = opt_col(&df, "parent_id");
+    assert_eq!(parents[0].as_deref(), Some("1529002000000000001"));
+    assert_eq!(parents[4], None);
+
+    assert!(opt_col(&df, "root_id").iter().all(|x| x.as_deref() == Some(MOCK_CONVERSATION_ID)));
+
+    // the reply to a deleted tweet cannot be placed in the tree
+    let depths: Vec<Option<u32>> = df.column("depth").unwrap().u32().unwrap().into_iter().collect();
+    assert_eq!(depths, vec![Some(2), None, Some(1), Some(1), Some(0)]);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1].param("query"), Some(format!("conversation_id:{}", MOCK_CONVERSATION_ID).as_str()));
+    assert_eq!(requests[2].param("ids"), Some(MOCK_CONVERSATION_ID));
+}
+
+#[tokio::test]
+async fn conversation_without_replies_is_just_the_root() {
+    let server = MockServer::start().unwrap();
+    let tw = client(&server);
+
+    let df = tw.conversation_threads(&["1529009000000000000"], SearchScope::Recent, &QueryOptions::default(), PageLimit::default()).await.unwrap();
+    assert_eq!(df.height(), 1);
+    assert_eq!(df.column("depth").unwrap().u32().unwrap().into_iter().collect::<Vec<_>>(), vec![Some(0)]);
+    assert_eq!(server.requests().len(), 2);
+
+    let err = tw.conversation(MOCK_CONVERSATION_ID, SearchScope::All, &QueryOptions::default(), PageLimit::default()).await.unwrap_err();
+    assert!(matches!(err, CtNlpError::Invalid(_)));
+}
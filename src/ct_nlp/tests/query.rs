@@ -0,0 +1,81 @@
+use ct_nlp::{topic_query, CtNlpError, SearchQuery, SearchScope};
+
+#[test]
+fn renders_terms_groups_and_filters() {
+    let query = SearchQuery::new()
+        .any_of(&["bayc", "mayc"])
+        .phrase("bored ape")
+        .hashtag("#nft")
+        .cashtag("$APE")
+        .from_user("@yugalabs")
+        .exclude("giveaway")
+        .retweets(false)
+        .lang("en")
+        .render(SearchScope::Recent)
+        .unwrap();
+
+    assert_eq!(query, "(bayc OR mayc) \"bored ape\" #nft $APE from:yugalabs -giveaway -is:retweet lang:en");
+}
+
+#[test]
+fn rejects_invalid_terms() {
+    let cases = [
+        SearchQuery::new().from_user("not a user"),
+        SearchQuery::new().keyword("two words"),
+        SearchQuery::new().keyword("OR"),
+        SearchQuery::new().cashtag("$1inch"),
+        SearchQuery::new().keyword("ape").lang("English"),
+    ];
+
+    for x in cases {
+        assert!(matches!(x.render(SearchScope::Recent), Err(CtNlpError::Invalid(_))), "{:?}", x);
+    }
+}
+
+#[test]
+fn filters_cannot_stand_alone() {
+    let query = SearchQuery::new()
+        .exclude("spam")
+        .exclude_user("bot")
+        .retweets(false)
+        .has_media(true)
+        .lang("en");
+
+    assert!(matches!(query.render(SearchScope::Recent), Err(CtNlpError::Invalid(_))));
+}
+
+#[test]
+fn length_limit_follows_scope() {
+    let words: Vec<String> = (0..100).map(|i| format!("word{:03}", i)).collect();
+    let query = SearchQuery {
+        all: words,
+        ..SearchQuery::default()
+    };
+
+    // 100 seven character words and their separators, 799 characters
+    assert!(matches!(query.render(SearchScope::Recent), Err(CtNlpError::Invalid(_))));
+    assert_eq!(query.render(SearchScope::All).unwrap().len(), 799);
+}
+
+#[test]
+fn topic_query_prefers_search_query() {
+    let query = SearchQuery::new().keyword("bayc").replies(false);
+    let json = query.to_json().unwrap();
+    assert_eq!(SearchQuery::from_json(&json).unwrap(), query);
+
+    assert_eq!(topic_query("bayc", Some(&json), SearchScope::Recent).unwrap(), "bayc -is:reply");
+    assert_eq!(topic_query("bayc lang:en", None, SearchScope::Recent).unwrap(), "bayc lang:en");
+    assert_eq!(topic_query("bayc lang:en", Some(" "), SearchScope::Recent).unwrap(), "bayc lang:en");
+
+    // partial json fills the rest with defaults
+    assert_eq!(topic_query("", Some(r#"{"hashtags": ["nft"]}"#), SearchScope::All).unwrap(), "#nft");
+    assert!(matches!(topic_query("", Some("{not json"), SearchScope::Recent), Err(CtNlpError::Invalid(_))));
+}
+
+#[test]
+fn unknown_search_query_fields_are_rejected() {
+    // hashtag for hashtags, the filter must not be dropped silently
+    let err = topic_query("bayc", Some(r#"{"all": ["bayc"], "hashtag": ["nft"]}"#), SearchScope::Recent).unwrap_err();
+    assert!(matches!(err, CtNlpError::Invalid(_)));
+    assert!(err.to_string().contains("hashtag"));
+}
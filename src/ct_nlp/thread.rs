@@ -0,0 +1,178 @@
+//! Conversation threads: every tweet sharing a conversation_id, rebuilt into a reply tree
+//! The conversation_id is the id of the tweet that started it, so the root is
+//! looked up on its own when search does not return it
+
+use std::collections::{HashMap, HashSet};
+
+use log::info;
+use polars::prelude::NamedFrom;
+use polars::series::Series;
+use polars::frame::DataFrame;
+
+use crate::error::CtNlpError;
+use crate::models::{Response, Tweet, User};
+use crate::{tweets_to_df, PageLimit, QueryOptions, SearchScope, TwitterClient, TWEET_FIELDS, USER_FIELDS};
+
+/// Id of the tweet this one replies to, quotes and retweets are not part of the tree
+pub fn parent_id(tweet: &Tweet) -> Option<&str> {
+    tweet.referenced_tweets.iter()
+        .find(|x| x.reference_type == "replied_to")
+        .map(|x| x.id.as_str())
+}
+
+/// Hops from each tweet up to its conversation root
+/// None when the chain is broken by a tweet we do not have (deleted, protected, outside the window)
+fn depths<'a>(tweets: &[&'a Tweet]) -> HashMap<&'a str, Option<u32>> {
+    let parents: HashMap<&str, Option<&str>> = tweets.iter()
+        .map(|t| (t.id.as_str(), parent_id(t)))
+        .collect();
+
+    let mut depths = HashMap::new();
+    for tweet in tweets {
+        let root = tweet.conversation_id.as_deref().unwrap_or(&tweet.id);
+
+        let mut depth = Some(0);
+        let mut current = tweet.id.as_str();
+        while current != root {
+            // a cycle can only come from bad data, give up rather than spin
+            depth = match (parents.get(current), depth) {
+                (Some(Some(parent)), Some(d)) if d < tweets.len() as u32 => {
+                    current = parent;
+                    Some(d + 1)
+                },
+                _ => None,
+            };
+
+            if depth.is_none() { break; }
+        }
+
+        depths.insert(tweet.id.as_str(), depth);
+    }
+
+    depths
+}
+
+/// Utility method to flatten a set of conversations into a threads DataFrame
+/// tweets are deduplicated on id, first one wins
+/// cols: see tweets_to_df, then parent_id, root_id, depth
+pub fn threads_to_df(tweets: &[Tweet], users: &[User]) -> Result<DataFrame, CtNlpError> {
+    let mut seen = HashSet::new();
+    let unique: Vec<&Tweet> = tweets.iter()
+        .filter(|t| t.author_id.is_some() && t.created_at.is_some())
+        .filter(|t| seen.insert(t.id.as_str()))
+        .collect();
+
+    let depths = depths(&unique);
+
+    let parent_vec: Vec<Option<&str>> = unique.iter().map(|t| parent_id(t)).collect();
+    let root_vec: Vec<&str> = unique.iter().map(|t| t.conversation_id.as_deref().unwrap_or(&t.id)).collect();
+    let depth_vec: Vec<Option<u32>> = unique.iter().map(|t| depths.get(t.id.as_str()).copied().flatten()).collect();
+
+    let owned: Vec<Tweet> = unique.into_iter().cloned().collect();
+    let mut df = tweets_to_df(&owned, users)?;
+    df.hstack_mut(&[
+        Series::new("parent_id", parent_vec),
+        Series::new("root_id", root_vec),
+        Series::new("depth", depth_vec),
+    ])?;
+
+    Ok(df)
+}
+
+impl TwitterClient {
+    /// Utility method to fetch every tweet in a conversation via search (v2)
+    /// scope All needs a start_time in options, same as search_all
+    pub async fn conversation(
+        &self,
+        conversation_id: &str,
+        scope: SearchScope,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<Response<Vec<Tweet>>, CtNlpError> {
+        info!("conversation|starting");
+        info!("conversation|conversation_id={}|scope={}", conversation_id, scope.as_str());
+
+        if conversation_id.is_empty() {
+            return Err(CtNlpError::Invalid(format!("conversation|conversation_id is not valid, conversation_id={}", conversation_id)));
+        }
+
+        if scope == SearchScope::All && options.start_time.is_none() {
+            return Err(CtNlpError::Invalid("conversation|start_time is required for scope=all".into()));
+        }
+
+        let url = self.url(&format!("/2/tweets/search/{}", scope.as_str()));
+        let query = format!("conversation_id:{}", conversation_id);
+
+        let mut params = vec![
+            ("query", query.as_str()),
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+        params.extend(options.params()?);
+
+        let result: Response<Vec<Tweet>> = self.get_pages(&url, params, "next_token", limit).await?;
+
+        info!("conversation|tweets={}", result.len());
+        info!("conversation|completed");
+        Ok(result)
+    }
+
+    /// Utility method to rebuild the reply threads the given tweets belong to
+    /// each tweet is looked up for its conversation_id, every conversation is searched
+    /// and roots missing from search are looked up, limit applies per conversation
+    /// cols: see threads_to_df
+    pub async fn conversation_threads(
+        &self,
+        tweet_ids: &[&str],
+        scope: SearchScope,
+        options: &QueryOptions,
+        limit: PageLimit,
+    ) -> Result<DataFrame, CtNlpError> {
+        info!("conversation_threads|starting");
+        info!("conversation_threads|ids={}", tweet_ids.len());
+
+        let params = vec![
+            ("expansions", "author_id"),
+            ("tweet.fields", TWEET_FIELDS),
+            ("user.fields", USER_FIELDS),
+        ];
+
+        let mut result: Response<Vec<Tweet>> = self.lookup_batches(&self.url("/2/tweets"), "ids", tweet_ids, params.clone()).await?;
+        for err in &result.errors {
+            info!("conversation_threads|ERR: tweet unavailable|{}", err.detail.as_deref().unwrap_or_default());
+        }
+
+        let mut conversations: Vec<String> = vec![];
+        for tweet in result.data.as_deref().unwrap_or_default() {
+            let c_id = tweet.conversation_id.clone().unwrap_or_else(|| tweet.id.clone());
+            if !conversations.contains(&c_id) {
+                conversations.push(c_id);
+            }
+        }
+
+        for c_id in &conversations {
+            match self.conversation(c_id, scope, options, limit).await {
+                Ok(replies) => result.extend(replies),
+                Err(err) if err.is_skippable() => info!("conversation_threads|no replies for conversation_id={}|{}", c_id, err),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let have: HashSet<&str> = result.data.as_deref().unwrap_or_default().iter().map(|t| t.id.as_str()).collect();
+        let roots: Vec<&str> = conversations.iter().map(|x| x.as_str()).filter(|x| !have.contains(x)).collect();
+
+        if !roots.is_empty() {
+            // a deleted root only shows up in errors, its replies keep a null depth
+            let found: Response<Vec<Tweet>> = self.lookup_batches(&self.url("/2/tweets"), "ids", &roots, params).await?;
+            info!("conversation_threads|roots={}|missing={}", roots.len(), found.errors.len());
+            result.extend(Response { errors: vec![], ..found });
+        }
+
+        let df = threads_to_df(result.data.as_deref().unwrap_or_default(), result.users())?;
+
+        info!("conversation_threads|conversations={}|tweets={}", conversations.len(), df.height());
+        info!("conversation_threads|completed");
+        Ok(df)
+    }
+}
@@ -0,0 +1,594 @@
+//! Event decoding from a json ABI, for contracts beyond the NFT standards
+//! Every selected event lands in one frame: the log columns, then a column per event parameter
+//! named {event}_{param}, null on rows of other events
+//!
+//! Solidity to Arrow:
+//! uint8..uint32 / int8..int32 -> UInt32 / Int32, up to 64 bits -> UInt64 / Int64,
+//! wider (uint256, int128, ..) -> Utf8 decimal strings, polars has no 256 bit integer
+//! address, bytes, bytesN -> Utf8 0x hex, string -> Utf8, bool -> Boolean
+//! T[] / T[k] -> List(T), arrays of arrays or tuples -> List(Utf8) with each element as json
+//! tuple -> one column per component, {event}_{param}_{component}
+//! indexed string, bytes, arrays and tuples only exist as their keccak256 -> Utf8 0x hex
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use num_bigint::{BigInt, BigUint};
+use polars::chunked_array::builder::get_list_builder;
+use polars::prelude::{DataType, IntoSeries, NamedFrom, Schema};
+use polars::series::Series;
+use polars::frame::DataFrame;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::EvmError;
+use crate::keccak::keccak256_hex;
+use crate::models::{hex_bytes, Log};
+
+/// json ABI entry, functions, errors and constructors are skipped
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbiEntry {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub inputs: Vec<AbiParam>,
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbiParam {
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub indexed: bool,
+    /// tuple members
+    #[serde(default)]
+    pub components: Vec<AbiParam>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiType {
+    Uint(usize),
+    Int(usize),
+    Address,
+    Bool,
+    FixedBytes(usize),
+    /// external function pointer, address + selector, 24 bytes on the wire
+    Function,
+    Bytes,
+    String,
+    Array(Box<AbiType>),
+    FixedArray(Box<AbiType>, usize),
+    Tuple(Vec<(String, AbiType)>),
+}
+
+impl AbiType {
+    /// Parse a solidity type, components fill in tuple, tuple[] and tuple[k]
+    pub fn parse(kind: &str, components: &[AbiParam]) -> Result<Self, EvmError> {
+        let invalid = || EvmError::Invalid(format!("AbiType|unsupported type={}", kind));
+
+        if let Some(inner) = kind.strip_suffix(']') {
+            let open = inner.rfind('[').ok_or_else(invalid)?;
+            let element = Box::new(Self::parse(&inner[..open], components)?);
+            return match &inner[open + 1..] {
+                "" => Ok(AbiType::Array(element)),
+                n => Ok(AbiType::FixedArray(element, n.parse().map_err(|_| invalid())?)),
+            };
+        }
+
+        let bits = |digits: &str| -> Result<usize, EvmError> {
+            match digits {
+                "" => Ok(256),
+                n => n.parse().ok().filter(|n| *n > 0 && *n <= 256 && n % 8 == 0).ok_or_else(invalid),
+            }
+        };
+
+        match kind {
+            "address" => Ok(AbiType::Address),
+            "bool" => Ok(AbiType::Bool),
+            "string" => Ok(AbiType::String),
+            "bytes" => Ok(AbiType::Bytes),
+            "function" => Ok(AbiType::Function),
+            "tuple" => components.iter()
+                .map(|x| Ok((x.name.clone(), Self::parse(&x.kind, &x.components)?)))
+                .collect::<Result<Vec<_>, EvmError>>()
+                .map(AbiType::Tuple),
+            x if x.starts_with("uint") => Ok(AbiType::Uint(bits(&x[4..])?)),
+            x if x.starts_with("int") => Ok(AbiType::Int(bits(&x[3..])?)),
+            x if x.starts_with("bytes") => {
+                let n: usize = x[5..].parse().map_err(|_| invalid())?;
+                match n {
+                    1..=32 => Ok(AbiType::FixedBytes(n)),
+                    _ => Err(invalid()),
+                }
+            },
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Type as it appears in a canonical signature, e.g. (address,uint256)[]
+    pub fn canonical(&self) -> String {
+        match self {
+            AbiType::Uint(n) => format!("uint{}", n),
+            AbiType::Int(n) => format!("int{}", n),
+            AbiType::Address => String::from("address"),
+            AbiType::Bool => String::from("bool"),
+            AbiType::FixedBytes(n) => format!("bytes{}", n),
+            AbiType::Function => String::from("function"),
+            AbiType::Bytes => String::from("bytes"),
+            AbiType::String => String::from("string"),
+            AbiType::Array(t) => format!("{}[]", t.canonical()),
+            AbiType::FixedArray(t, n) => format!("{}[{}]", t.canonical(), n),
+            AbiType::Tuple(ts) => format!("({})", ts.iter().map(|(_, t)| t.canonical()).collect::<Vec<_>>().join(",")),
+        }
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            AbiType::Bytes | AbiType::String | AbiType::Array(_) => true,
+            AbiType::FixedArray(t, _) => t.is_dynamic(),
+            AbiType::Tuple(ts) => ts.iter().any(|(_, t)| t.is_dynamic()),
+            _ => false,
+        }
+    }
+
+    /// Bytes the type takes in the head of its enclosing tuple
+    fn head_size(&self) -> usize {
+        match self {
+            AbiType::FixedArray(t, n) if !self.is_dynamic() => t.head_size() * n,
+            AbiType::Tuple(ts) if !self.is_dynamic() => ts.iter().map(|(_, t)| t.head_size()).sum(),
+            _ => 32,
+        }
+    }
+
+    /// Value types fit in one word, an indexed parameter of any other type is only its hash
+    fn is_value_type(&self) -> bool {
+        !matches!(self, AbiType::Bytes | AbiType::String | AbiType::Array(_) | AbiType::FixedArray(..) | AbiType::Tuple(_))
+    }
+
+    /// Column type of a scalar (non array, non tuple) value
+    fn scalar_dtype(&self) -> DataType {
+        match self {
+            AbiType::Uint(n) if *n <= 32 => DataType::UInt32,
+            AbiType::Uint(n) if *n <= 64 => DataType::UInt64,
+            AbiType::Int(n) if *n <= 32 => DataType::Int32,
+            AbiType::Int(n) if *n <= 64 => DataType::Int64,
+            AbiType::Bool => DataType::Boolean,
+            _ => DataType::Utf8,
+        }
+    }
+
+    /// Column type of a non tuple value
+    fn dtype(&self) -> DataType {
+        match self {
+            AbiType::Array(t) | AbiType::FixedArray(t, _) => match t.as_ref() {
+                AbiType::Array(_) | AbiType::FixedArray(..) | AbiType::Tuple(_) => DataType::List(Box::new(DataType::Utf8)),
+                t => DataType::List(Box::new(t.scalar_dtype())),
+            },
+            t => t.scalar_dtype(),
+        }
+    }
+}
+
+/// A decoded abi value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiValue {
+    Uint(BigUint),
+    Int(BigInt),
+    Address(String),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<AbiValue>),
+    Tuple(Vec<AbiValue>),
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", digits)
+}
+
+impl AbiValue {
+    /// Text form, used for Utf8 columns
+    pub fn render(&self) -> String {
+        match self {
+            AbiValue::Uint(x) => x.to_string(),
+            AbiValue::Int(x) => x.to_string(),
+            AbiValue::Address(x) | AbiValue::String(x) => x.clone(),
+            AbiValue::Bool(x) => x.to_string(),
+            AbiValue::Bytes(x) => to_hex(x),
+            AbiValue::Array(_) | AbiValue::Tuple(_) => self.to_json().to_string(),
+        }
+    }
+
+    /// json form, integers as decimal strings so nothing is rounded
+    pub fn to_json(&self) -> Value {
+        match self {
+            AbiValue::Bool(x) => Value::Bool(*x),
+            AbiValue::Array(xs) | AbiValue::Tuple(xs) => Value::Array(xs.iter().map(|x| x.to_json()).collect()),
+            x => Value::String(x.render()),
+        }
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        match self {
+            AbiValue::Uint(x) => x.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        match self {
+            AbiValue::Int(x) => x.try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+fn word(data: &[u8], at: usize) -> Result<&[u8], EvmError> {
+    data.get(at..at + 32)
+        .ok_or_else(|| EvmError::Parse(format!("abi|data ends before offset {}|len={}", at, data.len())))
+}
+
+fn word_usize(data: &[u8], at: usize) -> Result<usize, EvmError> {
+    let w = word(data, at)?;
+    if w[..24].iter().any(|b| *b != 0) {
+        return Err(EvmError::Parse(format!("abi|offset or length at {} overflows", at)));
+    }
+    Ok(u64::from_be_bytes(w[24..].try_into().unwrap_or_default()) as usize)
+}
+
+/// Values of types laid out as a tuple starting at data[0]
+fn decode_tuple(types: &[&AbiType], data: &[u8]) -> Result<Vec<AbiValue>, EvmError> {
+    let mut values = Vec::with_capacity(types.len());
+    let mut head = 0;
+
+    for t in types {
+        let value = match t.is_dynamic() {
+            true => {
+                let offset = word_usize(data, head)?;
+                let tail = data.get(offset..)
+                    .ok_or_else(|| EvmError::Parse(format!("abi|offset {} is past the data|len={}", offset, data.len())))?;
+                decode_value(t, tail)?
+            },
+            false => decode_value(t, &data[head.min(data.len())..])?,
+        };
+        values.push(value);
+        head += t.head_size();
+    }
+
+    Ok(values)
+}
+
+/// One value starting at data[0]
+fn decode_value(kind: &AbiType, data: &[u8]) -> Result<AbiValue, EvmError> {
+    match kind {
+        AbiType::Uint(_) => Ok(AbiValue::Uint(BigUint::from_bytes_be(word(data, 0)?))),
+        AbiType::Int(_) => Ok(AbiValue::Int(BigInt::from_signed_bytes_be(word(data, 0)?))),
+        AbiType::Address => Ok(AbiValue::Address(to_hex(&word(data, 0)?[12..]))),
+        AbiType::Bool => Ok(AbiValue::Bool(word(data, 0)?[31] != 0)),
+        AbiType::FixedBytes(n) => Ok(AbiValue::Bytes(word(data, 0)?[..*n].to_vec())),
+        AbiType::Function => Ok(AbiValue::Bytes(word(data, 0)?[..24].to_vec())),
+        AbiType::Bytes | AbiType::String => {
+            let len = word_usize(data, 0)?;
+            let bytes = data.get(32..32 + len)
+                .ok_or_else(|| EvmError::Parse(format!("abi|{} bytes past the data|len={}", len, data.len())))?;
+            match kind {
+                AbiType::String => Ok(AbiValue::String(String::from_utf8_lossy(bytes).into_owned())),
+                _ => Ok(AbiValue::Bytes(bytes.to_vec())),
+            }
+        },
+        AbiType::Array(t) => {
+            let len = word_usize(data, 0)?;
+            // every element needs at least a word, a bigger length is garbage
+            if len > data.len() / 32 {
+                return Err(EvmError::Parse(format!("abi|array length {} past the data|len={}", len, data.len())));
+            }
+            decode_tuple(&vec![t.as_ref(); len], &data[32..]).map(AbiValue::Array)
+        },
+        AbiType::FixedArray(t, n) => decode_tuple(&vec![t.as_ref(); *n], data).map(AbiValue::Array),
+        AbiType::Tuple(ts) => decode_tuple(&ts.iter().map(|(_, t)| t).collect::<Vec<_>>(), data).map(AbiValue::Tuple),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventParam {
+    pub name: String,
+    pub kind: AbiType,
+    pub indexed: bool,
+}
+
+/// An ABI event, topic0 is keccak256 of its canonical signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub params: Vec<EventParam>,
+    pub signature: String,
+    pub topic0: String,
+}
+
+/// Column names flattened out of a parameter, tuples get one column per component
+fn flatten_columns(prefix: &str, kind: &AbiType, indexed: bool, out: &mut Vec<(String, DataType)>) {
+    match kind {
+        AbiType::Tuple(ts) if !indexed => {
+            for (i, (name, t)) in ts.iter().enumerate() {
+                let name = match name.is_empty() { true => format!("{}", i), false => name.clone() };
+                flatten_columns(&format!("{}_{}", prefix, name), t, false, out);
+            }
+        },
+        t if indexed && !t.is_value_type() => out.push((prefix.to_string(), DataType::Utf8)),
+        t => out.push((prefix.to_string(), t.dtype())),
+    }
+}
+
+/// Values matching flatten_columns, one per column
+fn flatten_values(value: AbiValue, out: &mut Vec<AbiValue>) {
+    match value {
+        AbiValue::Tuple(xs) => xs.into_iter().for_each(|x| flatten_values(x, out)),
+        x => out.push(x),
+    }
+}
+
+impl Event {
+    pub fn from_entry(entry: &AbiEntry) -> Result<Self, EvmError> {
+        if entry.anonymous {
+            return Err(EvmError::Invalid(format!("Event|{} is anonymous, it has no topic0 to filter on", entry.name)));
+        }
+
+        let params = entry.inputs.iter()
+            .map(|x| Ok(EventParam { name: x.name.clone(), kind: AbiType::parse(&x.kind, &x.components)?, indexed: x.indexed }))
+            .collect::<Result<Vec<_>, EvmError>>()?;
+        if params.iter().filter(|x| x.indexed).count() > 3 {
+            return Err(EvmError::Invalid(format!("Event|{} has more than 3 indexed parameters", entry.name)));
+        }
+
+        let signature = format!("{}({})", entry.name, params.iter().map(|x| x.kind.canonical()).collect::<Vec<_>>().join(","));
+        let topic0 = keccak256_hex(&signature);
+        Ok(Self { name: entry.name.clone(), params, signature, topic0 })
+    }
+
+    fn param_name(&self, i: usize) -> String {
+        match self.params[i].name.is_empty() {
+            true => format!("{}_arg{}", self.name, i),
+            false => format!("{}_{}", self.name, self.params[i].name),
+        }
+    }
+
+    /// Parameter columns, names and types in order
+    pub fn columns(&self) -> Vec<(String, DataType)> {
+        let mut columns = Vec::new();
+        for (i, param) in self.params.iter().enumerate() {
+            flatten_columns(&self.param_name(i), &param.kind, param.indexed, &mut columns);
+        }
+        columns
+    }
+
+    /// Parameter values in declaration order, indexed ones from topics[1..], the rest from data
+    pub fn decode(&self, log: &Log) -> Result<Vec<AbiValue>, EvmError> {
+        let indexed = self.params.iter().filter(|x| x.indexed).count();
+        if log.topics.len() != indexed + 1 {
+            return Err(EvmError::Parse(format!("Event|{}|expected {} topics, log has {}", self.signature, indexed + 1, log.topics.len())));
+        }
+
+        let data = hex_bytes(&log.data)?;
+        let unindexed: Vec<&AbiType> = self.params.iter().filter(|x| !x.indexed).map(|x| &x.kind).collect();
+        let mut from_data = decode_tuple(&unindexed, &data)?.into_iter();
+        let mut topics = log.topics[1..].iter();
+
+        self.params.iter()
+            .map(|param| match param.indexed {
+                true => {
+                    let topic = hex_bytes(topics.next().map_or("", |x| x.as_str()))?;
+                    match param.kind.is_value_type() {
+                        true => decode_value(&param.kind, &topic),
+                        false => Ok(AbiValue::Bytes(topic)),
+                    }
+                },
+                false => from_data.next()
+                    .ok_or_else(|| EvmError::Parse(format!("Event|{}|missing data value", self.signature))),
+            })
+            .collect()
+    }
+
+    /// decode, flattened to one value per column
+    fn decode_flat(&self, log: &Log) -> Result<Vec<AbiValue>, EvmError> {
+        let mut values = Vec::new();
+        for value in self.decode(log)? {
+            flatten_values(value, &mut values);
+        }
+        Ok(values)
+    }
+}
+
+/// The events a landing step decodes, selected by name from an ABI
+#[derive(Debug, Clone)]
+pub struct EventSet {
+    events: Vec<Event>,
+}
+
+/// Log columns every event frame starts with
+const LOG_COLUMNS: [(&str, DataType); 6] = [
+    ("block_number", DataType::UInt64),
+    ("block_timestamp", DataType::UInt64),
+    ("transaction_hash", DataType::Utf8),
+    ("log_index", DataType::UInt64),
+    ("contract_address", DataType::Utf8),
+    ("event", DataType::Utf8),
+];
+
+impl EventSet {
+    /// Events out of a json ABI (an array of entries, or an object with an abi array as build tools write it)
+    /// names picks events by name or full signature, empty selects every non-anonymous event
+    pub fn from_abi(abi: &str, names: &[&str]) -> Result<Self, EvmError> {
+        let value: Value = serde_json::from_str(abi)?;
+        let entries: Vec<AbiEntry> = match value {
+            Value::Object(mut x) => serde_json::from_value(x.remove("abi").unwrap_or(Value::Null))?,
+            x => serde_json::from_value(x)?,
+        };
+
+        let entries: Vec<&AbiEntry> = entries.iter().filter(|x| x.kind == "event").collect();
+        let mut events = Vec::new();
+        match names.is_empty() {
+            true => {
+                for entry in entries.iter().filter(|x| !x.anonymous) {
+                    events.push(Event::from_entry(entry)?);
+                }
+            },
+            false => {
+                let all = entries.iter().filter(|x| !x.anonymous).map(|x| Event::from_entry(x)).collect::<Result<Vec<_>, EvmError>>()?;
+                for name in names {
+                    let name = name.trim();
+                    let found: Vec<&Event> = all.iter().filter(|x| x.name == name || x.signature == name).collect();
+                    if found.is_empty() {
+                        let known: Vec<&str> = all.iter().map(|x| x.signature.as_str()).collect();
+                        return Err(EvmError::Invalid(format!("EventSet|event {} is not in the abi|expected one of {}", name, known.join(", "))));
+                    }
+                    events.extend(found.into_iter().cloned());
+                }
+            },
+        }
+
+        // an event picked by name and by signature is decoded once
+        let mut topics = HashSet::new();
+        events.retain(|x| topics.insert(x.topic0.clone()));
+        if events.is_empty() {
+            return Err(EvmError::Invalid("EventSet|abi has no events to decode".into()));
+        }
+
+        // overloads share a name, their columns would collide
+        let mut seen: HashMap<&str, &str> = HashMap::new();
+        for event in &events {
+            if let Some(other) = seen.insert(&event.name, &event.signature) {
+                return Err(EvmError::Invalid(format!("EventSet|{} and {} share a name, select one by signature", other, event.signature)));
+            }
+        }
+
+        // {event}_{param} can still land on a log column or another event's column, e.g. event block(uint256 number)
+        let mut owners: HashMap<String, &str> = LOG_COLUMNS.iter().map(|(n, _)| (n.to_string(), "log columns")).collect();
+        for event in &events {
+            for (column, _) in event.columns() {
+                if let Some(other) = owners.insert(column.clone(), &event.signature) {
+                    return Err(EvmError::Invalid(format!("EventSet|column {} of {} collides with {}, select the events to land with --events", column, event.signature, other)));
+                }
+            }
+        }
+
+        Ok(Self { events })
+    }
+
+    /// from_abi over a file
+    pub fn load(path: &str, names: &[&str]) -> Result<Self, EvmError> {
+        let abi = fs::read_to_string(path)
+            .map_err(|e| EvmError::Invalid(format!("EventSet|unable to read abi {}|e={}", path, e)))?;
+        Self::from_abi(&abi, names)
+    }
+
+    /// From configuration.yaml, evm_abi is the ABI path and evm_events a comma separated list of names
+    /// None when evm_abi is not set
+    pub fn from_config(config: &std::collections::BTreeMap<String, String>) -> Result<Option<Self>, EvmError> {
+        let path = match config.get("evm_abi") {
+            Some(x) if !x.is_empty() => x,
+            _ => return Ok(None),
+        };
+        let names: Vec<&str> = config.get("evm_events")
+            .map(|x| x.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()).collect())
+            .unwrap_or_default();
+
+        Self::load(path, &names).map(Some)
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// topic0 values to filter eth_getLogs on
+    pub fn topics(&self) -> Vec<&str> {
+        self.events.iter().map(|x| x.topic0.as_str()).collect()
+    }
+
+    /// Every column of the frame, log columns then each event's parameters
+    pub fn columns(&self) -> Vec<(String, DataType)> {
+        let mut columns: Vec<(String, DataType)> = LOG_COLUMNS.iter().map(|(n, t)| (n.to_string(), t.clone())).collect();
+        for event in &self.events {
+            columns.extend(event.columns());
+        }
+        columns
+    }
+
+    pub fn schema(&self) -> Schema {
+        Schema::from(self.columns().into_iter().map(|(n, t)| polars::prelude::Field::new(&n, t)).collect::<Vec<_>>())
+    }
+
+    /// Decode every log of a selected event into a frame, other logs and reorged ones are skipped
+    /// block_timestamp is null for blocks missing from timestamps
+    pub fn to_df(&self, logs: &[Log], timestamps: &HashMap<u64, u64>) -> Result<DataFrame, EvmError> {
+        let by_topic: HashMap<&str, usize> = self.events.iter().enumerate().map(|(i, x)| (x.topic0.as_str(), i)).collect();
+        let columns = self.columns();
+
+        // column offset of each event's first parameter
+        let mut offsets = Vec::with_capacity(self.events.len());
+        let mut at = LOG_COLUMNS.len();
+        for event in &self.events {
+            offsets.push(at);
+            at += event.columns().len();
+        }
+
+        let mut rows: Vec<Vec<Option<AbiValue>>> = Vec::new();
+        for log in logs.iter().filter(|x| !x.removed) {
+            let topic0 = log.topics.first().map(|x| x.to_lowercase()).unwrap_or_default();
+            let i = match by_topic.get(topic0.as_str()) {
+                Some(i) => *i,
+                None => continue,
+            };
+
+            let event = &self.events[i];
+            let block_number = log.block_number()?;
+            let mut row: Vec<Option<AbiValue>> = vec![None; columns.len()];
+            row[0] = Some(AbiValue::Uint(block_number.into()));
+            row[1] = timestamps.get(&block_number).map(|x| AbiValue::Uint((*x).into()));
+            row[2] = log.transaction_hash.clone().map(AbiValue::String);
+            row[3] = Some(AbiValue::Uint(log.log_index()?.into()));
+            row[4] = Some(AbiValue::String(log.address.to_lowercase()));
+            row[5] = Some(AbiValue::String(event.name.clone()));
+            for (n, value) in event.decode_flat(log)?.into_iter().enumerate() {
+                row[offsets[i] + n] = Some(value);
+            }
+            rows.push(row);
+        }
+
+        let series = columns.iter().enumerate()
+            .map(|(c, (name, dtype))| column(name, dtype, rows.iter().map(|x| x[c].as_ref()).collect()))
+            .collect::<Result<Vec<_>, EvmError>>()?;
+        Ok(DataFrame::new(series)?)
+    }
+}
+
+/// Series of dtype out of decoded values, see the mapping at the top
+fn column(name: &str, dtype: &DataType, values: Vec<Option<&AbiValue>>) -> Result<Series, EvmError> {
+    let series = match dtype {
+        DataType::UInt32 => Series::new(name, values.iter().map(|x| x.and_then(|x| x.to_u64()).map(|x| x as u32)).collect::<Vec<Option<u32>>>()),
+        DataType::UInt64 => Series::new(name, values.iter().map(|x| x.and_then(|x| x.to_u64())).collect::<Vec<Option<u64>>>()),
+        DataType::Int32 => Series::new(name, values.iter().map(|x| x.and_then(|x| x.to_i64()).map(|x| x as i32)).collect::<Vec<Option<i32>>>()),
+        DataType::Int64 => Series::new(name, values.iter().map(|x| x.and_then(|x| x.to_i64())).collect::<Vec<Option<i64>>>()),
+        DataType::Boolean => Series::new(name, values.iter().map(|x| match x { Some(AbiValue::Bool(b)) => Some(*b), _ => None }).collect::<Vec<Option<bool>>>()),
+        DataType::List(inner) => {
+            let capacity = values.iter().map(|x| match x { Some(AbiValue::Array(xs)) => xs.len(), _ => 0 }).sum();
+            let mut builder = get_list_builder(inner, capacity, values.len(), name);
+            for value in &values {
+                match value {
+                    Some(AbiValue::Array(xs)) => builder.append_series(&column("", inner, xs.iter().map(Some).collect())?),
+                    _ => builder.append_null(),
+                }
+            }
+            builder.finish().into_series()
+        },
+        _ => Series::new(name, values.iter().map(|x| x.map(|x| x.render())).collect::<Vec<Option<String>>>()),
+    };
+
+    Ok(series)
+}
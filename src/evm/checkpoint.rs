@@ -0,0 +1,169 @@
+//! Block checkpoints: the high-water mark of an on-chain landing step
+//! Each run lands [last checkpoint + 1, finalized block] and checkpoints the finalized block with its hash,
+//! a stored hash the node no longer agrees with means a reorg replaced the blocks after it
+
+use log::info;
+use serde_json::json;
+
+use std::collections::BTreeMap;
+
+use crate::{to_quantity, Block, EvmError, RpcClient};
+
+/// Blocks on top of a block before it is landed, reorgs deeper than this are rare on mainnet
+pub const DEFAULT_CONFIRMATIONS: u64 = 12;
+
+/// Checkpoints compared against the node when looking for the last canonical one
+pub const REORG_LOOKBACK: usize = 32;
+
+/// A landed block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub number: u64,
+    pub hash: String,
+}
+
+impl Checkpoint {
+    pub fn from_block(block: &Block) -> Result<Self, EvmError> {
+        Ok(Self { number: block.number()?, hash: block.hash.to_lowercase() })
+    }
+}
+
+/// How far a run may land
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointPolicy {
+    /// blocks on top of the last block a run lands
+    pub confirmations: u64,
+    /// first block when there is no checkpoint yet
+    pub start_block: u64,
+    /// blocks per run, None lands everything up to the finalized block
+    pub max_blocks: Option<u64>,
+}
+
+impl Default for CheckpointPolicy {
+    fn default() -> Self {
+        Self { confirmations: DEFAULT_CONFIRMATIONS, start_block: 0, max_blocks: None }
+    }
+}
+
+impl CheckpointPolicy {
+    /// From configuration.yaml, evm_confirmations, evm_start_block and evm_max_blocks_per_run are optional
+    pub fn from_config(config: &BTreeMap<String, String>) -> Result<Self, EvmError> {
+        let number = |key: &str| -> Result<Option<u64>, EvmError> {
+            match config.get(key) {
+                Some(x) => x.parse().map(Some)
+                    .map_err(|_| EvmError::Invalid(format!("CheckpointPolicy|conf [{}] is invalid|value={}", key, x))),
+                None => Ok(None),
+            }
+        };
+
+        let max_blocks = number("evm_max_blocks_per_run")?;
+        if max_blocks == Some(0) {
+            return Err(EvmError::Invalid("CheckpointPolicy|conf [evm_max_blocks_per_run] must be > 0".into()));
+        }
+
+        Ok(Self {
+            confirmations: number("evm_confirmations")?.unwrap_or(DEFAULT_CONFIRMATIONS),
+            start_block: number("evm_start_block")?.unwrap_or(0),
+            max_blocks,
+        })
+    }
+}
+
+/// What the next run should land
+#[derive(Debug, Clone)]
+pub struct RangePlan {
+    /// first block to land
+    pub from: u64,
+    /// last block to land, the next checkpoint, None when nothing is finalized past from
+    pub end: Option<Block>,
+    /// newest checkpoint still on the canonical chain
+    pub canonical: Option<Checkpoint>,
+    /// checkpoints a reorg replaced, newest first, whatever was landed after canonical has to be re-landed
+    pub superseded: Vec<Checkpoint>,
+}
+
+impl RangePlan {
+    pub fn is_reorg(&self) -> bool {
+        !self.superseded.is_empty()
+    }
+
+    /// from..=to, None when there is nothing to land
+    pub fn range(&self) -> Option<(u64, u64)> {
+        let to = self.end.as_ref()?.number().ok()?;
+        Some((self.from, to))
+    }
+}
+
+impl RpcClient {
+    /// Newest block with at least confirmations blocks on top, None while the chain is shorter than that
+    pub async fn finalized_block(&self, confirmations: u64) -> Result<Option<Block>, EvmError> {
+        let latest = self.block_number().await?;
+        match latest.checked_sub(confirmations) {
+            Some(number) => Ok(Some(self.block(number).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Index of the newest checkpoint whose hash the node still agrees with, checkpoints newest first
+    /// None when every checkpoint was replaced
+    /// A node that has no block for a checkpoint is lagging, not reorged, that is a retryable error
+    pub async fn canonical_checkpoint(&self, checkpoints: &[Checkpoint]) -> Result<Option<usize>, EvmError> {
+        if checkpoints.is_empty() {
+            return Ok(None);
+        }
+
+        let head = self.block_number().await?;
+        if checkpoints[0].number > head {
+            return Err(EvmError::Request(format!("canonical_checkpoint|node head {} is behind checkpoint block {}", head, checkpoints[0].number)));
+        }
+
+        let params = checkpoints.iter().map(|x| json!([to_quantity(x.number), false])).collect();
+        let blocks: Vec<Option<Block>> = self.call_batch("eth_getBlockByNumber", params).await?;
+
+        for (i, (checkpoint, block)) in checkpoints.iter().zip(blocks).enumerate() {
+            match block {
+                Some(x) if x.hash.eq_ignore_ascii_case(&checkpoint.hash) => return Ok(Some(i)),
+                Some(_) => {},
+                None => return Err(EvmError::Request(format!("canonical_checkpoint|node has no block {}|head={}", checkpoint.number, head))),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Plan the next run from stored checkpoints, newest first
+    /// Lands after the newest canonical checkpoint, or from policy.start_block when there is none
+    /// Checkpoints without a canonical one are an error, a reorg that deep or a node on another chain
+    /// needs a look before any history is dropped
+    pub async fn plan_range(&self, checkpoints: &[Checkpoint], policy: &CheckpointPolicy) -> Result<RangePlan, EvmError> {
+        let (canonical, superseded) = match self.canonical_checkpoint(checkpoints).await? {
+            Some(i) => (Some(checkpoints[i].clone()), checkpoints[..i].to_vec()),
+            None if checkpoints.is_empty() => (None, Vec::new()),
+            None => return Err(EvmError::Invalid(format!("plan_range|no canonical checkpoint in blocks {}..={}, the node disagrees with all {} stored hashes|refusing to drop history",
+                checkpoints[checkpoints.len() - 1].number, checkpoints[0].number, checkpoints.len()))),
+        };
+
+        if !superseded.is_empty() {
+            info!("plan_range|reorg|superseded={}|newest={}|canonical={:?}",
+                superseded.len(), superseded[0].number, canonical.as_ref().map(|x| x.number));
+        }
+
+        let from = canonical.as_ref().map_or(policy.start_block, |x| x.number + 1);
+        let finalized = match self.finalized_block(policy.confirmations).await? {
+            Some(block) => block.number()?,
+            None => return Ok(RangePlan { from, end: None, canonical, superseded }),
+        };
+
+        let to = match policy.max_blocks {
+            Some(n) => finalized.min(from.saturating_add(n.max(1) - 1)),
+            None => finalized,
+        };
+
+        let end = match to >= from {
+            true => Some(self.block(to).await?),
+            false => None,
+        };
+        info!("plan_range|from={}|to={}|finalized={}|landing={}", from, to, finalized, end.is_some());
+
+        Ok(RangePlan { from, end, canonical, superseded })
+    }
+}
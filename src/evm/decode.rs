@@ -0,0 +1,181 @@
+//! NFT transfer events: ERC-721 Transfer, ERC-1155 TransferSingle / TransferBatch
+//! One row per token moved, a TransferBatch log fans out to one row per id
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+use polars::prelude::NamedFrom;
+use polars::series::Series;
+use polars::frame::DataFrame;
+
+use crate::error::EvmError;
+use crate::models::{hex_bytes, Log};
+
+/// keccak256("Transfer(address,address,uint256)"), shared with ERC-20
+pub const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+/// keccak256("TransferSingle(address,address,address,uint256,uint256)")
+pub const TRANSFER_SINGLE_TOPIC: &str = "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+/// keccak256("TransferBatch(address,address,address,uint256[],uint256[])")
+pub const TRANSFER_BATCH_TOPIC: &str = "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+
+/// topic0 values an NFT transfer filter asks for
+pub const NFT_TRANSFER_TOPICS: [&str; 3] = [TRANSFER_TOPIC, TRANSFER_SINGLE_TOPIC, TRANSFER_BATCH_TOPIC];
+
+/// One token moved by one log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub log_index: u64,
+    /// position inside a TransferBatch, 0 otherwise
+    pub batch_index: u32,
+    pub contract_address: String,
+    /// erc721 or erc1155
+    pub standard: &'static str,
+    pub event: &'static str,
+    /// erc1155 only
+    pub operator: Option<String>,
+    pub from_address: String,
+    pub to_address: String,
+    /// uint256 as a decimal string
+    pub token_id: String,
+    pub amount: String,
+}
+
+/// address held in the low 20 bytes of an indexed topic
+fn topic_address(topic: &str) -> Result<String, EvmError> {
+    let digits = topic.strip_prefix("0x").unwrap_or(topic);
+    match digits.len() {
+        64 => Ok(format!("0x{}", digits[24..].to_lowercase())),
+        n => Err(EvmError::Parse(format!("topic_address|topic is {} hex digits, expected 64", n))),
+    }
+}
+
+fn uint(bytes: &[u8]) -> String {
+    BigUint::from_bytes_be(bytes).to_string()
+}
+
+/// 32 byte abi word at index i
+fn word(data: &[u8], i: usize) -> Result<&[u8], EvmError> {
+    data.get(i * 32..(i + 1) * 32)
+        .ok_or_else(|| EvmError::Parse(format!("word|data has no word {}|len={}", i, data.len())))
+}
+
+/// abi word that has to fit a usize, offsets and lengths
+fn word_usize(data: &[u8], i: usize) -> Result<usize, EvmError> {
+    let w = word(data, i)?;
+    if w[..24].iter().any(|b| *b != 0) {
+        return Err(EvmError::Parse(format!("word_usize|word {} overflows", i)));
+    }
+    Ok(u64::from_be_bytes(w[24..].try_into().unwrap_or_default()) as usize)
+}
+
+/// dynamic uint256[] whose offset sits in word i
+fn uint_array(data: &[u8], i: usize) -> Result<Vec<String>, EvmError> {
+    let offset = word_usize(data, i)?;
+    if !offset.is_multiple_of(32) {
+        return Err(EvmError::Parse(format!("uint_array|offset {} is not word aligned", offset)));
+    }
+
+    let start = offset / 32;
+    let len = word_usize(data, start)?;
+    (0..len).map(|n| word(data, start + 1 + n).map(uint)).collect()
+}
+
+/// Decode one log, empty for logs that are not NFT transfers (ERC-20 Transfer included)
+/// Logs a reorg removed are dropped
+pub fn decode_transfers(log: &Log) -> Result<Vec<Transfer>, EvmError> {
+    if log.removed {
+        return Ok(vec![]);
+    }
+
+    let topic0 = log.topics.first().map(|x| x.to_lowercase()).unwrap_or_default();
+    let row = |batch_index: u32, standard: &'static str, event: &'static str| -> Result<Transfer, EvmError> {
+        Ok(Transfer {
+            block_number: log.block_number()?,
+            transaction_hash: log.transaction_hash.clone().unwrap_or_default(),
+            log_index: log.log_index()?,
+            batch_index,
+            contract_address: log.address.to_lowercase(),
+            standard,
+            event,
+            operator: None,
+            from_address: String::new(),
+            to_address: String::new(),
+            token_id: String::new(),
+            amount: String::new(),
+        })
+    };
+
+    match (topic0.as_str(), log.topics.len()) {
+        // erc721 indexes the token id, erc20 keeps the amount in data with 3 topics
+        (TRANSFER_TOPIC, 4) => {
+            let token = hex_bytes(&log.topics[3])?;
+            Ok(vec![Transfer {
+                from_address: topic_address(&log.topics[1])?,
+                to_address: topic_address(&log.topics[2])?,
+                token_id: uint(&token),
+                amount: String::from("1"),
+                ..row(0, "erc721", "Transfer")?
+            }])
+        },
+        (TRANSFER_SINGLE_TOPIC, 4) => {
+            let data = hex_bytes(&log.data)?;
+            Ok(vec![Transfer {
+                operator: Some(topic_address(&log.topics[1])?),
+                from_address: topic_address(&log.topics[2])?,
+                to_address: topic_address(&log.topics[3])?,
+                token_id: uint(word(&data, 0)?),
+                amount: uint(word(&data, 1)?),
+                ..row(0, "erc1155", "TransferSingle")?
+            }])
+        },
+        (TRANSFER_BATCH_TOPIC, 4) => {
+            let data = hex_bytes(&log.data)?;
+            let ids = uint_array(&data, 0)?;
+            let values = uint_array(&data, 1)?;
+            if ids.len() != values.len() {
+                return Err(EvmError::Parse(format!("decode_transfers|TransferBatch ids={} values={}", ids.len(), values.len())));
+            }
+
+            let operator = topic_address(&log.topics[1])?;
+            let from_address = topic_address(&log.topics[2])?;
+            let to_address = topic_address(&log.topics[3])?;
+            ids.into_iter().zip(values).enumerate()
+                .map(|(n, (token_id, amount))| Ok(Transfer {
+                    operator: Some(operator.clone()),
+                    from_address: from_address.clone(),
+                    to_address: to_address.clone(),
+                    token_id,
+                    amount,
+                    ..row(n as u32, "erc1155", "TransferBatch")?
+                }))
+                .collect()
+        },
+        _ => Ok(vec![]),
+    }
+}
+
+/// Utility method to flatten transfers into a DataFrame
+/// block_timestamp (epoch seconds) is null for blocks missing from timestamps
+/// cols: block_number, block_timestamp, transaction_hash, log_index, batch_index,
+/// contract_address, standard, event, operator, from_address, to_address, token_id, amount
+pub fn transfers_to_df(transfers: &[Transfer], timestamps: &HashMap<u64, u64>) -> Result<DataFrame, EvmError> {
+    let df = DataFrame::new(vec![
+        Series::new("block_number", transfers.iter().map(|x| x.block_number).collect::<Vec<u64>>()),
+        Series::new("block_timestamp", transfers.iter().map(|x| timestamps.get(&x.block_number).copied()).collect::<Vec<Option<u64>>>()),
+        Series::new("transaction_hash", transfers.iter().map(|x| x.transaction_hash.as_str()).collect::<Vec<&str>>()),
+        Series::new("log_index", transfers.iter().map(|x| x.log_index).collect::<Vec<u64>>()),
+        Series::new("batch_index", transfers.iter().map(|x| x.batch_index).collect::<Vec<u32>>()),
+        Series::new("contract_address", transfers.iter().map(|x| x.contract_address.as_str()).collect::<Vec<&str>>()),
+        Series::new("standard", transfers.iter().map(|x| x.standard).collect::<Vec<&str>>()),
+        Series::new("event", transfers.iter().map(|x| x.event).collect::<Vec<&str>>()),
+        Series::new("operator", transfers.iter().map(|x| x.operator.as_deref()).collect::<Vec<Option<&str>>>()),
+        Series::new("from_address", transfers.iter().map(|x| x.from_address.as_str()).collect::<Vec<&str>>()),
+        Series::new("to_address", transfers.iter().map(|x| x.to_address.as_str()).collect::<Vec<&str>>()),
+        Series::new("token_id", transfers.iter().map(|x| x.token_id.as_str()).collect::<Vec<&str>>()),
+        Series::new("amount", transfers.iter().map(|x| x.amount.as_str()).collect::<Vec<&str>>()),
+    ])?;
+
+    Ok(df)
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+/// json-rpc error codes nodes use when a getLogs range is too wide or returns too much
+const LIMIT_EXCEEDED: i64 = -32005;
+const LIMIT_HINTS: &[&str] = &["more than", "too many", "range", "limit exceeded", "response size"];
+
+#[derive(Debug)]
+pub enum EvmError {
+    /// error object returned by the node
+    Rpc { code: i64, message: String },
+    /// any non-200 status
+    Http(u16),
+    /// result could not be deserialized or decoded
+    Parse(String),
+    /// connection, timeout or client build failures, or a node lagging behind the checkpoints
+    Request(String),
+    /// bad arguments or configuration, nothing was sent
+    Invalid(String),
+}
+
+impl EvmError {
+    /// The node refused a getLogs call for its size, a narrower range may succeed
+    pub fn is_too_large(&self) -> bool {
+        match self {
+            EvmError::Rpc { code, message } => {
+                let message = message.to_lowercase();
+                *code == LIMIT_EXCEEDED || LIMIT_HINTS.iter().any(|x| message.contains(x))
+            },
+            _ => false,
+        }
+    }
+
+    /// Transient failures, the same request may succeed later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            EvmError::Request(_) => true,
+            EvmError::Http(s) => *s == 429 || *s >= 500,
+            _ => false,
+        }
+    }
+
+    /// job_step status a landing step should record for this error
+    /// S re-queues the step, F fails it
+    pub fn step_status(&self) -> &'static str {
+        match self.is_retryable() {
+            true => "S",
+            false => "F",
+        }
+    }
+}
+
+impl fmt::Display for EvmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvmError::Rpc { code, message } => write!(f, "ERR: rpc error|code={}|{}", code, message),
+            EvmError::Http(s) => write!(f, "ERR: unexpected status|status={}", s),
+            EvmError::Parse(x) => write!(f, "ERR: unable to parse result|{}", x),
+            EvmError::Request(x) => write!(f, "ERR: request failed|{}", x),
+            EvmError::Invalid(x) => write!(f, "ERR: invalid input|{}", x),
+        }
+    }
+}
+
+impl std::error::Error for EvmError {}
+
+impl From<reqwest::Error> for EvmError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(s) => EvmError::Http(s.as_u16()),
+            None => EvmError::Request(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for EvmError {
+    fn from(e: serde_json::Error) -> Self {
+        EvmError::Parse(e.to_string())
+    }
+}
+
+impl From<polars::error::PolarsError> for EvmError {
+    fn from(e: polars::error::PolarsError) -> Self {
+        EvmError::Parse(e.to_string())
+    }
+}
@@ -0,0 +1,322 @@
+use log::info;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use polars::frame::DataFrame;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod abi;
+pub mod checkpoint;
+pub mod decode;
+pub mod error;
+pub mod keccak;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod models;
+
+pub use abi::{AbiType, AbiValue, Event, EventSet};
+pub use checkpoint::{Checkpoint, CheckpointPolicy, RangePlan, DEFAULT_CONFIRMATIONS, REORG_LOOKBACK};
+pub use decode::{decode_transfers, transfers_to_df, Transfer, NFT_TRANSFER_TOPICS, TRANSFER_BATCH_TOPIC, TRANSFER_SINGLE_TOPIC, TRANSFER_TOPIC};
+pub use error::EvmError;
+pub use keccak::{keccak256, keccak256_hex};
+pub use models::{check_address, hex_bytes, quantity, to_quantity, Block, Log};
+
+use models::RpcResponse;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Blocks per eth_getLogs call, most hosted nodes cap a filter somewhere between 1k and 10k
+pub const DEFAULT_MAX_BLOCK_RANGE: u64 = 2000;
+
+/// Calls per json-rpc batch request
+pub const RPC_BATCH_SIZE: usize = 100;
+
+pub const MAX_RETRIES: u32 = 3;
+pub const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+fn backoff(attempt: u32) -> Duration {
+    BASE_BACKOFF.saturating_mul(2u32.saturating_pow(attempt))
+}
+
+/// Ethereum json-rpc client, eth_getLogs and the block lookups around it
+/// Works against any node: a hosted endpoint, a local dev node (anvil, hardhat) or mock::MockRpcServer
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    http: reqwest::Client,
+    url: String,
+    next_id: Arc<AtomicU64>,
+    max_block_range: u64,
+}
+
+impl RpcClient {
+    pub fn new(url: &str, timeout: Duration) -> Result<Self, EvmError> {
+        if url.is_empty() {
+            return Err(EvmError::Invalid("RpcClient|rpc url is not valid".into()));
+        }
+
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()?;
+
+        Ok(Self {
+            http,
+            url: url.to_string(),
+            next_id: Arc::new(AtomicU64::new(1)),
+            max_block_range: DEFAULT_MAX_BLOCK_RANGE,
+        })
+    }
+
+    /// Build a client from configuration.yaml
+    /// evm_rpc_url is required, evm_timeout_secs and evm_max_block_range are optional
+    pub fn from_config(config: &BTreeMap<String, String>) -> Result<Self, EvmError> {
+        let url = config.get("evm_rpc_url")
+            .ok_or_else(|| EvmError::Invalid("RpcClient|conf [evm_rpc_url] is missing".into()))?;
+        let timeout = match config.get("evm_timeout_secs") {
+            Some(x) => Duration::from_secs(x.parse().map_err(|_| EvmError::Invalid(format!("RpcClient|conf [evm_timeout_secs] is invalid|value={}", x)))?),
+            None => DEFAULT_TIMEOUT,
+        };
+        let max_block_range = match config.get("evm_max_block_range") {
+            Some(x) => x.parse().ok().filter(|n| *n > 0)
+                .ok_or_else(|| EvmError::Invalid(format!("RpcClient|conf [evm_max_block_range] is invalid|value={}", x)))?,
+            None => DEFAULT_MAX_BLOCK_RANGE,
+        };
+
+        Ok(Self::new(url, timeout)?.with_max_block_range(max_block_range))
+    }
+
+    pub fn with_max_block_range(mut self, max_block_range: u64) -> Self {
+        self.max_block_range = max_block_range.max(1);
+        self
+    }
+
+    pub fn max_block_range(&self) -> u64 {
+        self.max_block_range
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: &str, params: Value) -> Value {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+    }
+
+    /// POST a json-rpc body, retrying connection failures, 429 and 5xx up to MAX_RETRIES
+    async fn post(&self, body: &Value) -> Result<String, EvmError> {
+        let mut attempt = 0;
+
+        loop {
+            let result = match self.http.post(&self.url).json(body).send().await {
+                Ok(response) if response.status().is_success() => Ok(response.text().await?),
+                Ok(response) => Err(EvmError::Http(response.status().as_u16())),
+                Err(e) => Err(EvmError::from(e)),
+            };
+
+            match result {
+                Err(err) if err.is_retryable() && attempt < MAX_RETRIES => {
+                    let wait = backoff(attempt);
+                    attempt += 1;
+                    info!("post|{}|retry {}/{} in {:?}", err, attempt, MAX_RETRIES, wait);
+                    tokio::time::sleep(wait).await;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Single json-rpc call, an error object comes back as EvmError::Rpc
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, EvmError> {
+        let body = self.post(&self.request(method, params)).await?;
+        let response: RpcResponse<Value> = serde_json::from_str(&body)?;
+        unwrap_response(method, response)
+    }
+
+    /// The same method over many params, sent as json-rpc batches of RPC_BATCH_SIZE
+    /// Results come back in params order whatever order the node answers in
+    pub async fn call_batch<T: DeserializeOwned>(&self, method: &str, params: Vec<Value>) -> Result<Vec<T>, EvmError> {
+        let mut results = Vec::with_capacity(params.len());
+
+        for chunk in params.chunks(RPC_BATCH_SIZE) {
+            let requests: Vec<Value> = chunk.iter().map(|x| self.request(method, x.clone())).collect();
+            let ids: Vec<u64> = requests.iter().filter_map(|x| x["id"].as_u64()).collect();
+
+            let body = self.post(&Value::Array(requests)).await?;
+            let mut responses: HashMap<u64, RpcResponse<Value>> = serde_json::from_str::<Vec<RpcResponse<Value>>>(&body)?
+                .into_iter()
+                .filter_map(|x| x.id.map(|id| (id, x)))
+                .collect();
+
+            for id in ids {
+                let response = responses.remove(&id)
+                    .ok_or_else(|| EvmError::Parse(format!("call_batch|{}|no response for id {}", method, id)))?;
+                results.push(unwrap_response(method, response)?);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// eth_blockNumber
+    pub async fn block_number(&self) -> Result<u64, EvmError> {
+        quantity(&self.call::<String>("eth_blockNumber", json!([])).await?)
+    }
+
+    /// eth_getBlockByNumber without transactions
+    pub async fn block(&self, number: u64) -> Result<Block, EvmError> {
+        self.call::<Option<Block>>("eth_getBlockByNumber", json!([to_quantity(number), false])).await?
+            .ok_or_else(|| EvmError::Invalid(format!("block|block not found|number={}", number)))
+    }
+
+    /// Headers for every number, batched
+    pub async fn blocks(&self, numbers: &[u64]) -> Result<Vec<Block>, EvmError> {
+        let params = numbers.iter().map(|x| json!([to_quantity(*x), false])).collect();
+        let blocks: Vec<Option<Block>> = self.call_batch("eth_getBlockByNumber", params).await?;
+
+        numbers.iter().zip(blocks)
+            .map(|(n, block)| block.ok_or_else(|| EvmError::Invalid(format!("blocks|block not found|number={}", n))))
+            .collect()
+    }
+
+    /// Block timestamps (epoch seconds) keyed by number, duplicates are fetched once
+    pub async fn block_timestamps(&self, numbers: &[u64]) -> Result<HashMap<u64, u64>, EvmError> {
+        let mut numbers = numbers.to_vec();
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        self.blocks(&numbers).await?
+            .iter()
+            .map(|x| Ok((x.number()?, x.timestamp()?)))
+            .collect()
+    }
+
+    /// First block with a timestamp at or after timestamp, binary search over headers
+    /// latest + 1 when the chain has not got there yet
+    pub async fn block_at(&self, timestamp: u64) -> Result<u64, EvmError> {
+        let latest = self.block_number().await?;
+        if self.block(latest).await?.timestamp()? < timestamp {
+            return Ok(latest + 1);
+        }
+
+        let (mut lo, mut hi) = (0, latest);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.block(mid).await?.timestamp()? < timestamp {
+                true => lo = mid + 1,
+                false => hi = mid,
+            }
+        }
+
+        info!("block_at|timestamp={}|block={}", timestamp, lo);
+        Ok(lo)
+    }
+
+    /// eth_getLogs for one contract, topic0 any of topics, blocks from..=to
+    /// A range the node refuses as too large is split in half until it goes through
+    pub async fn get_logs(&self, address: &str, topics: &[&str], from: u64, to: u64) -> Result<Vec<Log>, EvmError> {
+        let address = check_address(address)?;
+        let mut logs = Vec::new();
+        let mut ranges = vec![(from, to)];
+
+        while let Some((from, to)) = ranges.pop() {
+            let filter = json!([{
+                "address": address,
+                "topics": [topics],
+                "fromBlock": to_quantity(from),
+                "toBlock": to_quantity(to),
+            }]);
+
+            match self.call::<Vec<Log>>("eth_getLogs", filter).await {
+                Ok(page) => {
+                    info!("get_logs|address={}|from={}|to={}|logs={}", address, from, to, page.len());
+                    logs.extend(page);
+                },
+                Err(err) if err.is_too_large() && from < to => {
+                    let mid = from + (to - from) / 2;
+                    info!("get_logs|{}|splitting {}..={} at {}", err, from, to, mid);
+                    // popped last, so the lower half lands first
+                    ranges.push((mid + 1, to));
+                    ranges.push((from, mid));
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(logs)
+    }
+
+    /// NFT transfers for one contract over blocks from..=to, a frame per max_block_range chunk
+    /// Chunks without transfers are left out, see decode::transfers_to_df for the columns
+    pub async fn nft_transfers(&self, address: &str, from: u64, to: u64) -> Result<Vec<DataFrame>, EvmError> {
+        let mut frames = Vec::new();
+
+        for (start, end) in block_ranges(from, to, self.max_block_range) {
+            let logs = self.get_logs(address, &NFT_TRANSFER_TOPICS, start, end).await?;
+            if let Some(df) = self.transfers_frame(&logs).await? {
+                frames.push(df);
+            }
+        }
+
+        Ok(frames)
+    }
+
+    /// Decode logs and stamp them with block timestamps, None when nothing decodes to a transfer
+    pub async fn transfers_frame(&self, logs: &[Log]) -> Result<Option<DataFrame>, EvmError> {
+        let mut transfers = Vec::new();
+        for log in logs {
+            transfers.extend(decode_transfers(log)?);
+        }
+        if transfers.is_empty() {
+            return Ok(None);
+        }
+
+        let numbers: Vec<u64> = transfers.iter().map(|x| x.block_number).collect();
+        let timestamps = self.block_timestamps(&numbers).await?;
+        transfers_to_df(&transfers, &timestamps).map(Some)
+    }
+
+    /// Decode logs of the selected events and stamp them with block timestamps, None when none of them match
+    pub async fn events_frame(&self, events: &EventSet, logs: &[Log]) -> Result<Option<DataFrame>, EvmError> {
+        let topics = events.topics();
+        let matched: Vec<Log> = logs.iter()
+            .filter(|x| !x.removed && x.topics.first().is_some_and(|t| topics.iter().any(|e| t.eq_ignore_ascii_case(e))))
+            .cloned()
+            .collect();
+        if matched.is_empty() {
+            return Ok(None);
+        }
+
+        let numbers = matched.iter().map(|x| x.block_number()).collect::<Result<Vec<u64>, EvmError>>()?;
+        let timestamps = self.block_timestamps(&numbers).await?;
+        events.to_df(&matched, &timestamps).map(Some)
+    }
+}
+
+/// from..=to cut into inclusive ranges of at most size blocks
+pub fn block_ranges(from: u64, to: u64, size: u64) -> Vec<(u64, u64)> {
+    let size = size.max(1);
+    let mut ranges = Vec::new();
+    let mut start = from;
+
+    while start <= to {
+        let end = start.saturating_add(size - 1).min(to);
+        ranges.push((start, end));
+        if end == u64::MAX { break; }
+        start = end + 1;
+    }
+
+    ranges
+}
+
+fn unwrap_response<T: DeserializeOwned>(method: &str, response: RpcResponse<Value>) -> Result<T, EvmError> {
+    if let Some(e) = response.error {
+        info!("call|{}|ERR: code={}|{}", method, e.code, e.message);
+        return Err(EvmError::Rpc { code: e.code, message: e.message });
+    }
+
+    serde_json::from_value(response.result.unwrap_or(Value::Null))
+        .map_err(|e| EvmError::Parse(format!("{}|{}", method, e)))
+}
@@ -0,0 +1,88 @@
+//! keccak256 as ethereum uses it (the original keccak padding, not NIST SHA3-256)
+//! Only hashes event signatures, so a plain single-shot implementation is enough
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// rho rotation offsets, lane x + 5y
+const ROTATIONS: [u32; 25] = [
+    0, 1, 62, 28, 27,
+    36, 44, 6, 55, 20,
+    3, 10, 43, 25, 39,
+    41, 45, 15, 21, 8,
+    18, 2, 61, 56, 14,
+];
+
+/// bytes absorbed per permutation for a 256 bit output
+const RATE: usize = 136;
+
+fn keccak_f(state: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS {
+        // theta
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut b = [0u64; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = state[x + 5 * y].rotate_left(ROTATIONS[x + 5 * y]);
+            }
+        }
+
+        // chi
+        for x in 0..5 {
+            for y in 0..5 {
+                state[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        // iota
+        state[0] ^= rc;
+    }
+}
+
+pub fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut state = [0u64; 25];
+
+    let mut padded = input.to_vec();
+    padded.push(0x01);
+    while !padded.len().is_multiple_of(RATE) {
+        padded.push(0);
+    }
+    *padded.last_mut().unwrap_or(&mut 0) |= 0x80;
+
+    for block in padded.chunks(RATE) {
+        for (i, lane) in block.chunks(8).enumerate() {
+            state[i] ^= u64::from_le_bytes(lane.try_into().unwrap_or_default());
+        }
+        keccak_f(&mut state);
+    }
+
+    let mut out = [0u8; 32];
+    for (i, lane) in state.iter().take(4).enumerate() {
+        out[i * 8..(i + 1) * 8].copy_from_slice(&lane.to_le_bytes());
+    }
+    out
+}
+
+/// 0x prefixed keccak256 of text, the topic0 of an event signature
+pub fn keccak256_hex(text: &str) -> String {
+    let hash = keccak256(text.as_bytes());
+    let digits: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", digits)
+}
@@ -0,0 +1,322 @@
+//! Local stand-in for an ethereum json-rpc node, serving a synthetic chain and canned logs
+//! Point an RpcClient at MockRpcServer::url() to run offline
+//! Only built for tests and with the mock feature
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::info;
+use serde_json::{json, Value};
+
+use crate::models::{quantity, to_quantity, Log};
+
+/// Head of the mock chain, blocks 0..=MOCK_LATEST_BLOCK exist
+pub const MOCK_LATEST_BLOCK: u64 = 20_000;
+
+/// Block 0 is mined at 2022-05-22T00:00:00Z, then one block every MOCK_BLOCK_TIME seconds
+pub const MOCK_GENESIS_TS: u64 = 1_653_177_600;
+pub const MOCK_BLOCK_TIME: u64 = 12;
+
+/// eth_getLogs over more blocks than this is refused with -32005, like a hosted node
+pub const MOCK_MAX_RANGE: u64 = 5_000;
+
+/// Contracts with logs in the fixtures
+pub const MOCK_ERC721: &str = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d";
+pub const MOCK_ERC1155: &str = "0x495f947276749ce646f68ac8c248420045cb7b5e";
+pub const MOCK_ERC20: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+/// Sale and Mint events, decoded with MOCK_MARKETPLACE_ABI
+pub const MOCK_MARKETPLACE: &str = "0x00000000006c3852cbef3e08e8df289169ede581";
+
+/// json ABI of MOCK_MARKETPLACE
+pub const MOCK_MARKETPLACE_ABI: &str = include_str!("fixtures/marketplace_abi.json");
+
+const LOGS: &str = include_str!("fixtures/logs.json");
+
+/// Timestamp of a mock block
+pub fn mock_timestamp(number: u64) -> u64 {
+    MOCK_GENESIS_TS + number * MOCK_BLOCK_TIME
+}
+
+/// Hash of a mock block before any reorg, the number in the low bytes
+pub fn mock_block_hash(number: u64) -> String {
+    format!("0x{:064x}", number)
+}
+
+/// A json-rpc call as seen by the mock, a batch request is one entry per call
+#[derive(Debug, Clone)]
+pub struct MockCall {
+    pub method: String,
+    pub params: Value,
+    /// calls sent together in one batch request share a number
+    pub request: u64,
+}
+
+#[derive(Default)]
+struct MockState {
+    calls: Vec<MockCall>,
+    failures: VecDeque<u16>,
+    requests: u64,
+    /// blocks from here on were replaced, fork times
+    reorg_from: Option<u64>,
+    fork: u64,
+}
+
+impl MockState {
+    /// Current hash of a block, the fork number in the high bytes once a reorg replaced it
+    fn block_hash(&self, number: u64) -> String {
+        match self.reorg_from {
+            Some(from) if number >= from => format!("0x{:016x}{:048x}", self.fork, number),
+            _ => mock_block_hash(number),
+        }
+    }
+}
+
+pub struct MockRpcServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl MockRpcServer {
+    /// Bind to an ephemeral port on localhost and serve on a background thread, a thread per connection
+    pub fn start() -> std::io::Result<Self> {
+        Self::bind("127.0.0.1:0")
+    }
+
+    pub fn bind(addr: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let t_state = Arc::clone(&state);
+        let t_shutdown = Arc::clone(&shutdown);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if t_shutdown.load(Ordering::SeqCst) { break; }
+                match stream {
+                    Ok(s) => {
+                        let c_state = Arc::clone(&t_state);
+                        thread::spawn(move || {
+                            if let Err(e) = handle(s, &c_state) {
+                                info!("mock_rpc|ERR: connection failed|e={}", e);
+                            }
+                        });
+                    },
+                    Err(e) => info!("mock_rpc|ERR: accept failed|e={}", e),
+                }
+            }
+        });
+
+        info!("mock_rpc|listening on {}", addr);
+        Ok(Self { addr, state, shutdown })
+    }
+
+    /// Url to hand to RpcClient, evm_rpc_url
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Answer the next n http requests with status instead of a json-rpc response
+    pub fn fail_next(&self, status: u16, n: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.failures.extend(std::iter::repeat_n(status, n));
+    }
+
+    /// Replace every block from from_block on, as a reorg would, their hashes change and logs move with them
+    /// Calling it again forks again, from the lower of the two blocks
+    pub fn reorg(&self, from_block: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.fork += 1;
+        state.reorg_from = Some(state.reorg_from.map_or(from_block, |x| x.min(from_block)));
+    }
+
+    /// Hash of a block as the mock serves it now
+    pub fn block_hash(&self, number: u64) -> String {
+        self.state.lock().unwrap().block_hash(number)
+    }
+
+    /// Every call served so far, in order
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Calls to one method, in order
+    pub fn calls_to(&self, method: &str) -> Vec<MockCall> {
+        self.calls().into_iter().filter(|x| x.method == method).collect()
+    }
+
+    /// Block the calling thread, used by the standalone binary
+    pub fn wait(&self) {
+        while !self.shutdown.load(Ordering::SeqCst) {
+            thread::park();
+        }
+    }
+}
+
+impl Drop for MockRpcServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake the accept loop so the thread can exit
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<MockState>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    if request_line.trim().is_empty() { return Ok(()); }
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 { break; }
+        let line = line.trim_end();
+        if line.is_empty() { break; }
+        if let Some((k, v)) = line.split_once(':') {
+            if k.eq_ignore_ascii_case("content-length") {
+                content_length = v.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (status, body) = {
+        let mut state = state.lock().unwrap();
+        state.requests += 1;
+        let request = state.requests;
+
+        match state.failures.pop_front() {
+            Some(code) => (code, String::from(r#"{"error": "injected failure"}"#)),
+            None => match serde_json::from_slice::<Value>(&body) {
+                Ok(Value::Array(calls)) => {
+                    let replies: Vec<Value> = calls.iter().map(|x| reply(x, request, &mut state)).collect();
+                    (200, Value::Array(replies).to_string())
+                },
+                Ok(call) => (200, reply(&call, request, &mut state).to_string()),
+                Err(e) => (200, rpc_error(Value::Null, -32700, &format!("parse error: {}", e)).to_string()),
+            },
+        }
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body,
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// Answer one call, recorded in the state
+fn reply(call: &Value, request: u64, state: &mut MockState) -> Value {
+    let id = call["id"].clone();
+    let method = call["method"].as_str().unwrap_or_default().to_string();
+    let params = call["params"].clone();
+    info!("mock_rpc|{}|{}", method, params);
+
+    state.calls.push(MockCall { method: method.clone(), params: params.clone(), request });
+
+    let result = match method.as_str() {
+        "eth_chainId" => Ok(json!("0x1")),
+        "eth_blockNumber" => Ok(json!(to_quantity(MOCK_LATEST_BLOCK))),
+        "eth_getBlockByNumber" => get_block(&params, state),
+        "eth_getLogs" => get_logs(&params, state),
+        _ => Err((-32601, format!("the method {} does not exist/is not available", method))),
+    };
+
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => rpc_error(id, code, &message),
+    }
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// "latest" or a hex quantity
+fn block_param(value: &Value) -> Result<u64, (i64, String)> {
+    match value.as_str() {
+        Some("latest") | None => Ok(MOCK_LATEST_BLOCK),
+        Some("earliest") => Ok(0),
+        Some(x) => quantity(x).map_err(|e| (-32602, e.to_string())),
+    }
+}
+
+/// [number, full_transactions], null past the head
+fn get_block(params: &Value, state: &MockState) -> Result<Value, (i64, String)> {
+    let number = block_param(&params[0])?;
+    if number > MOCK_LATEST_BLOCK {
+        return Ok(Value::Null);
+    }
+
+    Ok(json!({
+        "number": to_quantity(number),
+        "hash": state.block_hash(number),
+        "parentHash": state.block_hash(number.saturating_sub(1)),
+        "timestamp": to_quantity(mock_timestamp(number)),
+        "transactions": [],
+    }))
+}
+
+/// [{address, topics, fromBlock, toBlock}], topic0 only
+fn get_logs(params: &Value, state: &MockState) -> Result<Value, (i64, String)> {
+    let filter = &params[0];
+    let from = block_param(&filter["fromBlock"])?;
+    let to = block_param(&filter["toBlock"])?.min(MOCK_LATEST_BLOCK);
+
+    if to >= from && to - from + 1 > MOCK_MAX_RANGE {
+        return Err((-32005, format!("query exceeds max block range {}", MOCK_MAX_RANGE)));
+    }
+
+    let addresses: Vec<String> = match &filter["address"] {
+        Value::String(x) => vec![x.to_lowercase()],
+        Value::Array(x) => x.iter().filter_map(|x| x.as_str()).map(|x| x.to_lowercase()).collect(),
+        _ => vec![],
+    };
+    let topic0: Vec<String> = match &filter["topics"][0] {
+        Value::String(x) => vec![x.to_lowercase()],
+        Value::Array(x) => x.iter().filter_map(|x| x.as_str()).map(|x| x.to_lowercase()).collect(),
+        _ => vec![],
+    };
+
+    let logs: Vec<Log> = serde_json::from_str(LOGS).map_err(|e| (-32603, e.to_string()))?;
+    let matched: Vec<Log> = logs.into_iter()
+        .filter(|x| {
+            let number = x.block_number().unwrap_or_default();
+            number >= from && number <= to
+                && (addresses.is_empty() || addresses.contains(&x.address.to_lowercase()))
+                && (topic0.is_empty() || x.topics.first().is_some_and(|t| topic0.contains(&t.to_lowercase())))
+        })
+        .map(|x| Log { block_hash: Some(state.block_hash(x.block_number().unwrap_or_default())), ..x })
+        .collect();
+
+    serde_json::to_value(matched).map_err(|e| (-32603, e.to_string()))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
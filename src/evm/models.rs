@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::EvmError;
+
+/// eth_getLogs entry, quantities stay hex as the node sends them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+    pub block_number: Option<String>,
+    pub block_hash: Option<String>,
+    pub transaction_hash: Option<String>,
+    pub transaction_index: Option<String>,
+    pub log_index: Option<String>,
+    /// set when a reorg dropped the log
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// eth_getBlockByNumber header fields, without transactions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    pub number: String,
+    pub hash: String,
+    pub parent_hash: String,
+    pub timestamp: String,
+}
+
+impl Block {
+    pub fn number(&self) -> Result<u64, EvmError> {
+        quantity(&self.number)
+    }
+
+    pub fn timestamp(&self) -> Result<u64, EvmError> {
+        quantity(&self.timestamp)
+    }
+}
+
+impl Log {
+    pub fn block_number(&self) -> Result<u64, EvmError> {
+        quantity(self.block_number.as_deref().unwrap_or_default())
+    }
+
+    pub fn log_index(&self) -> Result<u64, EvmError> {
+        quantity(self.log_index.as_deref().unwrap_or_default())
+    }
+}
+
+/// json-rpc response envelope
+#[derive(Debug, Deserialize)]
+pub(crate) struct RpcResponse<T> {
+    pub id: Option<u64>,
+    pub result: Option<T>,
+    pub error: Option<RpcErrorObject>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RpcErrorObject {
+    pub code: i64,
+    pub message: String,
+}
+
+/// Hex quantity, e.g. 0x1b4
+pub fn quantity(value: &str) -> Result<u64, EvmError> {
+    let digits = value.strip_prefix("0x")
+        .ok_or_else(|| EvmError::Parse(format!("quantity|missing 0x prefix|value={:?}", value)))?;
+    u64::from_str_radix(digits, 16)
+        .map_err(|e| EvmError::Parse(format!("quantity|value={:?}|e={}", value, e)))
+}
+
+/// Block number as a hex quantity
+pub fn to_quantity(value: u64) -> String {
+    format!("{:#x}", value)
+}
+
+/// Raw bytes of 0x prefixed hex data
+pub fn hex_bytes(value: &str) -> Result<Vec<u8>, EvmError> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if !digits.len().is_multiple_of(2) {
+        return Err(EvmError::Parse(format!("hex_bytes|odd length|len={}", digits.len())));
+    }
+    // checked up front so the slicing below stays on char boundaries
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(EvmError::Parse(format!("hex_bytes|not hex|value={:?}", value)));
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16)
+            .map_err(|e| EvmError::Parse(format!("hex_bytes|e={}", e))))
+        .collect()
+}
+
+/// 0x prefixed 20 byte address, lowercased
+pub fn check_address(value: &str) -> Result<String, EvmError> {
+    let address = value.trim().to_lowercase();
+    let valid = address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit());
+
+    match valid {
+        true => Ok(address),
+        false => Err(EvmError::Invalid(format!("check_address|address is not valid, address={:?}", value))),
+    }
+}
@@ -0,0 +1,218 @@
+use evm::mock::{mock_timestamp, MockRpcServer, MOCK_ERC721, MOCK_LATEST_BLOCK, MOCK_MARKETPLACE, MOCK_MARKETPLACE_ABI};
+use evm::{keccak256_hex, AbiType, AbiValue, EventSet, EvmError, Log, RpcClient, TRANSFER_BATCH_TOPIC, TRANSFER_SINGLE_TOPIC, TRANSFER_TOPIC};
+
+use num_bigint::BigInt;
+use polars::prelude::{AnyValue, DataFrame, DataType};
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+const SALE_TOPIC: &str = "0x8486c60c22de1eac2bc7e5c4bf16e69b8dacc39d917f6d7db563dfb934f5afa1";
+const MINT_TOPIC: &str = "0x00b70ccc4a63a3ba61e3d60c7b20897017bbeb3a75c35ad63ff83fa259242ad8";
+
+/// an indexed string, a tuple array, bytes, a fixed array and a negative int256
+const LISTED_ABI: &str = r#"{"contractName": "Listings", "abi": [{"type": "event", "name": "Listed", "inputs": [
+    {"name": "slug", "type": "string", "indexed": true},
+    {"name": "items", "type": "tuple[]", "components": [{"name": "collection", "type": "address"}, {"name": "tokenId", "type": "uint256"}]},
+    {"name": "data", "type": "bytes"},
+    {"name": "pair", "type": "uint8[2]"},
+    {"name": "", "type": "int256"}
+]}]}"#;
+
+fn client(server: &MockRpcServer) -> RpcClient {
+    RpcClient::new(&server.url(), Duration::from_secs(5)).unwrap()
+}
+
+fn column(df: &DataFrame, name: &str) -> Vec<String> {
+    let series = df.column(name).unwrap();
+    (0..series.len())
+        .map(|i| match series.get(i) {
+            AnyValue::Utf8(x) => x.to_string(),
+            AnyValue::Null => String::from("null"),
+            x => x.to_string(),
+        })
+        .collect()
+}
+
+fn word(value: &str) -> String {
+    format!("{:0>64}", value)
+}
+
+#[test]
+fn keccak_matches_known_hashes() {
+    assert_eq!(keccak256_hex(""), "0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+    assert_eq!(keccak256_hex("Transfer(address,address,uint256)"), TRANSFER_TOPIC);
+    assert_eq!(keccak256_hex("TransferSingle(address,address,address,uint256,uint256)"), TRANSFER_SINGLE_TOPIC);
+    assert_eq!(keccak256_hex("TransferBatch(address,address,address,uint256[],uint256[])"), TRANSFER_BATCH_TOPIC);
+
+    // padding lands on, and past, the 136 byte block boundary
+    assert_eq!(keccak256_hex(&"a".repeat(135)), "0x34367dc248bbd832f4e3e69dfaac2f92638bd0bbd18f2912ba4ef454919cf446");
+    assert_eq!(keccak256_hex(&"a".repeat(136)), "0xa6c4d403279fe3e0af03729caada8374b5ca54d8065329a3ebcaeb4b60aa386e");
+    assert_eq!(keccak256_hex(&"a".repeat(200)), "0x96ea54061def936c4be90b518992fdc6f12f535068a256229aca54267b4d084d");
+}
+
+#[test]
+fn abi_events_have_canonical_signatures() {
+    let events = EventSet::from_abi(MOCK_MARKETPLACE_ABI, &[]).unwrap();
+    let signatures: Vec<&str> = events.events().iter().map(|x| x.signature.as_str()).collect();
+    assert_eq!(signatures, ["Sale(address,address,(address,uint256),uint256,bytes32)", "Mint(address,uint256,string,uint16[],int32)"]);
+    assert_eq!(events.topics(), [SALE_TOPIC, MINT_TOPIC]);
+
+    assert_eq!(AbiType::parse("uint", &[]).unwrap(), AbiType::Uint(256));
+    assert_eq!(AbiType::parse("int8[][3]", &[]).unwrap().canonical(), "int8[][3]");
+    assert!(AbiType::parse("uint7", &[]).is_err());
+    assert!(AbiType::parse("bytes33", &[]).is_err());
+}
+
+#[test]
+fn function_parameters_keep_their_type_in_the_signature() {
+    let abi = r#"[{"type": "event", "name": "Callback", "inputs": [
+        {"name": "cb", "type": "function", "indexed": false},
+        {"name": "id", "type": "uint256", "indexed": false}
+    ]}]"#;
+    let events = EventSet::from_abi(abi, &[]).unwrap();
+    let callback = &events.events()[0];
+    assert_eq!(callback.signature, "Callback(function,uint256)");
+    assert_eq!(callback.topic0, keccak256_hex("Callback(function,uint256)"));
+    assert_ne!(callback.topic0, keccak256_hex("Callback(bytes24,uint256)"));
+
+    // address + selector, left aligned like bytes24
+    let log = Log {
+        topics: vec![callback.topic0.clone()],
+        data: format!("0x{:0<64}{}", format!("{}{}", "11".repeat(20), "deadbeef"), word("2a")),
+        ..Log::default()
+    };
+    let values = callback.decode(&log).unwrap();
+    assert_eq!(values[0].render(), format!("0x{}deadbeef", "11".repeat(20)));
+    assert_eq!(values[1].render(), "42");
+}
+
+#[test]
+fn events_are_selected_by_name_or_signature() {
+    let events = EventSet::from_abi(MOCK_MARKETPLACE_ABI, &["Mint"]).unwrap();
+    assert_eq!(events.topics(), [MINT_TOPIC]);
+
+    let events = EventSet::from_abi(MOCK_MARKETPLACE_ABI, &["Sale(address,address,(address,uint256),uint256,bytes32)"]).unwrap();
+    assert_eq!(events.topics(), [SALE_TOPIC]);
+
+    // functions are not events
+    assert!(matches!(EventSet::from_abi(MOCK_MARKETPLACE_ABI, &["buy"]), Err(EvmError::Invalid(_))));
+
+    let anonymous = r#"[{"type": "event", "name": "Ping", "anonymous": true, "inputs": []}]"#;
+    assert!(matches!(EventSet::from_abi(anonymous, &[]), Err(EvmError::Invalid(_))));
+
+    let mut config = BTreeMap::new();
+    assert!(EventSet::from_config(&config).unwrap().is_none());
+    config.insert(String::from("evm_abi"), format!("{}/fixtures/marketplace_abi.json", env!("CARGO_MANIFEST_DIR")));
+    config.insert(String::from("evm_events"), String::from("Sale, Mint"));
+    assert_eq!(EventSet::from_config(&config).unwrap().unwrap().topics(), [SALE_TOPIC, MINT_TOPIC]);
+}
+
+#[test]
+fn events_picked_twice_are_decoded_once() {
+    let events = EventSet::from_abi(MOCK_MARKETPLACE_ABI, &["Sale", "Mint", "Sale(address,address,(address,uint256),uint256,bytes32)"]).unwrap();
+    assert_eq!(events.topics(), [SALE_TOPIC, MINT_TOPIC]);
+}
+
+#[test]
+fn colliding_column_names_are_rejected() {
+    let invalid = |abi: &str| match EventSet::from_abi(abi, &[]) {
+        Err(EvmError::Invalid(x)) => x,
+        x => panic!("expected Invalid, got {:?}", x.map(|x| x.topics().len())),
+    };
+
+    let log_column = r#"[{"type": "event", "name": "block", "inputs": [{"name": "number", "type": "uint256", "indexed": false}]}]"#;
+    assert!(invalid(log_column).contains("column block_number"));
+
+    let other_event = r#"[
+        {"type": "event", "name": "Sale", "inputs": [{"name": "item_id", "type": "uint256", "indexed": false}]},
+        {"type": "event", "name": "Sale_item", "inputs": [{"name": "id", "type": "uint256", "indexed": false}]}
+    ]"#;
+    let err = invalid(other_event);
+    assert!(err.contains("column Sale_item_id") && err.contains("Sale(uint256)"), "{}", err);
+}
+
+#[tokio::test]
+async fn marketplace_logs_decode_to_typed_columns() {
+    let server = MockRpcServer::start().unwrap();
+    let client = client(&server);
+    let events = EventSet::from_abi(MOCK_MARKETPLACE_ABI, &[]).unwrap();
+
+    let logs = client.get_logs(MOCK_MARKETPLACE, &events.topics(), 0, MOCK_LATEST_BLOCK).await.unwrap();
+    let df = client.events_frame(&events, &logs).await.unwrap().unwrap();
+    assert_eq!(df.schema(), events.schema());
+
+    assert_eq!(column(&df, "block_number"), ["5000", "5200", "12000"]);
+    assert_eq!(column(&df, "block_timestamp")[1], mock_timestamp(5200).to_string());
+    assert_eq!(column(&df, "event"), ["Sale", "Mint", "Sale"]);
+
+    // indexed addresses, a flattened tuple, uint256 as decimal text
+    assert_eq!(column(&df, "Sale_seller")[0], format!("0x{}", "11".repeat(20)));
+    assert_eq!(column(&df, "Sale_buyer")[0], format!("0x{}", "22".repeat(20)));
+    assert_eq!(column(&df, "Sale_item_collection"), [MOCK_ERC721, "null", MOCK_ERC721]);
+    assert_eq!(column(&df, "Sale_item_tokenId"), ["42", "null", "12345678901234567890123"]);
+    assert_eq!(column(&df, "Sale_price"), ["1500000000000000000", "null", "1606938044258990275541962092341162602522202993782792835301377"]);
+    assert_eq!(column(&df, "Sale_orderHash")[0], format!("0x{}", "ab".repeat(32)));
+
+    assert_eq!(column(&df, "Mint_tokenId"), ["null", "43", "null"]);
+    assert_eq!(column(&df, "Mint_uri")[1], "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi/43");
+    assert_eq!(column(&df, "Mint_score"), ["null", "-7", "null"]);
+
+    let traits: Vec<_> = df.column("Mint_traits").unwrap().list().unwrap().into_iter().collect();
+    assert!(traits[0].is_none());
+    let traits: Vec<Option<u32>> = traits[1].as_ref().unwrap().u32().unwrap().into_iter().collect();
+    assert_eq!(traits, [Some(1), Some(2), Some(300)]);
+
+    let dtype = |name: &str| df.column(name).unwrap().dtype().clone();
+    assert_eq!(dtype("Sale_price"), DataType::Utf8);
+    assert_eq!(dtype("Sale_item_collection"), DataType::Utf8);
+    assert_eq!(dtype("Mint_score"), DataType::Int32);
+    assert_eq!(dtype("Mint_traits"), DataType::List(Box::new(DataType::UInt32)));
+
+    // only the selected events
+    let mints = EventSet::from_abi(MOCK_MARKETPLACE_ABI, &["Mint"]).unwrap();
+    let df = client.events_frame(&mints, &logs).await.unwrap().unwrap();
+    assert_eq!(df.height(), 1);
+    assert!(df.column("Sale_price").is_err());
+}
+
+#[test]
+fn dynamic_types_decode_from_heads_and_tails() {
+    let events = EventSet::from_abi(LISTED_ABI, &[]).unwrap();
+    let listed = &events.events()[0];
+    assert_eq!(listed.signature, "Listed(string,(address,uint256)[],bytes,uint8[2],int256)");
+    assert_eq!(listed.topic0, "0x308b51cacb878c8104c7ad9d8122d8fed11c61a19b675b30f476fd647010623f");
+
+    let slug = keccak256_hex("bayc");
+    let data = [
+        word("a0"), word("140"), word("7"), word("8"), "f".repeat(64),
+        word("2"), word(&"11".repeat(20)), word("5"), word(&"22".repeat(20)), word("6"),
+        word("4"), format!("{:0<64}", "deadbeef"),
+    ];
+    let log = Log {
+        address: String::from("0x0000000000000000000000000000000000000001"),
+        topics: vec![listed.topic0.clone(), slug.clone()],
+        data: format!("0x{}", data.concat()),
+        block_number: Some(String::from("0x1")),
+        log_index: Some(String::from("0x0")),
+        ..Log::default()
+    };
+
+    let values = listed.decode(&log).unwrap();
+    assert_eq!(values[0].render(), slug, "an indexed string is only its hash");
+    assert_eq!(values[1].render(), format!(r#"[["0x{}","5"],["0x{}","6"]]"#, "11".repeat(20), "22".repeat(20)));
+    assert_eq!(values[2].render(), "0xdeadbeef");
+    assert_eq!(values[3].render(), r#"["7","8"]"#);
+    assert_eq!(values[4], AbiValue::Int(BigInt::from(-1)));
+
+    let df = events.to_df(std::slice::from_ref(&log), &HashMap::new()).unwrap();
+    assert_eq!(column(&df, "block_timestamp"), ["null"]);
+    assert_eq!(column(&df, "Listed_slug"), [slug]);
+    assert_eq!(column(&df, "Listed_arg4"), ["-1"]);
+    assert_eq!(df.column("Listed_items").unwrap().dtype(), &DataType::List(Box::new(DataType::Utf8)));
+    assert_eq!(df.column("Listed_pair").unwrap().dtype(), &DataType::List(Box::new(DataType::UInt32)));
+
+    // an offset past the data is an error, not a panic
+    let truncated = Log { data: format!("0x{}", word("ffff")), ..log };
+    assert!(matches!(listed.decode(&truncated), Err(EvmError::Parse(_))));
+}
@@ -0,0 +1,118 @@
+use evm::mock::{MockRpcServer, MOCK_LATEST_BLOCK};
+use evm::{Checkpoint, CheckpointPolicy, EvmError, RpcClient};
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+fn client(server: &MockRpcServer) -> RpcClient {
+    RpcClient::new(&server.url(), Duration::from_secs(5)).unwrap()
+}
+
+fn checkpoint(server: &MockRpcServer, number: u64) -> Checkpoint {
+    Checkpoint { number, hash: server.block_hash(number) }
+}
+
+#[tokio::test]
+async fn first_run_lands_from_start_block_to_the_finalized_block() {
+    let server = MockRpcServer::start().unwrap();
+    let policy = CheckpointPolicy { start_block: 100, ..Default::default() };
+
+    let plan = client(&server).plan_range(&[], &policy).await.unwrap();
+    assert!(!plan.is_reorg());
+    assert_eq!(plan.range(), Some((100, MOCK_LATEST_BLOCK - policy.confirmations)));
+
+    let end = Checkpoint::from_block(plan.end.as_ref().unwrap()).unwrap();
+    assert_eq!(end, checkpoint(&server, MOCK_LATEST_BLOCK - policy.confirmations));
+}
+
+#[tokio::test]
+async fn next_run_starts_after_the_checkpoint() {
+    let server = MockRpcServer::start().unwrap();
+    let policy = CheckpointPolicy { max_blocks: Some(1000), ..Default::default() };
+    let checkpoints = [checkpoint(&server, 5000), checkpoint(&server, 4000)];
+
+    let plan = client(&server).plan_range(&checkpoints, &policy).await.unwrap();
+    assert!(!plan.is_reorg());
+    assert_eq!(plan.canonical, Some(checkpoints[0].clone()));
+    assert_eq!(plan.range(), Some((5001, 6000)));
+}
+
+#[tokio::test]
+async fn nothing_to_land_inside_the_confirmation_depth() {
+    let server = MockRpcServer::start().unwrap();
+    let policy = CheckpointPolicy { confirmations: 10, ..Default::default() };
+    let checkpoints = [checkpoint(&server, MOCK_LATEST_BLOCK - 10)];
+
+    let plan = client(&server).plan_range(&checkpoints, &policy).await.unwrap();
+    assert_eq!(plan.range(), None);
+    assert_eq!(plan.from, MOCK_LATEST_BLOCK - 9);
+}
+
+#[tokio::test]
+async fn reorg_rewinds_to_the_last_canonical_checkpoint() {
+    let server = MockRpcServer::start().unwrap();
+    let checkpoints = [checkpoint(&server, 9000), checkpoint(&server, 8000), checkpoint(&server, 7000)];
+
+    server.reorg(7500);
+    assert_ne!(server.block_hash(8000), checkpoints[1].hash);
+
+    let plan = client(&server).plan_range(&checkpoints, &CheckpointPolicy::default()).await.unwrap();
+    assert!(plan.is_reorg());
+    assert_eq!(plan.superseded, checkpoints[..2]);
+    assert_eq!(plan.canonical, Some(checkpoints[2].clone()));
+    assert_eq!(plan.from, 7001);
+
+    // the new checkpoint carries the post-reorg hash
+    let end = Checkpoint::from_block(plan.end.as_ref().unwrap()).unwrap();
+    assert_eq!(end.hash, server.block_hash(end.number));
+}
+
+#[tokio::test]
+async fn reorg_past_every_checkpoint_is_an_error() {
+    let server = MockRpcServer::start().unwrap();
+    let checkpoints = [checkpoint(&server, 9000), checkpoint(&server, 8000)];
+    server.reorg(10);
+
+    let err = client(&server).plan_range(&checkpoints, &CheckpointPolicy::default()).await.unwrap_err();
+    assert!(matches!(err, EvmError::Invalid(_)), "{}", err);
+    assert_eq!(err.step_status(), "F");
+}
+
+#[tokio::test]
+async fn node_behind_the_checkpoint_is_retryable() {
+    let server = MockRpcServer::start().unwrap();
+    let checkpoints = [
+        Checkpoint { number: MOCK_LATEST_BLOCK + 5, hash: server.block_hash(MOCK_LATEST_BLOCK) },
+        checkpoint(&server, 8000),
+    ];
+
+    let err = client(&server).plan_range(&checkpoints, &CheckpointPolicy::default()).await.unwrap_err();
+    assert!(err.is_retryable(), "{}", err);
+    assert_eq!(err.step_status(), "S");
+}
+
+#[tokio::test]
+async fn logs_carry_the_post_reorg_block_hash() {
+    let server = MockRpcServer::start().unwrap();
+    server.reorg(3000);
+
+    let logs = client(&server).get_logs(evm::mock::MOCK_ERC721, &evm::NFT_TRANSFER_TOPICS, 0, 4000).await.unwrap();
+    let hashes: Vec<String> = logs.iter().map(|x| x.block_hash.clone().unwrap()).collect();
+    assert_eq!(hashes, [server.block_hash(100), server.block_hash(3000)]);
+    assert_ne!(hashes[1], evm::mock::mock_block_hash(3000));
+}
+
+#[test]
+fn policy_from_config() {
+    let mut config = BTreeMap::new();
+    assert_eq!(CheckpointPolicy::from_config(&config).unwrap(), CheckpointPolicy::default());
+
+    config.insert(String::from("evm_confirmations"), String::from("64"));
+    config.insert(String::from("evm_start_block"), String::from("12287507"));
+    config.insert(String::from("evm_max_blocks_per_run"), String::from("50000"));
+    let policy = CheckpointPolicy::from_config(&config).unwrap();
+    assert_eq!((policy.confirmations, policy.start_block, policy.max_blocks), (64, 12287507, Some(50000)));
+
+    config.insert(String::from("evm_max_blocks_per_run"), String::from("0"));
+    assert!(matches!(CheckpointPolicy::from_config(&config), Err(EvmError::Invalid(_))));
+}
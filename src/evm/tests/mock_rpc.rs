@@ -0,0 +1,159 @@
+use evm::mock::{mock_timestamp, MockRpcServer, MOCK_ERC1155, MOCK_ERC20, MOCK_ERC721, MOCK_LATEST_BLOCK, MOCK_MAX_RANGE};
+use evm::{decode_transfers, hex_bytes, quantity, EvmError, Log, RpcClient, NFT_TRANSFER_TOPICS, RPC_BATCH_SIZE, TRANSFER_TOPIC};
+
+use polars::prelude::{AnyValue, DataFrame};
+use serde_json::json;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// blocks mined on the first mock day
+const DAY_END: u64 = 86_400 / 12 - 1;
+
+fn client(server: &MockRpcServer) -> RpcClient {
+    RpcClient::new(&server.url(), Duration::from_secs(5)).unwrap()
+}
+
+fn column(df: &DataFrame, name: &str) -> Vec<String> {
+    let series = df.column(name).unwrap();
+    (0..series.len())
+        .map(|i| match series.get(i) {
+            AnyValue::Utf8(x) => x.to_string(),
+            AnyValue::Null => String::from("null"),
+            x => x.to_string(),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn erc721_transfers_with_timestamps() {
+    let server = MockRpcServer::start().unwrap();
+    let frames = client(&server).nft_transfers(MOCK_ERC721, 0, DAY_END).await.unwrap();
+    assert_eq!(frames.len(), 3, "a frame per chunk with transfers");
+
+    let df = frames.iter().skip(1).fold(frames[0].clone(), |acc, x| acc.vstack(x).unwrap());
+    assert_eq!(column(&df, "block_number"), ["100", "3000", "6000"]);
+    assert_eq!(column(&df, "block_timestamp")[0], mock_timestamp(100).to_string());
+    assert_eq!(column(&df, "standard"), ["erc721"; 3]);
+    assert_eq!(column(&df, "from_address")[0], format!("0x{}", "0".repeat(40)));
+    assert_eq!(column(&df, "token_id"), ["1", "1", "12345678901234567890123"]);
+    assert_eq!(column(&df, "operator"), ["null"; 3]);
+}
+
+#[tokio::test]
+async fn erc1155_batches_fan_out_per_token() {
+    let server = MockRpcServer::start().unwrap();
+    let frames = client(&server).with_max_block_range(10_000).nft_transfers(MOCK_ERC1155, 0, DAY_END).await.unwrap();
+    assert_eq!(frames.len(), 1);
+
+    let df = &frames[0];
+    assert_eq!(column(df, "event"), ["TransferSingle", "TransferBatch", "TransferBatch", "TransferBatch"]);
+    assert_eq!(column(df, "batch_index"), ["0", "0", "1", "2"]);
+    assert_eq!(column(df, "token_id"), ["7", "1", "2", "3"]);
+    assert_eq!(column(df, "amount"), ["3", "10", "20", "30"]);
+    assert_eq!(column(df, "operator")[1], format!("0x{}", "33".repeat(20)));
+}
+
+#[tokio::test]
+async fn wide_ranges_are_split_until_the_node_accepts() {
+    let server = MockRpcServer::start().unwrap();
+    let logs = client(&server).get_logs(MOCK_ERC1155, &NFT_TRANSFER_TOPICS, 0, MOCK_LATEST_BLOCK).await.unwrap();
+    assert_eq!(logs.len(), 3);
+
+    let numbers: Vec<u64> = logs.iter().map(|x| x.block_number().unwrap()).collect();
+    assert_eq!(numbers, [4000, 7100, 9000], "lower halves land first");
+
+    let calls = server.calls_to("eth_getLogs");
+    assert!(calls.len() > 1);
+    let accepted = calls.iter().filter(|x| {
+        let from = quantity(x.params[0]["fromBlock"].as_str().unwrap()).unwrap();
+        let to = quantity(x.params[0]["toBlock"].as_str().unwrap()).unwrap();
+        to - from < MOCK_MAX_RANGE
+    });
+    assert!(accepted.count() >= 4);
+}
+
+#[tokio::test]
+async fn block_at_finds_the_first_block_in_the_window() {
+    let server = MockRpcServer::start().unwrap();
+    let client = client(&server);
+
+    assert_eq!(client.block_at(mock_timestamp(5000)).await.unwrap(), 5000);
+    assert_eq!(client.block_at(mock_timestamp(5000) - 1).await.unwrap(), 5000);
+    assert_eq!(client.block_at(0).await.unwrap(), 0);
+    assert_eq!(client.block_at(mock_timestamp(MOCK_LATEST_BLOCK) + 1).await.unwrap(), MOCK_LATEST_BLOCK + 1);
+}
+
+#[tokio::test]
+async fn timestamps_are_fetched_in_batches() {
+    let server = MockRpcServer::start().unwrap();
+    let numbers: Vec<u64> = (0..150).chain(0..10).collect();
+    let timestamps = client(&server).block_timestamps(&numbers).await.unwrap();
+    assert_eq!(timestamps.len(), 150);
+    assert_eq!(timestamps[&149], mock_timestamp(149));
+
+    let calls = server.calls_to("eth_getBlockByNumber");
+    assert_eq!(calls.len(), 150);
+    assert_eq!(calls.iter().filter(|x| x.request == calls[0].request).count(), RPC_BATCH_SIZE);
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let server = MockRpcServer::start().unwrap();
+    server.fail_next(503, 1);
+    assert_eq!(client(&server).block_number().await.unwrap(), MOCK_LATEST_BLOCK);
+
+    server.fail_next(400, 1);
+    assert!(matches!(client(&server).block_number().await, Err(EvmError::Http(400))));
+}
+
+#[tokio::test]
+async fn rpc_errors_come_back_as_rpc() {
+    let server = MockRpcServer::start().unwrap();
+    match client(&server).call::<String>("eth_mine", json!([])).await {
+        Err(err @ EvmError::Rpc { code: -32601, .. }) => assert!(!err.is_too_large()),
+        x => panic!("expected EvmError::Rpc, got {:?}", x),
+    }
+}
+
+#[test]
+fn erc20_and_removed_logs_are_not_transfers() {
+    let word = |x: &str| format!("0x{:0>64}", x.trim_start_matches("0x"));
+    let erc20 = Log {
+        address: MOCK_ERC20.to_string(),
+        topics: vec![TRANSFER_TOPIC.to_string(), word("11"), word("22")],
+        data: word("64"),
+        block_number: Some("0x1".into()),
+        log_index: Some("0x0".into()),
+        ..Default::default()
+    };
+    assert!(decode_transfers(&erc20).unwrap().is_empty());
+
+    let mut erc721 = erc20.clone();
+    erc721.topics.push(word("5"));
+    assert_eq!(decode_transfers(&erc721).unwrap()[0].token_id, "5");
+
+    erc721.removed = true;
+    assert!(decode_transfers(&erc721).unwrap().is_empty());
+}
+
+#[test]
+fn hex_bytes_rejects_non_hex() {
+    assert_eq!(hex_bytes("0x00ff").unwrap(), [0, 255]);
+    assert!(matches!(hex_bytes("0x0g"), Err(EvmError::Parse(_))));
+    // multibyte chars must not split a byte pair
+    assert!(matches!(hex_bytes("0xa\u{e9}a"), Err(EvmError::Parse(_))));
+}
+
+#[test]
+fn config_needs_an_rpc_url() {
+    let mut config = BTreeMap::new();
+    assert!(matches!(RpcClient::from_config(&config), Err(EvmError::Invalid(_))));
+
+    config.insert(String::from("evm_rpc_url"), String::from("http://127.0.0.1:8545"));
+    config.insert(String::from("evm_max_block_range"), String::from("0"));
+    assert!(matches!(RpcClient::from_config(&config), Err(EvmError::Invalid(_))));
+
+    config.insert(String::from("evm_max_block_range"), String::from("500"));
+    assert_eq!(RpcClient::from_config(&config).unwrap().max_block_range(), 500);
+}
@@ -0,0 +1,145 @@
+//! EVM source: NFT Transfer / TransferSingle / TransferBatch logs of one contract over the window,
+//! or any events of a json ABI (evm_events)
+//! The topic's search_text is the contract address, the window maps to blocks by timestamp
+
+use futures::future::BoxFuture;
+use log::info;
+use polars::frame::DataFrame;
+use polars::prelude::Schema;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ct_nlp::RawSink;
+use ::evm::{block_ranges, check_address, to_quantity, transfers_to_df, EventSet, RpcClient, NFT_TRANSFER_TOPICS};
+
+use crate::{Source, SourceContext, SourceError, SourceTopic, Window};
+
+/// Registry name for NFT transfers
+pub const NFT_TRANSFERS: &str = "evm_nft_transfers";
+
+/// Registry name for events decoded from conf [evm_abi]
+pub const EVENTS: &str = "evm_events";
+
+/// What the logs are decoded as
+#[derive(Debug, Clone)]
+pub enum Decoder {
+    NftTransfers,
+    Events(EventSet),
+}
+
+pub struct EvmSource {
+    client: RpcClient,
+    raw: Option<Arc<RawSink>>,
+    decoder: Decoder,
+}
+
+impl EvmSource {
+    pub fn new(client: RpcClient) -> Self {
+        Self { client, raw: None, decoder: Decoder::NftTransfers }
+    }
+
+    /// Decode the events of an ABI instead of NFT transfers
+    pub fn with_events(mut self, events: EventSet) -> Self {
+        self.decoder = Decoder::Events(events);
+        self
+    }
+
+    /// Client from configuration.yaml, see evm::RpcClient::from_config
+    /// eth_getLogs results go to the raw sink, one page per block range
+    pub fn from_context(context: &SourceContext) -> Result<Self, SourceError> {
+        let client = RpcClient::from_config(context.config)?;
+        Ok(Self { client, raw: context.raw.clone(), decoder: Decoder::NftTransfers })
+    }
+
+    /// from_context decoding the events of conf [evm_abi], conf [evm_events] picks them by name (default every event)
+    pub fn events_from_context(context: &SourceContext) -> Result<Self, SourceError> {
+        let events = EventSet::from_config(context.config)?
+            .ok_or_else(|| SourceError::Invalid(format!("{}|conf [evm_abi] is required", EVENTS)))?;
+        Ok(Self::from_context(context)?.with_events(events))
+    }
+
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    async fn transfers(&self, topic: &SourceTopic, window: &Window) -> Result<Vec<DataFrame>, SourceError> {
+        let address = check_address(&topic.search_text)?;
+
+        // blocks mined in [start, end), end past the head stops at the head
+        let from = self.client.block_at(window.start.timestamp().max(0) as u64).await?;
+        let to = self.client.block_at(window.end.timestamp().max(0) as u64).await?;
+        info!("EvmSource|{}|address={}|window={}|blocks={}..{}", self.name(), address, window, from, to);
+        if to <= from {
+            info!("EvmSource|nothing to land|no blocks in window");
+            return Ok(vec![]);
+        }
+
+        self.blocks(&address, from, to - 1).await
+    }
+
+    /// Decoded logs of one contract over blocks from..=to, a batch per evm_max_block_range chunk with any
+    /// Block based runs (checkpoints) come in here, window based ones through fetch
+    pub async fn blocks(&self, address: &str, from: u64, to: u64) -> Result<Vec<DataFrame>, SourceError> {
+        let address = check_address(address)?;
+        let topics: Vec<&str> = match &self.decoder {
+            Decoder::NftTransfers => NFT_TRANSFER_TOPICS.to_vec(),
+            Decoder::Events(events) => events.topics(),
+        };
+        let mut frames = Vec::new();
+
+        for (start, end) in block_ranges(from, to, self.client.max_block_range()) {
+            let logs = self.client.get_logs(&address, &topics, start, end).await?;
+
+            if let Some(raw) = &self.raw {
+                let body = serde_json::to_string(&logs).map_err(|e| SourceError::Parse(e.to_string()))?;
+                let (from_block, to_block) = (to_quantity(start), to_quantity(end));
+                let query = [("address", address.as_str()), ("fromBlock", from_block.as_str()), ("toBlock", to_block.as_str())];
+                raw.write_page("POST", "eth_getLogs", &query, 200, &body)?;
+            }
+
+            let df = match &self.decoder {
+                Decoder::NftTransfers => self.client.transfers_frame(&logs).await?,
+                Decoder::Events(events) => self.client.events_frame(events, &logs).await?,
+            };
+            if let Some(df) = df {
+                frames.push(df);
+            }
+        }
+
+        Ok(frames)
+    }
+}
+
+impl Source for EvmSource {
+    fn name(&self) -> &str {
+        match self.decoder {
+            Decoder::NftTransfers => NFT_TRANSFERS,
+            Decoder::Events(_) => EVENTS,
+        }
+    }
+
+    /// see evm::transfers_to_df and evm::abi
+    fn schema(&self) -> Schema {
+        match &self.decoder {
+            Decoder::NftTransfers => transfers_to_df(&[], &HashMap::new()).map(|x| x.schema()).unwrap_or_default(),
+            Decoder::Events(events) => events.schema(),
+        }
+    }
+
+    fn fetch<'a>(&'a self, topic: &'a SourceTopic, window: &'a Window) -> BoxFuture<'a, Result<Vec<DataFrame>, SourceError>> {
+        Box::pin(self.transfers(topic, window))
+    }
+}
+
+pub(crate) fn nft_transfers(context: &SourceContext) -> Result<Box<dyn Source>, SourceError> {
+    Ok(Box::new(EvmSource::from_context(context)?))
+}
+
+pub(crate) fn events(context: &SourceContext) -> Result<Box<dyn Source>, SourceError> {
+    Ok(Box::new(EvmSource::events_from_context(context)?))
+}
@@ -0,0 +1,214 @@
+//! Data sources for the landing zone
+//! A Source turns a topic and a time window into record batches with a declared schema,
+//! the land binary runs any source in the Registry by name and writes the batches out
+//! Twitter (ct_nlp) and EVM logs (evm) are implementations, adding another is a new impl and a register call
+
+use futures::future::BoxFuture;
+use log::info;
+use polars::frame::DataFrame;
+use polars::prelude::Schema;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use ct_nlp::{CtNlpError, RawSink};
+use ::evm::EvmError;
+
+pub mod evm;
+pub mod twitter;
+
+pub use self::evm::EvmSource;
+pub use twitter::TwitterSource;
+
+#[derive(Debug)]
+pub enum SourceError {
+    /// unknown source, bad window or configuration, nothing was fetched
+    Invalid(String),
+    /// a batch did not match the declared schema
+    Schema(String),
+    /// batch could not be shaped or written
+    Parse(String),
+    Twitter(CtNlpError),
+    Evm(EvmError),
+}
+
+impl SourceError {
+    /// Nothing to land, the step can complete without output
+    pub fn is_skippable(&self) -> bool {
+        match self {
+            SourceError::Twitter(e) => e.is_skippable(),
+            _ => false,
+        }
+    }
+
+    /// job_step status a landing step should record for this error
+    /// S re-queues the step, C completes it, F fails it
+    pub fn step_status(&self) -> &'static str {
+        match self {
+            SourceError::Twitter(e) => e.step_status(),
+            SourceError::Evm(e) => e.step_status(),
+            _ => "F",
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Invalid(x) => write!(f, "ERR: invalid input|{}", x),
+            SourceError::Schema(x) => write!(f, "ERR: schema mismatch|{}", x),
+            SourceError::Parse(x) => write!(f, "ERR: unable to shape batch|{}", x),
+            SourceError::Twitter(e) => write!(f, "twitter|{}", e),
+            SourceError::Evm(e) => write!(f, "evm|{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+impl From<CtNlpError> for SourceError {
+    fn from(e: CtNlpError) -> Self {
+        SourceError::Twitter(e)
+    }
+}
+
+impl From<EvmError> for SourceError {
+    fn from(e: EvmError) -> Self {
+        SourceError::Evm(e)
+    }
+}
+
+impl From<polars::error::PolarsError> for SourceError {
+    fn from(e: polars::error::PolarsError) -> Self {
+        SourceError::Parse(e.to_string())
+    }
+}
+
+/// The topic row a source works from
+#[derive(Debug, Clone, Default)]
+pub struct SourceTopic {
+    pub topic_id: i32,
+    pub search_text: String,
+    /// structured query json, see ct_nlp::SearchQuery
+    pub search_query: Option<String>,
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Half-open time window [start, end)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Window {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Self, SourceError> {
+        if start >= end {
+            return Err(SourceError::Invalid(format!("Window|start must be before end|start={}|end={}", start, end)));
+        }
+        Ok(Self { start, end })
+    }
+
+    /// [start_date, end_date) at midnight UTC
+    pub fn days(start_date: NaiveDate, end_date: NaiveDate) -> Result<Self, SourceError> {
+        Self::new(midnight(start_date), midnight(end_date))
+    }
+
+    /// The previous UTC day, the default landing window
+    pub fn yesterday() -> Self {
+        let today = Utc::now().date_naive();
+        Self { start: midnight(today - Duration::days(1)), end: midnight(today) }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start.to_rfc3339(), self.end.to_rfc3339())
+    }
+}
+
+/// Something that can be landed for a topic
+pub trait Source: Send + Sync {
+    /// Registry name, also used in landing file names
+    fn name(&self) -> &str;
+
+    /// Columns every batch carries, names and types in order
+    fn schema(&self) -> Schema;
+
+    /// Record batches for topic over window, empty when there is nothing to land
+    fn fetch<'a>(&'a self, topic: &'a SourceTopic, window: &'a Window) -> BoxFuture<'a, Result<Vec<DataFrame>, SourceError>>;
+}
+
+/// Check a batch against a declared schema, column order included
+pub fn check_schema(schema: &Schema, batch: &DataFrame) -> Result<(), SourceError> {
+    let actual = batch.schema();
+    let expected: Vec<(&String, &polars::prelude::DataType)> = schema.iter().collect();
+    let found: Vec<(&String, &polars::prelude::DataType)> = actual.iter().collect();
+
+    if expected != found {
+        return Err(SourceError::Schema(format!("expected={:?}|found={:?}", expected, found)));
+    }
+    Ok(())
+}
+
+/// What a source factory gets to build from
+pub struct SourceContext<'a> {
+    pub config: &'a BTreeMap<String, String>,
+    /// untouched responses land here, for sources that keep them
+    pub raw: Option<Arc<RawSink>>,
+}
+
+/// Builds a source from configuration.yaml, so only the source that runs needs its credentials
+pub type SourceFactory = fn(&SourceContext) -> Result<Box<dyn Source>, SourceError>;
+
+/// Sources the land binary can run, by name
+pub struct Registry {
+    factories: BTreeMap<&'static str, SourceFactory>,
+}
+
+impl Registry {
+    /// Registry without any source
+    pub fn empty() -> Self {
+        Self { factories: BTreeMap::new() }
+    }
+
+    /// Every source that ships with honey-faucet
+    pub fn builtin() -> Self {
+        let mut registry = Self::empty();
+        registry.register(twitter::RECENT, twitter::recent);
+        registry.register(twitter::ALL, twitter::all);
+        registry.register(self::evm::NFT_TRANSFERS, self::evm::nft_transfers);
+        registry.register(self::evm::EVENTS, self::evm::events);
+        registry
+    }
+
+    /// Add or replace a source
+    pub fn register(&mut self, name: &'static str, factory: SourceFactory) {
+        self.factories.insert(name, factory);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.factories.keys().copied().collect()
+    }
+
+    /// Build the source registered as name
+    pub fn build(&self, name: &str, context: &SourceContext) -> Result<Box<dyn Source>, SourceError> {
+        let factory = self.factories.get(name)
+            .ok_or_else(|| SourceError::Invalid(format!("Registry|unknown source={}|expected one of {}", name, self.names().join(", "))))?;
+
+        info!("Registry|building source={}", name);
+        factory(context)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
@@ -0,0 +1,83 @@
+use evm::mock::{MockRpcServer, MOCK_ERC1155, MOCK_ERC721, MOCK_MARKETPLACE};
+use source::{check_schema, Registry, SourceContext, SourceError, SourceTopic, Window};
+
+use chrono::NaiveDate;
+use polars::prelude::AnyValue;
+
+use std::collections::BTreeMap;
+
+fn config(server: &MockRpcServer) -> BTreeMap<String, String> {
+    let mut config = BTreeMap::new();
+    config.insert(String::from("evm_rpc_url"), server.url());
+    config
+}
+
+fn contract(address: &str) -> SourceTopic {
+    SourceTopic { topic_id: 1, search_text: address.to_string(), search_query: None }
+}
+
+fn day(value: &str) -> Window {
+    let start = NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+    Window::days(start, start.succ_opt().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn nft_transfers_land_the_window() {
+    let server = MockRpcServer::start().unwrap();
+    let config = config(&server);
+    let source = Registry::builtin().build("evm_nft_transfers", &SourceContext { config: &config, raw: None }).unwrap();
+
+    // the mock chain starts 2022-05-22, the second day only has one TransferSingle
+    let batches = source.fetch(&contract(MOCK_ERC1155), &day("2022-05-22")).await.unwrap();
+    assert_eq!(batches.iter().map(|x| x.height()).sum::<usize>(), 4);
+    for batch in &batches {
+        check_schema(&source.schema(), batch).unwrap();
+    }
+
+    let batches = source.fetch(&contract(MOCK_ERC1155), &day("2022-05-23")).await.unwrap();
+    assert_eq!(batches.iter().map(|x| x.height()).sum::<usize>(), 1);
+
+    let batches = source.fetch(&contract(MOCK_ERC721), &day("2022-05-24")).await.unwrap();
+    assert!(batches.is_empty());
+}
+
+#[tokio::test]
+async fn search_text_must_be_a_contract_address() {
+    let server = MockRpcServer::start().unwrap();
+    let config = config(&server);
+    let source = Registry::builtin().build("evm_nft_transfers", &SourceContext { config: &config, raw: None }).unwrap();
+
+    match source.fetch(&contract("boredapes"), &day("2022-05-22")).await {
+        Err(SourceError::Evm(e)) => assert_eq!(e.step_status(), "F"),
+        _ => panic!("expected SourceError::Evm"),
+    }
+}
+
+#[tokio::test]
+async fn abi_events_land_the_window() {
+    let server = MockRpcServer::start().unwrap();
+    let mut config = config(&server);
+    let registry = Registry::builtin();
+
+    // evm_events needs an abi
+    match registry.build("evm_events", &SourceContext { config: &config, raw: None }) {
+        Err(SourceError::Invalid(_)) => (),
+        _ => panic!("expected SourceError::Invalid"),
+    }
+
+    config.insert(String::from("evm_abi"), format!("{}/../evm/fixtures/marketplace_abi.json", env!("CARGO_MANIFEST_DIR")));
+    config.insert(String::from("evm_events"), String::from("Sale"));
+    let source = registry.build("evm_events", &SourceContext { config: &config, raw: None }).unwrap();
+    assert_eq!(source.name(), "evm_events");
+
+    // one Sale on each of the first two days, the Mint is not selected
+    let batches = source.fetch(&contract(MOCK_MARKETPLACE), &day("2022-05-22")).await.unwrap();
+    assert_eq!(batches.iter().map(|x| x.height()).sum::<usize>(), 1);
+    for batch in &batches {
+        check_schema(&source.schema(), batch).unwrap();
+        assert_eq!(batch.column("Sale_item_tokenId").unwrap().get(0), AnyValue::Utf8("42"));
+    }
+
+    let batches = source.fetch(&contract(MOCK_MARKETPLACE), &day("2022-05-23")).await.unwrap();
+    assert_eq!(batches.iter().map(|x| x.height()).sum::<usize>(), 1);
+}
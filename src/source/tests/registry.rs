@@ -0,0 +1,103 @@
+use ct_nlp::mock::{MockServer, MOCK_ARCHIVE_DATE, MOCK_BEARER_TOKEN};
+use source::{check_schema, Registry, Source, SourceContext, SourceError, SourceTopic, Window};
+
+use chrono::NaiveDate;
+use futures::future::BoxFuture;
+use polars::prelude::{DataFrame, DataType, Field, NamedFrom, Schema, Series};
+
+use std::collections::BTreeMap;
+
+fn config(server: &MockServer) -> BTreeMap<String, String> {
+    let mut config = BTreeMap::new();
+    config.insert(String::from("bearer_token"), String::from(MOCK_BEARER_TOKEN));
+    config.insert(String::from("twitter_base_url"), server.url());
+    config
+}
+
+fn topic(search_text: &str) -> SourceTopic {
+    SourceTopic { topic_id: 1, search_text: search_text.to_string(), search_query: None }
+}
+
+fn day(value: &str) -> Window {
+    let start = NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap();
+    Window::days(start, start.succ_opt().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn twitter_all_lands_the_window() {
+    let server = MockServer::start().unwrap();
+    let config = config(&server);
+    let source = Registry::builtin().build("twitter_all", &SourceContext { config: &config, raw: None }).unwrap();
+    assert_eq!(source.name(), "twitter_all");
+
+    let batches = source.fetch(&topic("nft"), &day(MOCK_ARCHIVE_DATE)).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert!(batches[0].height() > 0);
+    check_schema(&source.schema(), &batches[0]).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/2/tweets/search/all");
+    assert_eq!(requests[0].param("start_time"), Some(format!("{}T00:00:00Z", MOCK_ARCHIVE_DATE).as_str()));
+}
+
+#[tokio::test]
+async fn empty_window_has_no_batches() {
+    let server = MockServer::start().unwrap();
+    let config = config(&server);
+    let source = Registry::builtin().build("twitter_all", &SourceContext { config: &config, raw: None }).unwrap();
+
+    let batches = source.fetch(&topic("nft"), &day("2020-01-01")).await.unwrap();
+    assert!(batches.is_empty());
+}
+
+#[test]
+fn unknown_source_is_invalid() {
+    let config = BTreeMap::new();
+    match Registry::builtin().build("myspace", &SourceContext { config: &config, raw: None }) {
+        Err(SourceError::Invalid(x)) => assert!(x.contains("twitter_recent")),
+        _ => panic!("expected SourceError::Invalid"),
+    }
+}
+
+#[test]
+fn batches_must_match_the_schema() {
+    let schema = Schema::from(vec![Field::new("id", DataType::Utf8), Field::new("n", DataType::UInt64)]);
+
+    let ok = DataFrame::new(vec![Series::new("id", &["a"]), Series::new("n", &[1u64])]).unwrap();
+    check_schema(&schema, &ok).unwrap();
+
+    let reordered = DataFrame::new(vec![Series::new("n", &[1u64]), Series::new("id", &["a"])]).unwrap();
+    assert!(matches!(check_schema(&schema, &reordered), Err(SourceError::Schema(_))));
+
+    let retyped = DataFrame::new(vec![Series::new("id", &["a"]), Series::new("n", &[1i64])]).unwrap();
+    assert!(matches!(check_schema(&schema, &retyped), Err(SourceError::Schema(_))));
+}
+
+/// A source outside the builtins, one row per topic
+struct Constant;
+
+impl Source for Constant {
+    fn name(&self) -> &str {
+        "constant"
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::from(vec![Field::new("topic_id", DataType::Int32)])
+    }
+
+    fn fetch<'a>(&'a self, topic: &'a SourceTopic, _window: &'a Window) -> BoxFuture<'a, Result<Vec<DataFrame>, SourceError>> {
+        Box::pin(async move { Ok(vec![DataFrame::new(vec![Series::new("topic_id", &[topic.topic_id])])?]) })
+    }
+}
+
+#[tokio::test]
+async fn new_sources_register_by_name() {
+    let mut registry = Registry::builtin();
+    registry.register("constant", |_| Ok(Box::new(Constant)));
+    assert!(registry.names().contains(&"constant"));
+
+    let config = BTreeMap::new();
+    let source = registry.build("constant", &SourceContext { config: &config, raw: None }).unwrap();
+    let batches = source.fetch(&topic("ignored"), &Window::yesterday()).await.unwrap();
+    check_schema(&source.schema(), &batches[0]).unwrap();
+}
@@ -0,0 +1,102 @@
+//! Twitter sources: recent and full-archive search over the window, one batch of tweets
+//! The topic's search_query wins over search_text, see ct_nlp::topic_query
+
+use futures::future::BoxFuture;
+use log::info;
+use polars::frame::DataFrame;
+use polars::prelude::Schema;
+
+use chrono::{Duration, SecondsFormat, Utc};
+
+use ct_nlp::{topic_query, tweets_to_df, PageLimit, QueryOptions, SearchScope, TwitterClient};
+
+use crate::{Source, SourceContext, SourceError, SourceTopic, Window};
+
+/// Registry name for recent search, the last 7 days
+pub const RECENT: &str = "twitter_recent";
+/// Registry name for full-archive search
+pub const ALL: &str = "twitter_all";
+
+/// recent search rejects an end_time closer to now than this
+const END_TIME_MARGIN: i64 = 30;
+
+pub struct TwitterSource {
+    client: TwitterClient,
+    scope: SearchScope,
+    limit: PageLimit,
+}
+
+impl TwitterSource {
+    pub fn new(client: TwitterClient, scope: SearchScope, limit: PageLimit) -> Self {
+        Self { client, scope, limit }
+    }
+
+    /// Client from configuration.yaml, paging from conf [max_pages] / [max_rows]
+    pub fn from_context(context: &SourceContext, scope: SearchScope) -> Result<Self, SourceError> {
+        let mut client = TwitterClient::from_config(context.config)?;
+        if let Some(raw) = &context.raw {
+            client = client.with_raw_sink(raw.clone());
+        }
+
+        let limit = PageLimit {
+            max_pages: context.config.get("max_pages").and_then(|x| x.parse().ok()),
+            max_rows: context.config.get("max_rows").and_then(|x| x.parse().ok()),
+        };
+
+        Ok(Self::new(client, scope, limit))
+    }
+
+    async fn search(&self, topic: &SourceTopic, window: &Window) -> Result<Vec<DataFrame>, SourceError> {
+        let query = topic_query(&topic.search_text, topic.search_query.as_deref(), self.scope)?;
+
+        let end = match self.scope {
+            SearchScope::Recent => window.end.min(Utc::now() - Duration::seconds(END_TIME_MARGIN)),
+            SearchScope::All => window.end,
+        };
+        let options = QueryOptions::window(
+            &window.start.to_rfc3339_opts(SecondsFormat::Secs, true),
+            &end.to_rfc3339_opts(SecondsFormat::Secs, true),
+        );
+        info!("TwitterSource|{}|query={}|options={:?}", self.name(), query, options);
+
+        let result = match self.scope {
+            SearchScope::Recent => self.client.get_recent_tweets(&query, &options, self.limit).await,
+            SearchScope::All => self.client.search_all(&query, &options, self.limit).await,
+        };
+
+        match result {
+            Ok(df) => Ok(vec![df]),
+            Err(err) if err.is_skippable() => {
+                info!("TwitterSource|nothing to land|{}", err);
+                Ok(vec![])
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Source for TwitterSource {
+    fn name(&self) -> &str {
+        match self.scope {
+            SearchScope::Recent => RECENT,
+            SearchScope::All => ALL,
+        }
+    }
+
+    /// see ct_nlp::tweets_to_df
+    fn schema(&self) -> Schema {
+        tweets_to_df(&[], &[]).map(|x| x.schema()).unwrap_or_default()
+    }
+
+    fn fetch<'a>(&'a self, topic: &'a SourceTopic, window: &'a Window) -> BoxFuture<'a, Result<Vec<DataFrame>, SourceError>> {
+        Box::pin(self.search(topic, window))
+    }
+}
+
+pub(crate) fn recent(context: &SourceContext) -> Result<Box<dyn Source>, SourceError> {
+    Ok(Box::new(TwitterSource::from_context(context, SearchScope::Recent)?))
+}
+
+pub(crate) fn all(context: &SourceContext) -> Result<Box<dyn Source>, SourceError> {
+    Ok(Box::new(TwitterSource::from_context(context, SearchScope::All)?))
+}
@@ -0,0 +1,3 @@
+\c prod;
+
+ALTER TABLE topic ADD COLUMN IF NOT EXISTS search_query TEXT;
@@ -0,0 +1,28 @@
+\c prod;
+
+CREATE TABLE IF NOT EXISTS chain_checkpoint (
+    id SERIAL PRIMARY KEY,
+    topic_id INTEGER REFERENCES topic (id) NOT NULL,
+    flow_step_id INTEGER REFERENCES flow_step (id) NOT NULL,
+    contract_address VARCHAR(42) NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    created_dt TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    UNIQUE (topic_id, flow_step_id, contract_address, block_number)
+);
+
+CREATE TABLE IF NOT EXISTS landing_catalog (
+    id SERIAL PRIMARY KEY,
+    topic_id INTEGER REFERENCES topic (id) NOT NULL,
+    flow_step_id INTEGER REFERENCES flow_step (id) NOT NULL,
+    job_step_id INTEGER REFERENCES job_step (id) NOT NULL,
+    file_path VARCHAR(512) NOT NULL,
+    row_count BIGINT NOT NULL,
+    contract_address VARCHAR(42),
+    from_block BIGINT,
+    to_block BIGINT,
+    is_valid BOOLEAN NOT NULL DEFAULT TRUE,
+    invalid_reason VARCHAR(256),
+    created_dt TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_dt TIMESTAMP WITHOUT TIME ZONE
+);